@@ -0,0 +1,144 @@
+//! Checks a history recorded outside of Stateright, such as by a test harness, for consistency.
+//! See the `stateright::semantics::history` module documentation for the JSON format.
+
+use stateright::semantics::history::{Event, History};
+use stateright::semantics::key_value::KeyValue;
+use stateright::semantics::register::Register;
+use stateright::semantics::{
+    ConsistencyTester, LinearizabilityTester, PartitionedSpec, SequentialConsistencyTester,
+    SequentialSpec,
+};
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+type Process = u64;
+
+/// Checks a history and prints a minimal inconsistent subhistory if there is one.
+fn check<RefObj, C>(
+    history: &History<Process, RefObj::Op, RefObj::Ret>,
+    tester: C,
+) -> Result<bool, String>
+where
+    RefObj: SequentialSpec,
+    RefObj::Op: Clone + serde::Serialize,
+    RefObj::Ret: Clone + serde::Serialize,
+    C: Clone + ConsistencyTester<(Process, usize), RefObj>,
+{
+    match history.minimal_inconsistent_subhistory(tester)? {
+        None => {
+            println!("Consistent.");
+            Ok(true)
+        }
+        Some(subhistory) => {
+            println!("Inconsistent. Minimal inconsistent subhistory:");
+            for event in subhistory.0 {
+                println!("{}", serde_json::to_string(&event).unwrap());
+            }
+            Ok(false)
+        }
+    }
+}
+
+fn check_spec<RefObj>(consistency: &str, json: &str, init_ref_obj: RefObj) -> Result<bool, String>
+where
+    RefObj: Clone + Debug + std::hash::Hash + PartialEq + SequentialSpec,
+    RefObj::Op: Clone + Debug + serde::Serialize + serde::de::DeserializeOwned,
+    RefObj::Ret: Clone + Debug + PartialEq + serde::Serialize + serde::de::DeserializeOwned,
+{
+    let history = History::from_json(json)?;
+    match consistency {
+        "linearizable" => check(&history, LinearizabilityTester::new(init_ref_obj)),
+        "sequential" => check(&history, SequentialConsistencyTester::new(init_ref_obj)),
+        _ => Err(format!("Unknown consistency model: {}", consistency)),
+    }
+}
+
+/// Checks the linearizability of each partition separately, which is much faster than checking
+/// the whole history, and then only minimizes the operations of a partition that failed.
+fn check_partitions<RefObj>(json: &str, init_ref_obj: RefObj) -> Result<bool, String>
+where
+    RefObj: Clone + Debug + std::hash::Hash + PartialEq + PartitionedSpec,
+    RefObj::Op: Clone + Debug + serde::Serialize + serde::de::DeserializeOwned,
+    RefObj::Ret: Clone + Debug + PartialEq + serde::Serialize + serde::de::DeserializeOwned,
+{
+    let history: History<Process, RefObj::Op, RefObj::Ret> = History::from_json(json)?;
+    let mut tester = LinearizabilityTester::new(init_ref_obj.clone());
+    tester.on_history(history.tester_events()?)?;
+    if tester.serialized_history_by_partition().is_some() {
+        println!("Consistent.");
+        return Ok(true);
+    }
+
+    for (key, partition) in tester.partitions() {
+        if partition.serialized_history().is_some() {
+            continue;
+        }
+        // Completions are attributed to the partition of the process's latest invocation.
+        let mut key_by_process = BTreeMap::new();
+        let events = history
+            .0
+            .iter()
+            .filter(|event| match event {
+                Event::Invoke { process, op } => {
+                    let op_key = RefObj::partition(op);
+                    let is_kept = op_key == key;
+                    key_by_process.insert(*process, op_key);
+                    is_kept
+                }
+                _ => key_by_process.get(event.process()) == Some(&key),
+            })
+            .cloned()
+            .collect();
+        return check(&History(events), LinearizabilityTester::new(init_ref_obj));
+    }
+    check(&history, LinearizabilityTester::new(init_ref_obj))
+}
+
+fn main() -> Result<(), pico_args::Error> {
+    let mut args = pico_args::Arguments::from_env();
+    let consistency: Option<String> = args.opt_free_from_str()?;
+    let spec: Option<String> = args.opt_free_from_str()?;
+    let path: Option<String> = args.opt_free_from_str()?;
+    let (consistency, spec, path) = match (consistency, spec, path) {
+        (Some(consistency), Some(spec), Some(path)) => (consistency, spec, path),
+        _ => {
+            println!("USAGE:");
+            println!("  ./check-history linearizable|sequential register|key-value PATH");
+            println!();
+            println!("A register history has operations such as {{\"Write\": 1}} and \"Read\",");
+            println!("and returns such as \"WriteOk\" and {{\"ReadOk\": 1}}, where reading null");
+            println!("indicates the initial value.");
+            println!();
+            println!("A key-value history has operations such as {{\"Put\": [\"x\", 1]}} and");
+            println!("{{\"Get\": \"x\"}}, and returns such as \"PutOk\" and {{\"GetOk\": 1}}.");
+            return Ok(());
+        }
+    };
+
+    let json = match std::fs::read_to_string(&path) {
+        Ok(json) => json,
+        Err(err) => {
+            eprintln!("Unable to read {}: {}", path, err);
+            std::process::exit(2);
+        }
+    };
+    let result = match spec.as_str() {
+        "register" => check_spec::<Register<Option<i64>>>(&consistency, &json, Register(None)),
+        "key-value" if consistency == "linearizable" => {
+            check_partitions::<KeyValue<String, i64>>(&json, KeyValue::default())
+        }
+        "key-value" => {
+            check_spec::<KeyValue<String, i64>>(&consistency, &json, KeyValue::default())
+        }
+        _ => Err(format!("Unknown spec: {}", spec)),
+    };
+    match result {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(1),
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    }
+    Ok(())
+}
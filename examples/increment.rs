@@ -106,7 +106,7 @@
 
 use stateright::{report::WriteReporter, *};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Hash)]
 pub enum Action {
     /// A specified thread reads from the shared state into its local state.
     Read(usize),
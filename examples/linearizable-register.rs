@@ -410,6 +410,8 @@ fn main() -> Result<(), pico_args::Error> {
                     ),
                 ],
             )
+            .unwrap()
+            .join()
             .unwrap();
         }
         _ => {
@@ -246,6 +246,8 @@ pub fn main() -> Result<(), pico_args::Error> {
                     ),
                 ],
             )
+            .unwrap()
+            .join()
             .unwrap();
         }
         _ => {
@@ -490,6 +490,8 @@ fn main() -> Result<(), pico_args::Error> {
                     ),
                 ],
             )
+            .unwrap()
+            .join()
             .unwrap();
         }
         _ => {
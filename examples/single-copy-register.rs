@@ -216,6 +216,8 @@ fn main() -> Result<(), pico_args::Error> {
                     SingleCopyActor,
                 )],
             )
+            .unwrap()
+            .join()
             .unwrap();
         }
         _ => {
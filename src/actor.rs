@@ -1,5 +1,13 @@
 //! This module provides an [Actor] trait, which can be model checked using [`ActorModel`].  You
-//! can also [`spawn()`] the actor in which case it will communicate over a UDP socket.
+//! can also [`spawn()`] the actor in which case it will communicate over a UDP socket, or
+//! [`spawn_tcp()`] it to communicate over TCP with ordered delivery. Other [`Transport`]s, such
+//! as the in-process [`ChannelNetwork`], are supported via [`spawn_with_transport()`].
+//! Spawned actors are addressed by the IPv4 address and port encoded in their [`Id`]s unless an
+//! [`AddressBook`] maps them to IPv6 addresses or hostnames.
+//! [`FaultConfig`] wraps a transport to inject network faults into a spawned system, and a
+//! [`TraceRecorder`] captures its steps for [`ActorModel::validate_trace`].
+//! A [`Simulator`] runs actors deterministically against a virtual clock. With the
+//! `tokio` feature enabled, `spawn_async()` instead runs each actor as a task.
 //!
 //! ## Example
 //!
@@ -83,11 +91,18 @@
 //! are available in the repository.
 
 use choice::{Choice, Never};
+mod address_book;
+mod fault;
 mod model;
 mod model_state;
 mod network;
+mod simulator;
 mod spawn;
+#[cfg(feature = "tokio")]
+mod spawn_async;
 mod timers;
+mod trace;
+mod transport;
 use std::borrow::Cow;
 use std::fmt::{Debug, Display, Formatter};
 use std::hash::Hash;
@@ -97,14 +112,21 @@ use std::time::Duration;
 
 #[cfg(test)]
 pub mod actor_test_util;
+pub use address_book::*;
+pub use fault::*;
 pub use model::*;
 pub use model_state::*;
 pub use network::*;
 pub use timers::*;
+pub use trace::*;
+pub use transport::*;
 pub mod ordered_reliable_link;
 pub mod register;
 pub mod write_once_register;
+pub use simulator::*;
 pub use spawn::*;
+#[cfg(feature = "tokio")]
+pub use spawn_async::*;
 
 /// Uniquely identifies an [`Actor`]. Encodes the socket address for spawned
 /// actors. Encodes an index for model checked actors.
@@ -167,6 +189,8 @@ pub enum Command<Msg, Timer, Random> {
     Send(Id, Msg),
     /// Choose a random
     ChooseRandom(String, Vec<Random>),
+    /// Persist the actor's state, which survives a crash. See [`Actor::on_restart`].
+    Persist,
 }
 
 /// Holds [`Command`]s output by an actor.
@@ -236,6 +260,14 @@ impl<A: Actor> Out<A> {
     pub fn remove_random(&mut self, key: impl Into<String>) {
         self.0.push(Command::ChooseRandom(key.into(), vec![]));
     }
+
+    /// Records the need to persist the actor's state, such as when flushing it to disk. The
+    /// state resulting from the current event is what gets persisted, and the most recently
+    /// persisted state is passed to [`Actor::on_restart`] if the actor later crashes and
+    /// recovers. State that was never persisted is lost upon a crash.
+    pub fn persist(&mut self) {
+        self.0.push(Command::Persist);
+    }
 }
 
 impl<A: Actor> Debug for Out<A> {
@@ -383,6 +415,19 @@ pub trait Actor: Sized {
         let _ = o;
     }
 
+    /// Indicates the state and commands when an actor restarts after a crash. `durable_state` is
+    /// the state most recently persisted via [`Out::persist`], if any. Defaults to
+    /// [`Actor::on_start`], which discards the durable state.
+    fn on_restart(
+        &self,
+        id: Id,
+        durable_state: Option<&Self::State>,
+        o: &mut Out<Self>,
+    ) -> Self::State {
+        let _ = durable_state;
+        self.on_start(id, o)
+    }
+
     fn name(&self) -> String {
         String::new()
     }
@@ -443,6 +488,20 @@ where
         }
     }
 
+    fn on_restart(
+        &self,
+        id: Id,
+        durable_state: Option<&Self::State>,
+        o: &mut Out<Self>,
+    ) -> Self::State {
+        let actor = self.get();
+        let mut o_prime = Out::new();
+        let state = actor.on_restart(id, durable_state.map(|s| s.get()), &mut o_prime);
+
+        o.append(&mut o_prime);
+        Choice::new(state)
+    }
+
     fn name(&self) -> String {
         self.get().name()
     }
@@ -539,6 +598,37 @@ where
         }
     }
 
+    fn on_restart(
+        &self,
+        id: Id,
+        durable_state: Option<&Self::State>,
+        o: &mut Out<Self>,
+    ) -> Self::State {
+        match (self, durable_state) {
+            (Choice::L(actor), None | Some(Choice::L(_))) => {
+                let durable_state = durable_state.map(|s| match s {
+                    Choice::L(s) => s,
+                    Choice::R(_) => unreachable!(),
+                });
+                let mut o_prime = Out::new();
+                let state = actor.on_restart(id, durable_state, &mut o_prime);
+                o.append(&mut o_prime);
+                Choice::L(state)
+            }
+            (Choice::R(actor), None | Some(Choice::R(_))) => {
+                let durable_state = durable_state.map(|s| match s {
+                    Choice::L(_) => unreachable!(),
+                    Choice::R(s) => s,
+                });
+                let mut o_prime = Out::new();
+                let state = actor.on_restart(id, durable_state, &mut o_prime);
+                o.append(&mut o_prime);
+                Choice::R(state)
+            }
+            _ => unreachable!(),
+        }
+    }
+
     fn name(&self) -> String {
         match self {
             Choice::L(a) => a.name(),
@@ -0,0 +1,72 @@
+//! Private module for selective re-export.
+
+use crate::actor::Id;
+use std::collections::HashMap;
+use std::io::{self, ErrorKind};
+use std::net::{SocketAddr, SocketAddrV4, ToSocketAddrs};
+
+/// Maps actor [`Id`]s to network addresses that an [`Id`] cannot encode, such as IPv6 addresses
+/// and hostnames. An [`Id`] without an entry falls back to the IPv4 address and port encoded
+/// within it. Only spawned actors consult the address book, so [`Id`]s behave the same in a
+/// model regardless of the addresses they map to.
+///
+/// # Example
+///
+/// ```no_run
+/// use stateright::actor::{AddressBook, Id, UdpTransport, spawn_with_transport};
+/// # mod serde_json {
+/// #     pub fn to_vec(_: &()) -> Result<Vec<u8>, ()> { Ok(vec![]) }
+/// #     pub fn from_slice(_: &[u8]) -> Result<(), ()> { Ok(()) }
+/// # }
+/// # let actor1 = ();
+/// # let actor2 = ();
+/// let addresses = AddressBook::new()
+///     .address(Id::from(0), "[::1]:3001")
+///     .address(Id::from(1), "node1.example.com:3001");
+/// spawn_with_transport(
+///     |id| UdpTransport::bind_with(id, addresses.clone()),
+///     serde_json::to_vec,
+///     |bytes| serde_json::from_slice(bytes),
+///     vec![
+///         (Id::from(0), actor1),
+///         (Id::from(1), actor2),
+///     ]).unwrap().join().unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct AddressBook {
+    addresses: HashMap<Id, String>,
+}
+
+impl AddressBook {
+    /// Constructs an empty address book, in which every [`Id`] maps to the IPv4 address and port
+    /// that it encodes.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Maps an [`Id`] to an address of the form `host:port`, where the host is an IP address or a
+    /// hostname. IPv6 addresses must be enclosed in brackets.
+    pub fn address(mut self, id: impl Into<Id>, address: impl Into<String>) -> Self {
+        self.addresses.insert(id.into(), address.into());
+        self
+    }
+
+    /// Resolves the socket address of the actor with the specified [`Id`], looking up hostnames
+    /// as needed. A hostname that resolves to several addresses maps to the first.
+    pub fn resolve(&self, id: Id) -> io::Result<SocketAddr> {
+        match self.addresses.get(&id) {
+            None => Ok(SocketAddrV4::from(id).into()),
+            Some(address) => address.to_socket_addrs()?.next().ok_or_else(|| {
+                io::Error::new(
+                    ErrorKind::AddrNotAvailable,
+                    format!("{} has no addresses", address),
+                )
+            }),
+        }
+    }
+
+    /// Iterates through the [`Id`]s that have an entry.
+    pub fn ids(&self) -> impl Iterator<Item = Id> + '_ {
+        self.addresses.keys().copied()
+    }
+}
@@ -0,0 +1,297 @@
+//! Private module for selective re-export.
+
+use crate::actor::{Id, Transport};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeSet;
+use std::io;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+/// Describes the faults to inject into messages sent by spawned actors, mirroring the lossy,
+/// duplicating, and unordered [`Network`](crate::actor::Network)s that the checker explores.
+/// Each actor's [`Transport`] is wrapped via [`FaultConfig::wrap`]. There is no separate reorder
+/// fault: messages are only reordered by a nonempty [`FaultConfig::delay`] range.
+///
+/// Randomness is derived from a seed and the sending actor's [`Id`], so each actor makes the same
+/// choices across runs given the same sequence of sends. Partition windows are measured from when
+/// the config was created.
+///
+/// # Example
+///
+/// ```no_run
+/// use stateright::actor::{FaultConfig, Id, UdpTransport, spawn_with_transport};
+/// use std::net::{Ipv4Addr, SocketAddrV4};
+/// use std::time::Duration;
+/// # mod serde_json {
+/// #     pub fn to_vec(_: &()) -> Result<Vec<u8>, ()> { Ok(vec![]) }
+/// #     pub fn from_slice(_: &[u8]) -> Result<(), ()> { Ok(()) }
+/// # }
+/// # let actor1 = ();
+/// # let actor2 = ();
+/// let id1 = Id::from(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 3001));
+/// let id2 = Id::from(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 3002));
+/// let faults = FaultConfig::new(42)
+///     .drop_probability(0.1)
+///     .duplicate_probability(0.05)
+///     .delay(Duration::ZERO..Duration::from_millis(50))
+///     .partition(Duration::from_secs(5)..Duration::from_secs(10), [id1], [id2]);
+/// spawn_with_transport(
+///     |id| Ok(faults.wrap(id, UdpTransport::bind(id)?)),
+///     serde_json::to_vec,
+///     |bytes| serde_json::from_slice(bytes),
+///     vec![
+///         (id1, actor1),
+///         (id2, actor2),
+///     ]).unwrap().join().unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct FaultConfig {
+    seed: u64,
+    epoch: Instant,
+    drop_probability: f64,
+    duplicate_probability: f64,
+    delay: Range<Duration>,
+    partitions: Vec<Partition>,
+}
+
+#[derive(Clone, Debug)]
+struct Partition {
+    window: Range<Duration>,
+    side_a: BTreeSet<Id>,
+    side_b: BTreeSet<Id>,
+}
+
+impl FaultConfig {
+    /// Constructs a config that injects no faults until configured otherwise.
+    pub fn new(seed: u64) -> Self {
+        FaultConfig {
+            seed,
+            epoch: Instant::now(),
+            drop_probability: 0.0,
+            duplicate_probability: 0.0,
+            delay: Duration::ZERO..Duration::ZERO,
+            partitions: Vec::new(),
+        }
+    }
+
+    /// Drops each message with the specified probability.
+    pub fn drop_probability(self, probability: f64) -> Self {
+        assert!((0.0..=1.0).contains(&probability), "invalid probability");
+        FaultConfig {
+            drop_probability: probability,
+            ..self
+        }
+    }
+
+    /// Sends each message twice with the specified probability.
+    pub fn duplicate_probability(self, probability: f64) -> Self {
+        assert!((0.0..=1.0).contains(&probability), "invalid probability");
+        FaultConfig {
+            duplicate_probability: probability,
+            ..self
+        }
+    }
+
+    /// Delays each message by a duration chosen uniformly from the range. A nonempty range
+    /// reorders messages whose delays overlap.
+    pub fn delay(self, range: Range<Duration>) -> Self {
+        FaultConfig {
+            delay: range,
+            ..self
+        }
+    }
+
+    /// Drops messages between actors on opposite sides during the specified window.
+    pub fn partition(
+        mut self,
+        window: Range<Duration>,
+        side_a: impl IntoIterator<Item = Id>,
+        side_b: impl IntoIterator<Item = Id>,
+    ) -> Self {
+        self.partitions.push(Partition {
+            window,
+            side_a: side_a.into_iter().collect(),
+            side_b: side_b.into_iter().collect(),
+        });
+        self
+    }
+
+    /// Wraps the transport bound for the actor with the specified [`Id`].
+    pub fn wrap<T: Transport>(&self, id: Id, transport: T) -> FaultyTransport<T> {
+        FaultyTransport {
+            id,
+            config: self.clone(),
+            rng: StdRng::seed_from_u64(self.seed ^ id.0.rotate_left(32)),
+            delayed: Vec::new(),
+            transport,
+        }
+    }
+
+    fn is_partitioned(&self, src: Id, dst: Id, at: Instant) -> bool {
+        let elapsed = at.saturating_duration_since(self.epoch);
+        self.partitions.iter().any(|p| {
+            p.window.contains(&elapsed)
+                && (p.side_a.contains(&src) && p.side_b.contains(&dst)
+                    || p.side_b.contains(&src) && p.side_a.contains(&dst))
+        })
+    }
+}
+
+/// A [`Transport`] that injects the faults described by a [`FaultConfig`] into outgoing
+/// messages. Delayed messages are sent while the actor waits to receive.
+pub struct FaultyTransport<T> {
+    id: Id,
+    config: FaultConfig,
+    rng: StdRng,
+    delayed: Vec<(Instant, Id, Vec<u8>)>,
+    transport: T,
+}
+
+impl<T: Transport> FaultyTransport<T> {
+    fn send_delayed(&mut self) {
+        let now = Instant::now();
+        while let Some(index) = self.next_delayed().filter(|&i| self.delayed[i].0 <= now) {
+            let (_, dst, bytes) = self.delayed.remove(index);
+            if self.config.is_partitioned(self.id, dst, now) {
+                log::debug!("Partitioned. Dropping. src={}, dst={}", self.id, dst);
+            } else if let Err(e) = self.transport.send(dst, &bytes) {
+                log::warn!(
+                    "Unable to send delayed message. Ignoring. src={}, dst={}, err={:?}",
+                    self.id,
+                    dst,
+                    e
+                );
+            }
+        }
+    }
+
+    /// The index of the delayed message that is due first, sent in FIFO order on ties.
+    fn next_delayed(&self) -> Option<usize> {
+        (0..self.delayed.len()).min_by_key(|&i| self.delayed[i].0)
+    }
+}
+
+impl<T: Transport> Transport for FaultyTransport<T> {
+    type Address = T::Address;
+
+    fn resolve(&self, id: Id) -> io::Result<T::Address> {
+        self.transport.resolve(id)
+    }
+
+    fn send(&mut self, dst: Id, bytes: &[u8]) -> io::Result<()> {
+        let now = Instant::now();
+        if self.config.is_partitioned(self.id, dst, now) {
+            log::debug!("Partitioned. Dropping. src={}, dst={}", self.id, dst);
+            return Ok(());
+        }
+        if self.rng.gen_bool(self.config.drop_probability) {
+            log::debug!("Dropping. src={}, dst={}", self.id, dst);
+            return Ok(());
+        }
+        let copies = if self.rng.gen_bool(self.config.duplicate_probability) {
+            log::debug!("Duplicating. src={}, dst={}", self.id, dst);
+            2
+        } else {
+            1
+        };
+        for _ in 0..copies {
+            let delay = if self.config.delay.start < self.config.delay.end {
+                self.rng.gen_range(self.config.delay.clone())
+            } else {
+                self.config.delay.start
+            };
+            if delay.is_zero() {
+                self.transport.send(dst, bytes)?;
+            } else {
+                self.delayed.push((now + delay, dst, bytes.to_vec()));
+            }
+        }
+        Ok(())
+    }
+
+    fn recv(&mut self, timeout: Duration) -> io::Result<Option<(Id, Vec<u8>)>> {
+        self.send_delayed();
+        let timeout = match self.next_delayed() {
+            Some(i) => timeout.min(self.delayed[i].0.saturating_duration_since(Instant::now())),
+            None => timeout,
+        };
+        let received = self.transport.recv(timeout);
+        self.send_delayed();
+        received
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::actor::ChannelNetwork;
+
+    fn received(transport: &mut impl Transport) -> Vec<Vec<u8>> {
+        let mut received = Vec::new();
+        while let Some((_, bytes)) = transport.recv(Duration::from_millis(20)).unwrap() {
+            received.push(bytes);
+        }
+        received
+    }
+
+    #[test]
+    fn can_drop_and_duplicate() {
+        let network = ChannelNetwork::new();
+        let (id0, id1) = (Id::from(0), Id::from(1));
+        let mut dst = network.bind(id1).unwrap();
+
+        let mut src = FaultConfig::new(0)
+            .drop_probability(1.0)
+            .wrap(id0, network.bind(id0).unwrap());
+        src.send(id1, b"dropped").unwrap();
+        assert!(received(&mut dst).is_empty());
+        drop(src);
+
+        let mut src = FaultConfig::new(0)
+            .duplicate_probability(1.0)
+            .wrap(id0, network.bind(id0).unwrap());
+        src.send(id1, b"twice").unwrap();
+        assert_eq!(
+            received(&mut dst),
+            vec![b"twice".to_vec(), b"twice".to_vec()]
+        );
+    }
+
+    #[test]
+    fn can_delay_and_partition() {
+        let network = ChannelNetwork::new();
+        let (id0, id1, id2) = (Id::from(0), Id::from(1), Id::from(2));
+        let config = FaultConfig::new(0)
+            .delay(Duration::from_millis(30)..Duration::from_millis(30))
+            .partition(Duration::ZERO..Duration::from_secs(3600), [id0], [id2]);
+        let mut src = config.wrap(id0, network.bind(id0).unwrap());
+        let mut dst1 = network.bind(id1).unwrap();
+        let mut dst2 = network.bind(id2).unwrap();
+
+        src.send(id1, b"delayed").unwrap();
+        src.send(id2, b"partitioned").unwrap();
+        assert_eq!(dst1.recv(Duration::ZERO).unwrap(), None);
+        assert_eq!(src.recv(Duration::from_millis(100)).unwrap(), None);
+        assert_eq!(received(&mut dst1), vec![b"delayed".to_vec()]);
+        assert!(received(&mut dst2).is_empty());
+    }
+
+    #[test]
+    fn is_reproducible() {
+        let run = || {
+            let network = ChannelNetwork::new();
+            let mut src = FaultConfig::new(7)
+                .drop_probability(0.5)
+                .wrap(Id::from(0), network.bind(Id::from(0)).unwrap());
+            let mut dst = network.bind(Id::from(1)).unwrap();
+            for i in 0..32 {
+                src.send(Id::from(1), &[i]).unwrap();
+            }
+            received(&mut dst)
+        };
+        let first = run();
+        assert!(!first.is_empty() && first.len() < 32);
+        assert_eq!(first, run());
+    }
+}
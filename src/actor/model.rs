@@ -4,7 +4,7 @@ use crate::actor::{
     is_no_op, is_no_op_with_timer, Actor, ActorModelState, Command, Envelope, Id, Network, Out,
     RandomChoices,
 };
-use crate::{Expectation, Model, Path, Property};
+use crate::{fingerprint, Expectation, Fairness, Model, Path, Property};
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fmt::{Debug, Display, Formatter};
@@ -33,7 +33,12 @@ where
     pub lossy_network: LossyNetwork,
     /// Maximum number of actors that can be contemporarily crashed
     pub max_crashes: usize,
+    /// Maximum number of times that crashed actors can be restarted
+    pub max_recoveries: usize,
+    /// Whether timers fire within the ranges they were set with
+    pub timed_timers: bool,
     pub properties: Vec<Property<ActorModel<A, C, H>>>,
+    pub fairness: Vec<Fairness<ActorModel<A, C, H>>>,
     pub record_msg_in: fn(cfg: &C, history: &H, envelope: Envelope<&A::Msg>) -> Option<H>,
     pub record_msg_out: fn(cfg: &C, history: &H, envelope: Envelope<&A::Msg>) -> Option<H>,
     pub within_boundary: fn(cfg: &C, state: &ActorModelState<A, H>) -> bool,
@@ -53,6 +58,8 @@ pub enum ActorModelAction<Msg, Timer, Random> {
     /// An actor can by notified after a timeout.
     Timeout(Id, Timer),
     Crash(Id),
+    /// A crashed actor can be restarted. See [`Actor::on_restart`].
+    Recover(Id),
     /// A random selection by a node.
     SelectRandom {
         actor: Id,
@@ -72,7 +79,8 @@ pub enum LossyNetwork {
 
 /// The specific timeout value is not relevant for model checking, so this helper can be used to
 /// generate an arbitrary timeout range. The specific value is subject to change, so this helper
-/// must only be used for model checking.
+/// must only be used for model checking, and it is not meaningful for
+/// [`ActorModel::timed_timers`].
 pub fn model_timeout() -> Range<Duration> {
     Duration::from_micros(0)..Duration::from_micros(0)
 }
@@ -100,7 +108,10 @@ where
             init_network: Network::new_unordered_duplicating([]),
             lossy_network: LossyNetwork::No,
             max_crashes: 0,
+            max_recoveries: 0,
+            timed_timers: false,
             properties: Default::default(),
+            fairness: Default::default(),
             record_msg_in: |_, _, _| None,
             record_msg_out: |_, _, _| None,
             within_boundary: |_, _| true,
@@ -139,6 +150,33 @@ where
         self
     }
 
+    /// Specifies the maximum number of times that crashed actors can be restarted. Restarting an
+    /// actor cancels its timers and pending random choices and then runs [`Actor::on_restart`]
+    /// with whatever state the actor last persisted. Messages sent to the actor while it was
+    /// crashed remain on the network.
+    pub fn max_recoveries(mut self, max_recoveries: usize) -> Self {
+        self.max_recoveries = max_recoveries;
+        self
+    }
+
+    /// Specifies whether timers fire within the [`Range<Duration>`] passed to [`Out::set_timer`].
+    /// Disabled by default, in which case any set timer can fire at any time, so timers of very
+    /// different durations race arbitrarily.
+    ///
+    /// When enabled, the model tracks the window in which each timer can fire, relative to the
+    /// latest step that fired or set a timer. A timer can only fire if its window opens before
+    /// every other timer's window closes. Each step that fires or sets a timer happens after an
+    /// unknown amount of time, which is at most the earliest closing of any window and, when a
+    /// timer fires, at least the opening of its window. The remaining windows are shifted by that
+    /// amount and widened to cover every possibility before any new timer is set. This rules out
+    /// interleavings that real timeouts preclude, such as a 10 second election timer firing before
+    /// a 1 second heartbeat timer, without ruling out any that real timeouts allow, at the cost of
+    /// a larger state space.
+    pub fn timed_timers(mut self, timed_timers: bool) -> Self {
+        self.timed_timers = timed_timers;
+        self
+    }
+
     /// Adds a [`Property`] to this model.
     #[allow(clippy::type_complexity)]
     pub fn property(
@@ -151,10 +189,20 @@ where
             expectation,
             name,
             condition,
+            formula: None,
+            transition: None,
         });
         self
     }
 
+    /// Adds a [`Fairness`] constraint to this model. For example, weak fairness for
+    /// [`ActorModelAction::Deliver`] indicates that a message that remains deliverable is
+    /// eventually delivered.
+    pub fn fairness(mut self, fairness: Fairness<ActorModel<A, C, H>>) -> Self {
+        self.fairness.push(fairness);
+        self
+    }
+
     /// Defines whether/how an incoming message contributes to relevant history. Returning
     /// `Some(new_history)` updates the relevant history, while `None` does not.
     pub fn record_msg_in(
@@ -184,6 +232,20 @@ where
         self
     }
 
+    /// With timed timers, a handler other than a timeout runs at some unknown time before the
+    /// earliest deadline, so the existing windows are widened before it sets any new timers.
+    /// Otherwise that time is covered when the next timer fires.
+    fn elapse_before_commands(&self, commands: &Out<A>, state: &mut ActorModelState<A, H>) {
+        if !self.timed_timers || !commands.iter().any(|c| matches!(c, Command::SetTimer(..))) {
+            return;
+        }
+        if let Some(deadline) = state.timers_set.iter().filter_map(Timers::deadline).min() {
+            for timers in &mut state.timers_set {
+                timers.advance(Duration::ZERO..deadline);
+            }
+        }
+    }
+
     /// Updates the actor state, sends messages, and configures the timers.
     fn process_commands(&self, id: Id, commands: Out<A>, state: &mut ActorModelState<A, H>) {
         let index = usize::from(id);
@@ -203,12 +265,16 @@ where
                     }
                     state.network.send(Envelope { src: id, dst, msg });
                 }
-                Command::SetTimer(timer, _) => {
+                Command::SetTimer(timer, range) => {
                     // must use the index to infer how large as actor state may not be initialized yet
                     if state.timers_set.len() <= index {
                         state.timers_set.resize_with(index + 1, Timers::new);
                     }
-                    state.timers_set[index].set(timer);
+                    if self.timed_timers {
+                        state.timers_set[index].set_within(timer, range);
+                    } else {
+                        state.timers_set[index].set(timer);
+                    }
                 }
                 Command::CancelTimer(timer) => {
                     state.timers_set[index].cancel(&timer);
@@ -220,6 +286,9 @@ where
                         state.random_choices[index].insert(key, random)
                     }
                 }
+                Command::Persist => {
+                    state.durable_states[index] = Some(Arc::clone(&state.actor_states[index]));
+                }
             }
         }
     }
@@ -241,6 +310,8 @@ where
             random_choices: vec![RandomChoices::default(); self.actors.len()],
             network: self.init_network.clone(),
             crashed: vec![false; self.actors.len()],
+            durable_states: vec![None; self.actors.len()],
+            recoveries: 0,
         };
 
         // init each actor
@@ -282,8 +353,15 @@ where
         }
 
         // option 3: actor timeout
+        let deadline = state.timers_set.iter().filter_map(Timers::deadline).min();
         for (index, timers) in state.timers_set.iter().enumerate() {
             for timer in timers.iter() {
+                // A timer cannot fire while another must fire first.
+                if let (Some(window), Some(deadline)) = (timers.window(timer), deadline) {
+                    if window.start > deadline {
+                        continue;
+                    }
+                }
                 actions.push(ActorModelAction::Timeout(Id::from(index), timer.clone()));
             }
         }
@@ -299,7 +377,17 @@ where
                 .for_each(|index| actions.push(ActorModelAction::Crash(Id::from(index))));
         }
 
-        // option 5: random choice
+        // option 5: crashed actor recovery
+        if state.recoveries < self.max_recoveries {
+            state
+                .crashed
+                .iter()
+                .enumerate()
+                .filter_map(|(index, &crashed)| if crashed { Some(index) } else { None })
+                .for_each(|index| actions.push(ActorModelAction::Recover(Id::from(index))));
+        }
+
+        // option 6: random choice
         for (actor_index, random_decisions) in state.random_choices.iter().enumerate() {
             for (key, decision) in random_decisions.map.into_iter() {
                 for choice in decision {
@@ -374,6 +462,7 @@ where
                 if let Some(history) = history {
                     next_sys_state.history = history;
                 }
+                self.elapse_before_commands(&out, &mut next_sys_state);
                 self.process_commands(id, out, &mut next_sys_state);
                 Some(next_sys_state)
             }
@@ -383,12 +472,25 @@ where
                 let mut state = Cow::Borrowed(&*last_sys_state.actor_states[index]);
                 let mut out = Out::new();
                 self.actors[index].on_timeout(id, &mut state, &timer, &mut out);
-                if is_no_op_with_timer(&state, &out, &timer) {
+                // Even an otherwise no-op timeout advances the clock for timed timers.
+                if !self.timed_timers && is_no_op_with_timer(&state, &out, &timer) {
                     return None;
                 }
                 let mut next_sys_state = last_sys_state.clone();
 
-                // Timer is no longer valid.
+                // Timer is no longer valid, and the clock advances to some time when it could fire.
+                if let Some(window) = next_sys_state.timers_set[index].window(&timer) {
+                    let deadline = next_sys_state
+                        .timers_set
+                        .iter()
+                        .filter_map(Timers::deadline)
+                        .min()
+                        .unwrap_or(window.end);
+                    let elapsed = window.start..deadline;
+                    for timers in &mut next_sys_state.timers_set {
+                        timers.advance(elapsed.clone());
+                    }
+                }
                 next_sys_state.timers_set[index].cancel(&timer);
 
                 if let Cow::Owned(next_actor_state) = state {
@@ -407,6 +509,23 @@ where
 
                 Some(next_sys_state)
             }
+            ActorModelAction::Recover(id) => {
+                let index = usize::from(id);
+                let mut out = Out::new();
+                let durable_state = last_sys_state.durable_states[index].as_deref();
+                let actor_state = self.actors[index].on_restart(id, durable_state, &mut out);
+
+                let mut next_sys_state = last_sys_state.clone();
+                next_sys_state.timers_set[index].cancel_all();
+                next_sys_state.random_choices[index].map.clear();
+                next_sys_state.actor_states[index] = Arc::new(actor_state);
+                next_sys_state.crashed[index] = false;
+                next_sys_state.recoveries += 1;
+                self.elapse_before_commands(&out, &mut next_sys_state);
+                self.process_commands(id, out, &mut next_sys_state);
+
+                Some(next_sys_state)
+            }
             ActorModelAction::SelectRandom { actor, key, random } => {
                 let actor_index = usize::from(actor);
                 let mut state = Cow::Borrowed(&*last_sys_state.actor_states[actor_index]);
@@ -419,6 +538,7 @@ where
                 if let Cow::Owned(next_actor_state) = state {
                     next_sys_state.actor_states[actor_index] = Arc::new(next_actor_state);
                 }
+                self.elapse_before_commands(&out, &mut next_sys_state);
                 self.process_commands(actor, out, &mut next_sys_state);
                 Some(next_sys_state)
             }
@@ -519,6 +639,24 @@ where
                     )
                 })
             }
+            ActorModelAction::Recover(id) => {
+                let index = usize::from(id);
+                let last_actor_state = match last_state.actor_states.get(index) {
+                    None => return None,
+                    Some(last_actor_state) => &**last_actor_state,
+                };
+                let mut out = Out::new();
+                let durable_state = last_state.durable_states[index].as_deref();
+                let next_actor_state = self.actors[index].on_restart(id, durable_state, &mut out);
+                Some(format!(
+                    "{}",
+                    ActorStep {
+                        last_state: last_actor_state,
+                        next_state: Some(next_actor_state),
+                        out,
+                    }
+                ))
+            }
             ActorModelAction::SelectRandom {
                 actor,
                 key: _,
@@ -671,6 +809,26 @@ where
                     )
                     .unwrap();
                 }
+                Some(ActorModelAction::Recover(actor_id)) => {
+                    let (x, y) = plot(actor_id.into(), time);
+                    writeln!(
+                        &mut svg,
+                        "<circle cx='{}' cy='{}' r='10' class='svg-event-shape' />",
+                        x, y
+                    )
+                    .unwrap();
+
+                    // Track sends to facilitate building arrows.
+                    let index = usize::from(actor_id);
+                    let mut out = Out::new();
+                    let durable_state = state.durable_states[index].as_deref();
+                    self.actors[index].on_restart(actor_id, durable_state, &mut out);
+                    for command in out {
+                        if let Command::Send(dst, msg) = command {
+                            send_time.insert((actor_id, dst, msg), time);
+                        }
+                    }
+                }
                 Some(ActorModelAction::SelectRandom {
                     actor,
                     key: _,
@@ -732,6 +890,15 @@ where
                     )
                     .unwrap();
                 }
+                Some(ActorModelAction::Recover(id)) => {
+                    let (x, y) = plot(id.into(), time);
+                    writeln!(
+                        &mut svg,
+                        "<text x='{}' y='{}' class='svg-event-label'>Recover</text>",
+                        x, y
+                    )
+                    .unwrap();
+                }
                 Some(ActorModelAction::SelectRandom {
                     actor,
                     key: _,
@@ -757,6 +924,43 @@ where
         self.properties.clone()
     }
 
+    fn fairness(&self) -> Vec<Fairness<Self>> {
+        self.fairness.clone()
+    }
+
+    fn independent(&self, state: &Self::State, a: &Self::Action, b: &Self::Action) -> bool {
+        // A step only changes the state of the actor taking it and the channels to and from that
+        // actor, so steps of different actors commute, with several exceptions that are
+        // conservatively treated as dependent:
+        //
+        // - A duplicating network records the last delivered message.
+        // - With timed timers, a timeout advances the clock that bounds every actor's timers.
+        // - A recorded history may be sensitive to the order of the steps, so steps that change
+        //   it are dependent.
+        // - Dropped messages, crashes, and recoveries interact with other actors.
+        fn actor<Msg, Timer, Random>(action: &ActorModelAction<Msg, Timer, Random>) -> Option<Id> {
+            match action {
+                ActorModelAction::Deliver { dst, .. } => Some(*dst),
+                ActorModelAction::Timeout(id, _) => Some(*id),
+                ActorModelAction::SelectRandom { actor, .. } => Some(*actor),
+                ActorModelAction::Drop(_)
+                | ActorModelAction::Crash(_)
+                | ActorModelAction::Recover(_) => None,
+            }
+        }
+        if self.timed_timers || matches!(state.network, Network::UnorderedDuplicating(..)) {
+            return false;
+        }
+        if !matches!((actor(a), actor(b)), (Some(a), Some(b)) if a != b) {
+            return false;
+        }
+        let history = fingerprint(&state.history);
+        [a, b].into_iter().all(|action| {
+            let next_state = self.next_state(state, action.clone());
+            !matches!(next_state, Some(s) if fingerprint(&s.history) != history)
+        })
+    }
+
     fn within_boundary(&self, state: &Self::State) -> bool {
         (self.within_boundary)(&self.cfg, state)
     }
@@ -782,12 +986,15 @@ mod test {
              last_msg: Option<Envelope<PingPongMsg>>| {
                 let timers_set = vec![Timers::new(); states.len()];
                 let crashed = vec![false; states.len()];
+                let durable_states = vec![None; states.len()];
                 ActorModelState {
                     actor_states: states.into_iter().map(Arc::new).collect::<Vec<_>>(),
                     network: Network::new_unordered_duplicating_with_last_msg(envelopes, last_msg),
                     timers_set,
                     random_choices: vec![],
                     crashed,
+                    durable_states,
+                    recoveries: 0,
                     history: (0_u32, 0_u32), // constant as `maintains_history: false`
                 }
             };
@@ -1021,6 +1228,100 @@ mod test {
         checker.assert_no_discovery("must reach max");
     }
 
+    #[test]
+    fn partial_order_reduction_skips_interleavings_of_independent_steps() {
+        // Each actor counts to 3 by messaging itself.
+        #[derive(Clone)]
+        struct Counter;
+        impl Actor for Counter {
+            type State = u8;
+            type Msg = ();
+            type Timer = ();
+            type Random = ();
+            fn on_start(&self, id: Id, o: &mut Out<Self>) -> Self::State {
+                o.send(id, ());
+                0
+            }
+            fn on_msg(
+                &self,
+                id: Id,
+                state: &mut Cow<Self::State>,
+                _: Id,
+                _: Self::Msg,
+                o: &mut Out<Self>,
+            ) {
+                if **state < 3 {
+                    *state.to_mut() += 1;
+                    o.send(id, ());
+                }
+            }
+        }
+        let model = || {
+            ActorModel::new((), ())
+                .actors([Counter, Counter, Counter])
+                .init_network(Network::new_unordered_nonduplicating([]))
+                .property(Expectation::Sometimes, "done", |_, state| {
+                    state.actor_states.iter().all(|s| **s == 3)
+                })
+                .property(Expectation::Always, "in range", |_, state| {
+                    state.actor_states.iter().all(|s| **s <= 3)
+                })
+        };
+
+        let checker = model().checker().spawn_dfs().join();
+        assert_eq!(checker.unique_state_count(), 4 * 4 * 4);
+        assert_eq!(checker.pruned_state_count(), 0);
+
+        let checker = model()
+            .checker()
+            .partial_order_reduction()
+            .spawn_dfs()
+            .join();
+        assert_eq!(checker.unique_state_count(), 4 * 4 * 4);
+        assert_eq!(checker.state_count(), 4 * 4 * 4);
+        assert!(checker.pruned_state_count() > 0);
+        checker.assert_properties();
+
+        // A duplicating network (the default) records the last delivered message, and a history
+        // may be sensitive to the order of deliveries, so no interleavings can be skipped.
+        let checker = ActorModel::new((), ())
+            .actors([Counter, Counter])
+            .property(Expectation::Always, "true", |_, _| true)
+            .checker()
+            .spawn_dfs()
+            .join();
+        assert_eq!(checker.unique_state_count(), 25);
+        let checker = ActorModel::new((), ())
+            .actors([Counter, Counter])
+            .property(Expectation::Always, "true", |_, _| true)
+            .checker()
+            .partial_order_reduction()
+            .spawn_dfs()
+            .join();
+        assert_eq!(checker.unique_state_count(), 25);
+        assert_eq!(checker.pruned_state_count(), 0);
+        let model = || {
+            ActorModel::new((), Vec::new())
+                .actors([Counter, Counter])
+                .init_network(Network::new_unordered_nonduplicating([]))
+                .record_msg_in(|_, history, envelope| {
+                    let mut history = history.clone();
+                    history.push(envelope.dst);
+                    Some(history)
+                })
+                .property(Expectation::Always, "true", |_, _| true)
+        };
+        let checker = model().checker().spawn_dfs().join();
+        let unique_state_count = checker.unique_state_count();
+        let checker = model()
+            .checker()
+            .partial_order_reduction()
+            .spawn_dfs()
+            .join();
+        assert_eq!(checker.unique_state_count(), unique_state_count);
+        assert_eq!(checker.pruned_state_count(), 0);
+    }
+
     #[test]
     fn can_reach_max() {
         let checker = PingPongCfg {
@@ -1308,6 +1609,317 @@ mod test {
         );
     }
 
+    #[test]
+    fn timed_timers_respect_ranges() {
+        #[derive(Clone, Debug, Eq, Hash, PartialEq)]
+        enum TestTimer {
+            Heartbeat,
+            Election,
+        }
+        #[derive(Clone)]
+        struct TestActor;
+        impl Actor for TestActor {
+            type State = Vec<TestTimer>; // in the order fired
+            type Msg = ();
+            type Timer = TestTimer;
+            type Random = ();
+            fn on_start(&self, _: Id, o: &mut Out<Self>) -> Self::State {
+                let second = Duration::from_secs(1);
+                o.set_timer(TestTimer::Heartbeat, second..2 * second);
+                o.set_timer(TestTimer::Election, 5 * second..10 * second);
+                Vec::new()
+            }
+            fn on_timeout(
+                &self,
+                _: Id,
+                state: &mut Cow<Self::State>,
+                timer: &Self::Timer,
+                o: &mut Out<Self>,
+            ) {
+                let second = Duration::from_secs(1);
+                state.to_mut().push(timer.clone());
+                if *timer == TestTimer::Heartbeat && state.len() < 4 {
+                    o.set_timer(TestTimer::Heartbeat, second..2 * second);
+                }
+            }
+        }
+
+        use TestTimer::*;
+        let model = ActorModel::new((), ())
+            .actor(TestActor)
+            .property(Expectation::Sometimes, "election first", |_, state| {
+                state.actor_states[0].first() == Some(&Election)
+            })
+            .property(Expectation::Sometimes, "election second", |_, state| {
+                *state.actor_states[0] == vec![Heartbeat, Election]
+            })
+            .property(Expectation::Sometimes, "election third", |_, state| {
+                *state.actor_states[0] == vec![Heartbeat, Heartbeat, Election]
+            });
+
+        let checker = model.clone().checker().spawn_bfs().join();
+        checker.assert_discovery("election first", vec![Timeout(Id(0), Election)]);
+
+        // Heartbeats fire every 1-2 seconds, so the election timer needs at least 3 heartbeats
+        // before it can have elapsed, although the third heartbeat could be late.
+        let checker = model.timed_timers(true).checker().spawn_bfs().join();
+        checker.assert_no_discovery("election first");
+        checker.assert_no_discovery("election second");
+        checker.assert_discovery(
+            "election third",
+            vec![
+                Timeout(Id(0), Heartbeat),
+                Timeout(Id(0), Heartbeat),
+                Timeout(Id(0), Election),
+            ],
+        );
+    }
+
+    #[test]
+    fn timed_timers_account_for_time_before_delivery() {
+        #[derive(Clone, Debug, Eq, Hash, PartialEq)]
+        enum TestTimer {
+            Slow,
+            Fast,
+        }
+        #[derive(Clone)]
+        struct TestActor;
+        impl Actor for TestActor {
+            type State = Vec<TestTimer>; // in the order fired
+            type Msg = ();
+            type Timer = TestTimer;
+            type Random = ();
+            fn on_start(&self, id: Id, o: &mut Out<Self>) -> Self::State {
+                if id == Id(0) {
+                    let second = Duration::from_secs(1);
+                    o.set_timer(TestTimer::Slow, second..2 * second);
+                } else {
+                    o.send(Id(0), ());
+                }
+                Vec::new()
+            }
+            fn on_msg(&self, _: Id, _: &mut Cow<Self::State>, _: Id, _: (), o: &mut Out<Self>) {
+                let millisecond = Duration::from_millis(1);
+                o.set_timer(TestTimer::Fast, 50 * millisecond..100 * millisecond);
+            }
+            fn on_timeout(
+                &self,
+                _: Id,
+                state: &mut Cow<Self::State>,
+                timer: &Self::Timer,
+                _: &mut Out<Self>,
+            ) {
+                state.to_mut().push(timer.clone());
+            }
+        }
+
+        // The message can arrive just before the slow timer fires, so the fast timer that it
+        // sets can fire second.
+        use TestTimer::*;
+        let checker = ActorModel::new((), ())
+            .actors([TestActor, TestActor])
+            .timed_timers(true)
+            .property(Expectation::Sometimes, "slow then fast", |_, state| {
+                *state.actor_states[0] == vec![Slow, Fast]
+            })
+            .checker()
+            .spawn_bfs()
+            .join();
+        checker.assert_discovery(
+            "slow then fast",
+            vec![
+                Deliver {
+                    src: Id(1),
+                    dst: Id(0),
+                    msg: (),
+                },
+                Timeout(Id(0), Slow),
+                Timeout(Id(0), Fast),
+            ],
+        );
+    }
+
+    #[test]
+    fn recovers_crashed_actors() {
+        #[derive(Clone)]
+        struct TestActor;
+        impl Actor for TestActor {
+            type State = u8;
+            type Msg = ();
+            type Timer = ();
+            type Random = ();
+            fn on_start(&self, _: Id, o: &mut Out<Self>) -> Self::State {
+                o.set_timer((), model_timeout());
+                0
+            }
+            fn on_timeout(
+                &self,
+                _: Id,
+                state: &mut Cow<Self::State>,
+                _: &Self::Timer,
+                _: &mut Out<Self>,
+            ) {
+                *state.to_mut() += 1;
+            }
+        }
+
+        let model = ActorModel::new((), ())
+            .actor(TestActor)
+            .max_crashes(1)
+            .property(Expectation::Always, "unused", |_, _| true) // force full traversal
+            .property(Expectation::Sometimes, "restarted", |_, state| {
+                state.recoveries > 0 && !state.crashed[0] && *state.actor_states[0] == 0
+            });
+
+        // Crashing before or after the timeout, but never restarting.
+        let checker = model.clone().checker().spawn_bfs().join();
+        assert_eq!(checker.unique_state_count(), 4);
+        checker.assert_no_discovery("restarted");
+
+        // Restarting re-runs `on_start`, which also sets the timer again.
+        let checker = model.max_recoveries(1).checker().spawn_bfs().join();
+        assert_eq!(checker.unique_state_count(), 8);
+        checker.assert_discovery("restarted", vec![Crash(Id(0)), Recover(Id(0))]);
+        checker.assert_discovery(
+            "restarted",
+            vec![Timeout(Id(0), ()), Crash(Id(0)), Recover(Id(0))],
+        );
+    }
+
+    #[test]
+    fn restarts_from_durable_state() {
+        #[derive(Clone)]
+        struct TestActor {
+            persist: bool,
+        }
+        impl Actor for TestActor {
+            type State = u8;
+            type Msg = ();
+            type Timer = ();
+            type Random = ();
+            fn on_start(&self, _: Id, o: &mut Out<Self>) -> Self::State {
+                o.set_timer((), model_timeout());
+                0
+            }
+            fn on_timeout(
+                &self,
+                _: Id,
+                state: &mut Cow<Self::State>,
+                _: &Self::Timer,
+                o: &mut Out<Self>,
+            ) {
+                if **state < 2 {
+                    *state.to_mut() += 1;
+                    o.set_timer((), model_timeout());
+                    if self.persist {
+                        o.persist();
+                    }
+                }
+            }
+            fn on_restart(
+                &self,
+                _: Id,
+                durable_state: Option<&Self::State>,
+                _: &mut Out<Self>,
+            ) -> Self::State {
+                durable_state.cloned().unwrap_or_default()
+            }
+        }
+
+        let model = |persist| {
+            ActorModel::new((), ())
+                .actor(TestActor { persist })
+                .max_crashes(1)
+                .max_recoveries(1)
+                .property(Expectation::Sometimes, "restored", |_, state| {
+                    state.recoveries > 0 && !state.crashed[0] && *state.actor_states[0] == 2
+                })
+        };
+
+        // Volatile state is lost upon a crash.
+        let checker = model(false).checker().spawn_bfs().join();
+        checker.assert_no_discovery("restored");
+
+        // Persisted state is handed back upon recovery.
+        let checker = model(true).checker().spawn_bfs().join();
+        checker.assert_discovery(
+            "restored",
+            vec![
+                Timeout(Id(0), ()),
+                Timeout(Id(0), ()),
+                Crash(Id(0)),
+                Recover(Id(0)),
+            ],
+        );
+    }
+
+    #[test]
+    fn fairness_rules_out_retrying_forever() {
+        #[derive(Clone)]
+        enum TestActor {
+            Client,
+            Server,
+        }
+        impl Actor for TestActor {
+            type State = bool; // whether a reply was received
+            type Msg = bool; // whether the message is a reply
+            type Timer = ();
+            type Random = ();
+            fn on_start(&self, _: Id, o: &mut Out<Self>) -> Self::State {
+                if let TestActor::Client = self {
+                    o.send(Id(1), false);
+                    o.set_timer((), model_timeout());
+                }
+                false
+            }
+            fn on_msg(
+                &self,
+                _: Id,
+                state: &mut Cow<Self::State>,
+                src: Id,
+                is_reply: Self::Msg,
+                o: &mut Out<Self>,
+            ) {
+                if is_reply {
+                    *state.to_mut() = true;
+                    o.cancel_timer(());
+                } else {
+                    o.send(src, true);
+                }
+            }
+            fn on_timeout(
+                &self,
+                _: Id,
+                _: &mut Cow<Self::State>,
+                _: &Self::Timer,
+                o: &mut Out<Self>,
+            ) {
+                o.send(Id(1), false);
+                o.set_timer((), model_timeout());
+            }
+        }
+
+        let model = ActorModel::new((), ())
+            .actors([TestActor::Client, TestActor::Server])
+            .property(Expectation::Eventually, "replied", |_, state| {
+                *state.actor_states[0]
+            });
+
+        // Without fairness the client can retry forever.
+        let checker = model.clone().checker().spawn_liveness().join();
+        checker.assert_discovery("replied", vec![Timeout(Id(0), ())]);
+
+        // Otherwise every deliverable message is eventually delivered.
+        model
+            .fairness(Fairness::weak(|_, action| {
+                matches!(action, ActorModelAction::Deliver { .. })
+            }))
+            .checker()
+            .spawn_liveness()
+            .join()
+            .assert_properties();
+    }
+
     #[test]
     fn choose_random() {
         #[derive(Hash, PartialEq, Eq, Debug, Clone)]
@@ -18,6 +18,10 @@ pub struct ActorModelState<A: Actor, H = ()> {
     pub timers_set: Vec<Timers<A::Timer>>,
     pub random_choices: Vec<RandomChoices<A::Random>>,
     pub crashed: Vec<bool>,
+    /// The most recent state that each actor persisted, which survives a crash.
+    pub durable_states: Vec<Option<Arc<A::State>>>,
+    /// How many times crashed actors have been restarted.
+    pub recoveries: usize,
     pub history: H,
 }
 
@@ -97,6 +101,8 @@ where
             random_choices: self.random_choices.clone(),
             network: self.network.clone(),
             crashed: self.crashed.clone(),
+            durable_states: self.durable_states.clone(),
+            recoveries: self.recoveries,
         }
     }
 }
@@ -115,6 +121,9 @@ where
         builder.field("is_timer_set", &self.timers_set);
         builder.field("random_choices", &self.random_choices);
         builder.field("network", &self.network);
+        builder.field("crashed", &self.crashed);
+        builder.field("durable_states", &self.durable_states);
+        builder.field("recoveries", &self.recoveries);
         builder.finish()
     }
 }
@@ -141,6 +150,9 @@ where
         self.history.hash(state);
         self.timers_set.hash(state);
         self.network.hash(state);
+        self.crashed.hash(state);
+        self.durable_states.hash(state);
+        self.recoveries.hash(state);
     }
 }
 
@@ -157,6 +169,9 @@ where
             && self.history.eq(&other.history)
             && self.timers_set.eq(&other.timers_set)
             && self.network.eq(&other.network)
+            && self.crashed.eq(&other.crashed)
+            && self.durable_states.eq(&other.durable_states)
+            && self.recoveries.eq(&other.recoveries)
     }
 }
 
@@ -176,6 +191,8 @@ where
             timers_set: plan.reindex(&self.timers_set),
             random_choices: plan.reindex(&self.random_choices),
             crashed: plan.reindex(&self.crashed),
+            durable_states: plan.reindex(&self.durable_states),
+            recoveries: self.recoveries,
             history: self.history.rewrite(&plan),
         }
     }
@@ -215,6 +232,8 @@ mod test {
             timers_set: vec![non_empty_timers.clone(), empty_timers.clone(), non_empty_timers.clone()],
             random_choices: vec![RandomChoices::default(); 3],
             crashed: vec![false; 3],
+            durable_states: vec![None; 3],
+            recoveries: 0,
             history: History {
                 send_sequence: vec![
                     // Id(0) sends two writes
@@ -254,6 +273,8 @@ mod test {
             timers_set: vec![empty_timers, non_empty_timers.clone(), non_empty_timers.clone()],
             random_choices: vec![RandomChoices::default(); 3],
             crashed: vec![false; 3],
+            durable_states: vec![None; 3],
+            recoveries: 0,
             history: History {
                 send_sequence: vec![
                     // Id(2) sends two writes
@@ -60,10 +60,12 @@ where
     ///
     /// # See Also
     ///
-    /// The [`ordered_reliable_link`] module partially implements this contract as long as actors do
-    /// not restart. A later version of the module and checker will account for actor restarts.
+    /// The [`ordered_reliable_link`] module partially implements this contract, including across
+    /// actor restarts in a model or via [`SpawnHandle::restart`], although messages that a
+    /// crashed actor sent but that were never acknowledged are abandoned.
     ///
     /// [`ordered_reliable_link`]: crate::actor::ordered_reliable_link
+    /// [`SpawnHandle::restart`]: crate::actor::SpawnHandle::restart
     Ordered(BTreeMap<(Id, Id), VecDeque<Msg>>),
 }
 
@@ -77,10 +79,12 @@ where
     ///
     /// # See Also
     ///
-    /// The [`ordered_reliable_link`] module partially implements this contract as long as actors do
-    /// not restart. A later version of the module and checker will account for actor restarts.
+    /// The [`ordered_reliable_link`] module partially implements this contract, including across
+    /// actor restarts in a model or via [`SpawnHandle::restart`], although messages that a
+    /// crashed actor sent but that were never acknowledged are abandoned.
     ///
     /// [`ordered_reliable_link`]: crate::actor::ordered_reliable_link
+    /// [`SpawnHandle::restart`]: crate::actor::SpawnHandle::restart
     pub fn new_ordered(envelopes: impl IntoIterator<Item = Envelope<Msg>>) -> Self {
         let mut this = Self::Ordered(BTreeMap::new());
         for env in envelopes {
@@ -6,8 +6,14 @@
 //! Order is maintained for messages between a source/destination pair. Order is not maintained
 //! between different destinations or different sources.
 //!
-//! The implementation assumes that actors cannot restart. A later version could persist sequencer
-//! information to properly handle actor restarts.
+//! Sequencers are scoped to an [`Incarnation`] of the actor, which increments whenever the actor
+//! restarts via [`Actor::on_restart`], so a restarted sender's messages are not mistaken for
+//! redeliveries. The receive side is persisted along with the wrapped actor's state, so messages
+//! are not redelivered to a restarted receiver unless their effects were lost.
+//!
+//! Spawned actors keep persisted state in memory, so this only holds for restarts requested via
+//! [`SpawnHandle::restart`]. An actor spawned again in a new process starts over at the first
+//! incarnation, and peers that remember its earlier messages ignore the new ones.
 //!
 //! # See Also
 //!
@@ -39,24 +45,43 @@ pub struct ActorWrapper<A: Actor> {
     Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, serde::Serialize, serde::Deserialize,
 )]
 pub enum MsgWrapper<Msg> {
-    Deliver(Sequencer, Msg),
-    Ack(Sequencer),
+    Deliver(Incarnation, Sequencer, Msg),
+    Ack(Incarnation, Sequencer),
 }
 
 /// Message sequencer.
 pub type Sequencer = u64;
 
+/// Distinguishes the lifetimes of an actor between restarts.
+pub type Incarnation = u64;
+
 /// Maintains state for the ORL.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct StateWrapper<Msg, State> {
     // send side
+    incarnation: Incarnation,
     next_send_seq: Sequencer,
     msgs_pending_ack: HashableHashMap<Sequencer, (Id, Msg)>,
 
     // receive (ack'ing) side
-    last_delivered_seqs: HashableHashMap<Id, Sequencer>,
+    last_delivered_seqs: HashableHashMap<Id, (Incarnation, Sequencer)>,
 
     wrapped_state: State,
+    durable_wrapped_state: Option<State>,
+}
+
+impl<Msg: Clone, State: Clone> StateWrapper<Msg, State> {
+    /// Replaces the wrapped state, avoiding an unnecessary clone of the existing one.
+    fn set_wrapped_state(state: &mut Cow<Self>, wrapped_state: State) {
+        *state = Cow::Owned(StateWrapper {
+            incarnation: state.incarnation,
+            next_send_seq: state.next_send_seq,
+            msgs_pending_ack: state.msgs_pending_ack.clone(),
+            last_delivered_seqs: state.last_delivered_seqs.clone(),
+            wrapped_state,
+            durable_wrapped_state: state.durable_wrapped_state.clone(),
+        });
+    }
 }
 
 /// Wrapper for timers.
@@ -86,13 +111,46 @@ where
 
     fn on_start(&self, id: Id, o: &mut Out<Self>) -> Self::State {
         o.set_timer(TimerWrapper::Network, self.resend_interval.clone());
+        o.persist(); // so that the incarnation survives a restart
 
         let mut wrapped_out = Out::new();
         let mut state = StateWrapper {
+            incarnation: 0,
             next_send_seq: 1,
             msgs_pending_ack: Default::default(),
             last_delivered_seqs: Default::default(),
             wrapped_state: self.wrapped_actor.on_start(id, &mut wrapped_out),
+            durable_wrapped_state: None,
+        };
+        process_output(&mut state, wrapped_out, o);
+        state
+    }
+
+    fn on_restart(
+        &self,
+        id: Id,
+        durable_state: Option<&Self::State>,
+        o: &mut Out<Self>,
+    ) -> Self::State {
+        o.set_timer(TimerWrapper::Network, self.resend_interval.clone());
+        o.persist(); // so that the incarnation survives another restart
+
+        // Unacknowledged messages are abandoned along with the previous incarnation.
+        let durable_wrapped_state = durable_state.and_then(|s| s.durable_wrapped_state.clone());
+        let mut wrapped_out = Out::new();
+        let mut state = StateWrapper {
+            incarnation: durable_state.map_or(0, |s| s.incarnation) + 1,
+            next_send_seq: 1,
+            msgs_pending_ack: Default::default(),
+            last_delivered_seqs: durable_state
+                .map(|s| s.last_delivered_seqs.clone())
+                .unwrap_or_default(),
+            wrapped_state: self.wrapped_actor.on_restart(
+                id,
+                durable_wrapped_state.as_ref(),
+                &mut wrapped_out,
+            ),
+            durable_wrapped_state,
         };
         process_output(&mut state, wrapped_out, o);
         state
@@ -107,10 +165,12 @@ where
         o: &mut Out<Self>,
     ) {
         match msg {
-            MsgWrapper::Deliver(seq, wrapped_msg) => {
-                // Always ack the message to prevent re-sends, and early exit if already delivered.
-                o.send(src, MsgWrapper::Ack(seq));
-                if seq <= *state.last_delivered_seqs.get(&src).unwrap_or(&0) {
+            MsgWrapper::Deliver(incarnation, seq, wrapped_msg) => {
+                // Always ack the message to prevent re-sends, and early exit if already delivered
+                // or sent by an earlier incarnation of the source.
+                o.send(src, MsgWrapper::Ack(incarnation, seq));
+                let last_delivered = state.last_delivered_seqs.get(&src).copied();
+                if (incarnation, seq) <= last_delivered.unwrap_or((0, 0)) {
                     return;
                 }
 
@@ -130,20 +190,19 @@ where
 
                 // Never delivered, and not ignored by actor, so update the sequencer and process the original output.
                 if let Cow::Owned(wrapped_state) = wrapped_state {
-                    // Avoid unnecessarily cloning wrapped_state by not calling to_mut() in this
-                    // case.
-                    *state = Cow::Owned(StateWrapper {
-                        next_send_seq: state.next_send_seq,
-                        msgs_pending_ack: state.msgs_pending_ack.clone(),
-                        last_delivered_seqs: state.last_delivered_seqs.clone(),
-                        wrapped_state,
-                    });
+                    StateWrapper::set_wrapped_state(state, wrapped_state);
                 }
-                state.to_mut().last_delivered_seqs.insert(src, seq);
+                state
+                    .to_mut()
+                    .last_delivered_seqs
+                    .insert(src, (incarnation, seq));
                 process_output(state.to_mut(), wrapped_out, o);
             }
-            MsgWrapper::Ack(seq) => {
-                state.to_mut().msgs_pending_ack.remove(&seq);
+            MsgWrapper::Ack(incarnation, seq) => {
+                // Acks for an earlier incarnation refer to abandoned messages.
+                if incarnation == state.incarnation {
+                    state.to_mut().msgs_pending_ack.remove(&seq);
+                }
             }
         }
     }
@@ -159,7 +218,10 @@ where
             TimerWrapper::Network => {
                 o.set_timer(TimerWrapper::Network, self.resend_interval.clone());
                 for (seq, (dst, msg)) in &state.msgs_pending_ack {
-                    o.send(*dst, MsgWrapper::Deliver(*seq, msg.clone()));
+                    o.send(
+                        *dst,
+                        MsgWrapper::Deliver(state.incarnation, *seq, msg.clone()),
+                    );
                 }
             }
             TimerWrapper::User(timer) => {
@@ -170,6 +232,9 @@ where
                 if is_no_op(&wrapped_state, &wrapped_out) {
                     return;
                 }
+                if let Cow::Owned(wrapped_state) = wrapped_state {
+                    StateWrapper::set_wrapped_state(state, wrapped_state);
+                }
                 process_output(state.to_mut(), wrapped_out, o);
             }
         }
@@ -189,16 +254,16 @@ fn process_output<A: Actor>(
 {
     for command in wrapped_out {
         match command {
-            Command::CancelTimer(_) => {
-                todo!("CancelTimer is not supported at this time");
+            Command::CancelTimer(timer) => {
+                o.cancel_timer(TimerWrapper::User(timer));
             }
-            Command::SetTimer(_, _) => {
-                todo!("SetTimer is not supported at this time");
+            Command::SetTimer(timer, range) => {
+                o.set_timer(TimerWrapper::User(timer), range);
             }
             Command::Send(dst, inner_msg) => {
                 o.send(
                     dst,
-                    MsgWrapper::Deliver(state.next_send_seq, inner_msg.clone()),
+                    MsgWrapper::Deliver(state.incarnation, state.next_send_seq, inner_msg.clone()),
                 );
                 state
                     .msgs_pending_ack
@@ -208,25 +273,33 @@ fn process_output<A: Actor>(
             Command::ChooseRandom(_, _) => {
                 todo!("ChooseRandom is not supported at this time");
             }
+            Command::Persist => {
+                state.durable_wrapped_state = Some(state.wrapped_state.clone());
+                o.persist();
+            }
         }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::actor::ordered_reliable_link::{ActorWrapper, MsgWrapper};
-    use crate::actor::{Actor, Id, Out};
+    use crate::actor::ordered_reliable_link::{ActorWrapper, MsgWrapper, TimerWrapper};
+    use crate::actor::{spawn_with_transport, Actor, ChannelNetwork, Id, Out};
     use crate::actor::{ActorModel, ActorModelAction, LossyNetwork, Network};
     use crate::{Checker, Expectation, Model};
     use std::borrow::Cow;
+    use std::time::{Duration, Instant};
 
     pub enum TestActor {
         Sender { receiver_id: Id },
+        TimedSender { receiver_id: Id },
         Receiver,
     }
     #[derive(Clone, Debug, Eq, Hash, PartialEq)]
     pub struct Received(Vec<(Id, TestMsg)>);
-    #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+    #[derive(
+        Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, serde::Serialize, serde::Deserialize,
+    )]
     pub struct TestMsg(u64);
 
     impl Actor for TestActor {
@@ -236,11 +309,30 @@ mod test {
         type Random = ();
 
         fn on_start(&self, _id: Id, o: &mut Out<Self>) -> Self::State {
-            if let TestActor::Sender { receiver_id } = self {
+            match self {
+                TestActor::Sender { receiver_id } => {
+                    o.send(*receiver_id, TestMsg(42));
+                    o.send(*receiver_id, TestMsg(43));
+                }
+                TestActor::TimedSender { .. } => {
+                    o.set_timer((), Duration::from_secs(1)..Duration::from_secs(2));
+                }
+                TestActor::Receiver => {}
+            }
+            Received(Vec::new())
+        }
+
+        fn on_timeout(
+            &self,
+            _id: Id,
+            _received: &mut Cow<Self::State>,
+            _timer: &Self::Timer,
+            o: &mut Out<Self>,
+        ) {
+            if let TestActor::TimedSender { receiver_id } = self {
                 o.send(*receiver_id, TestMsg(42));
                 o.send(*receiver_id, TestMsg(43));
             }
-            Received(Vec::new())
         }
 
         fn on_msg(
@@ -312,14 +404,132 @@ mod test {
                 ActorModelAction::Deliver {
                     src: Id(0),
                     dst: Id(1),
-                    msg: MsgWrapper::Deliver(1, TestMsg(42)),
+                    msg: MsgWrapper::Deliver(0, 1, TestMsg(42)),
                 },
                 ActorModelAction::Deliver {
                     src: Id(0),
                     dst: Id(1),
-                    msg: MsgWrapper::Deliver(2, TestMsg(43)),
+                    msg: MsgWrapper::Deliver(0, 2, TestMsg(43)),
                 },
             ],
         );
     }
+
+    #[test]
+    fn user_timers_are_supported() {
+        let checker = ActorModel::new((), ())
+            .actor(ActorWrapper::with_default_timeout(TestActor::TimedSender {
+                receiver_id: Id::from(1),
+            }))
+            .actor(ActorWrapper::with_default_timeout(TestActor::Receiver))
+            .init_network(Network::new_unordered_duplicating([]))
+            .property(Expectation::Sometimes, "delivered", |_, state| {
+                state.actor_states[1].wrapped_state.0
+                    == vec![(Id::from(0), TestMsg(42)), (Id::from(0), TestMsg(43))]
+            })
+            .within_boundary(|_, state| state.network.len() < 5)
+            .checker()
+            .spawn_bfs()
+            .join();
+        checker.assert_discovery(
+            "delivered",
+            vec![
+                ActorModelAction::Timeout(Id(0), TimerWrapper::User(())),
+                ActorModelAction::Deliver {
+                    src: Id(0),
+                    dst: Id(1),
+                    msg: MsgWrapper::Deliver(0, 1, TestMsg(42)),
+                },
+                ActorModelAction::Deliver {
+                    src: Id(0),
+                    dst: Id(1),
+                    msg: MsgWrapper::Deliver(0, 2, TestMsg(43)),
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn messages_from_restarted_senders_are_delivered() {
+        let checker = ActorModel::new((), ())
+            .actor(ActorWrapper::with_default_timeout(TestActor::Sender {
+                receiver_id: Id::from(1),
+            }))
+            .actor(ActorWrapper::with_default_timeout(TestActor::Receiver))
+            .init_network(Network::new_unordered_nonduplicating([]))
+            .max_crashes(1)
+            .max_recoveries(1)
+            .property(
+                Expectation::Sometimes,
+                "delivered after restart",
+                |_, state| state.actor_states[1].wrapped_state.0.len() == 4,
+            )
+            .checker()
+            .spawn_bfs()
+            .join();
+        checker.assert_discovery(
+            "delivered after restart",
+            vec![
+                ActorModelAction::Deliver {
+                    src: Id(0),
+                    dst: Id(1),
+                    msg: MsgWrapper::Deliver(0, 1, TestMsg(42)),
+                },
+                ActorModelAction::Deliver {
+                    src: Id(0),
+                    dst: Id(1),
+                    msg: MsgWrapper::Deliver(0, 2, TestMsg(43)),
+                },
+                ActorModelAction::Crash(Id(0)),
+                ActorModelAction::Recover(Id(0)),
+                ActorModelAction::Deliver {
+                    src: Id(0),
+                    dst: Id(1),
+                    msg: MsgWrapper::Deliver(1, 1, TestMsg(42)),
+                },
+                ActorModelAction::Deliver {
+                    src: Id(0),
+                    dst: Id(1),
+                    msg: MsgWrapper::Deliver(1, 2, TestMsg(43)),
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn messages_from_restarted_spawned_senders_are_delivered() {
+        let network = ChannelNetwork::new();
+        let (sender_id, receiver_id) = (Id::from(0), Id::from(1));
+        let handle = spawn_with_transport(
+            |id| network.bind(id),
+            serde_json::to_vec,
+            |bytes| serde_json::from_slice(bytes),
+            vec![
+                (
+                    sender_id,
+                    ActorWrapper::with_default_timeout(TestActor::Sender { receiver_id }),
+                ),
+                (
+                    receiver_id,
+                    ActorWrapper::with_default_timeout(TestActor::Receiver),
+                ),
+            ],
+        )
+        .unwrap();
+        let await_received = |count: usize| {
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while handle.state(receiver_id).unwrap().wrapped_state.0.len() < count {
+                assert!(Instant::now() < deadline, "messages not delivered");
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        };
+        await_received(2);
+
+        // The restarted sender begins a new incarnation, so its sequencer starts over without
+        // its messages being mistaken for redeliveries.
+        assert!(handle.restart(sender_id));
+        await_received(4);
+        assert_eq!(handle.state(sender_id).unwrap().incarnation, 1);
+        handle.shutdown().unwrap();
+    }
 }
@@ -0,0 +1,426 @@
+//! Private module for selective re-export.
+
+use crate::actor::{Actor, ActorModelAction, Command, Id, Out};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::ops::Range;
+use std::time::Duration;
+
+/// Executes [`Actor`]s deterministically against a virtual clock, sitting between exhaustive
+/// model checking and running real actors via [`spawn`](crate::actor::spawn).
+///
+/// Unlike [`ActorModel`](crate::actor::ActorModel), which treats every timer as immediately
+/// fireable, the simulator fires each timer after a duration chosen from the range passed to
+/// [`Out::set_timer`], and delivers each message after a latency that is configurable via
+/// [`Simulator::builder`]. All choices are drawn from a single seeded RNG, so a seed reproduces
+/// the same execution exactly, including timing-dependent behavior such as election timeouts.
+/// Actors are identified by their index, as in the model.
+///
+/// # Example
+///
+/// ```
+/// use stateright::actor::{Actor, Id, Out, Simulator};
+/// use std::time::Duration;
+///
+/// struct Heartbeat;
+/// impl Actor for Heartbeat {
+///     type Msg = ();
+///     type State = u32;
+///     type Timer = ();
+///     type Random = ();
+///     fn on_start(&self, _: Id, o: &mut Out<Self>) -> u32 {
+///         o.set_timer((), Duration::from_millis(50)..Duration::from_millis(100));
+///         0
+///     }
+///     fn on_timeout(&self, _: Id, beats: &mut std::borrow::Cow<u32>, _: &(), o: &mut Out<Self>) {
+///         *beats.to_mut() += 1;
+///         o.set_timer((), Duration::from_millis(50)..Duration::from_millis(100));
+///     }
+/// }
+///
+/// let mut simulator = Simulator::new(vec![Heartbeat], 42);
+/// simulator.run_until(Duration::from_secs(1));
+/// assert!((10..=20).contains(simulator.state(Id::from(0))));
+/// ```
+pub struct Simulator<A: Actor> {
+    actors: Vec<A>,
+    states: Vec<A::State>,
+    rng: StdRng,
+    latency: Range<Duration>,
+    random_delay: Range<Duration>,
+    now: Duration,
+    sequence: u64,
+    queue: BinaryHeap<Reverse<(Duration, u64)>>,
+    pending: HashMap<u64, Pending<A>>,
+    timers: HashMap<(Id, A::Timer), u64>,
+    randoms: HashMap<(Id, String), u64>,
+}
+
+/// An event scheduled to occur at a virtual time.
+enum Pending<A: Actor> {
+    Deliver(Id, Id, A::Msg),
+    Timeout(Id, A::Timer),
+    Random(Id, String, A::Random),
+}
+
+/// Configures a [`Simulator`] before its actors start, so that the configuration also applies to
+/// the messages, timers, and random choices of [`Actor::on_start`]. See [`Simulator::builder`].
+pub struct SimulatorBuilder<A: Actor> {
+    actors: Vec<A>,
+    seed: u64,
+    latency: Range<Duration>,
+    random_delay: Range<Duration>,
+}
+
+impl<A: Actor> SimulatorBuilder<A> {
+    /// Delivers each message after a duration chosen from the specified range, which defaults to
+    /// between 1 and 10 milliseconds.
+    pub fn latency(mut self, range: Range<Duration>) -> Self {
+        self.latency = range;
+        self
+    }
+
+    /// Selects each random choice after a duration chosen from the specified range, which
+    /// defaults to between 0 and 10 seconds like [`spawn`](crate::actor::spawn).
+    pub fn random_delay(mut self, range: Range<Duration>) -> Self {
+        self.random_delay = range;
+        self
+    }
+
+    /// Starts the actors.
+    pub fn start(self) -> Simulator<A> {
+        let mut simulator = Simulator {
+            states: Vec::with_capacity(self.actors.len()),
+            actors: self.actors,
+            rng: StdRng::seed_from_u64(self.seed),
+            latency: self.latency,
+            random_delay: self.random_delay,
+            now: Duration::ZERO,
+            sequence: 0,
+            queue: BinaryHeap::new(),
+            pending: HashMap::new(),
+            timers: HashMap::new(),
+            randoms: HashMap::new(),
+        };
+        for index in 0..simulator.actors.len() {
+            let id = Id::from(index);
+            let mut out = Out::new();
+            let state = simulator.actors[index].on_start(id, &mut out);
+            simulator.states.push(state);
+            simulator.process_commands(id, out);
+        }
+        simulator
+    }
+}
+
+impl<A: Actor> Simulator<A> {
+    /// Starts the actors with the default configuration, seeding every subsequent choice from
+    /// `seed`. Equivalent to `Simulator::builder(actors, seed).start()`.
+    pub fn new(actors: Vec<A>, seed: u64) -> Self {
+        Self::builder(actors, seed).start()
+    }
+
+    /// Configures a simulation of the actors, seeding every choice from `seed`. Call
+    /// [`SimulatorBuilder::start`] to start the actors.
+    pub fn builder(actors: Vec<A>, seed: u64) -> SimulatorBuilder<A> {
+        SimulatorBuilder {
+            actors,
+            seed,
+            latency: Duration::from_millis(1)..Duration::from_millis(10),
+            random_delay: Duration::ZERO..Duration::from_secs(10),
+        }
+    }
+
+    /// The current virtual time, measured from when the actors started.
+    pub fn now(&self) -> Duration {
+        self.now
+    }
+
+    /// The current state of the actor with the specified [`Id`].
+    pub fn state(&self, id: Id) -> &A::State {
+        &self.states[usize::from(id)]
+    }
+
+    /// The current states of all actors, indexed by [`Id`].
+    pub fn states(&self) -> &[A::State] {
+        &self.states
+    }
+
+    /// Schedules a message for delivery after the configured latency, such as one from a client
+    /// outside the simulation. Messages to recipients outside the simulation are dropped.
+    pub fn send(&mut self, src: Id, dst: Id, msg: A::Msg) {
+        if usize::from(dst) >= self.actors.len() {
+            log::debug!("Unknown recipient. Dropping. src={:?}, dst={:?}", src, dst);
+            return;
+        }
+        let at = self.now + self.sample(self.latency.clone());
+        self.schedule(at, Pending::Deliver(src, dst, msg));
+    }
+
+    /// Advances the clock to the next scheduled event and applies it, returning the time and the
+    /// corresponding action. Returns `None` once nothing remains scheduled.
+    #[allow(clippy::type_complexity)]
+    pub fn step(&mut self) -> Option<(Duration, ActorModelAction<A::Msg, A::Timer, A::Random>)> {
+        self.next_at()?;
+        let Reverse((at, sequence)) = self.queue.pop()?;
+        let pending = self.pending.remove(&sequence)?;
+        self.now = at;
+        let mut out = Out::new();
+        let (id, action) = match pending {
+            Pending::Deliver(src, dst, msg) => {
+                let index = usize::from(dst);
+                let mut state = Cow::Borrowed(&self.states[index]);
+                self.actors[index].on_msg(dst, &mut state, src, msg.clone(), &mut out);
+                if let Cow::Owned(state) = state {
+                    self.states[index] = state;
+                }
+                (dst, ActorModelAction::Deliver { src, dst, msg })
+            }
+            Pending::Timeout(id, timer) => {
+                self.timers.remove(&(id, timer.clone()));
+                let index = usize::from(id);
+                let mut state = Cow::Borrowed(&self.states[index]);
+                self.actors[index].on_timeout(id, &mut state, &timer, &mut out);
+                if let Cow::Owned(state) = state {
+                    self.states[index] = state;
+                }
+                (id, ActorModelAction::Timeout(id, timer))
+            }
+            Pending::Random(actor, key, random) => {
+                self.randoms.remove(&(actor, key.clone()));
+                let index = usize::from(actor);
+                let mut state = Cow::Borrowed(&self.states[index]);
+                self.actors[index].on_random(actor, &mut state, &random, &mut out);
+                if let Cow::Owned(state) = state {
+                    self.states[index] = state;
+                }
+                (actor, ActorModelAction::SelectRandom { actor, key, random })
+            }
+        };
+        self.process_commands(id, out);
+        Some((at, action))
+    }
+
+    /// Applies events until the next one is scheduled after `deadline`, then advances the clock
+    /// to `deadline`. Returns the number of events applied.
+    pub fn run_until(&mut self, deadline: Duration) -> usize {
+        let mut count = 0;
+        while self.next_at().is_some_and(|at| at <= deadline) {
+            self.step();
+            count += 1;
+        }
+        self.now = self.now.max(deadline);
+        count
+    }
+
+    /// The time of the next scheduled event, discarding queue entries for events that were
+    /// cancelled or replaced.
+    fn next_at(&mut self) -> Option<Duration> {
+        while let Some(&Reverse((at, sequence))) = self.queue.peek() {
+            if self.pending.contains_key(&sequence) {
+                return Some(at);
+            }
+            self.queue.pop();
+        }
+        None
+    }
+
+    fn sample(&mut self, range: Range<Duration>) -> Duration {
+        if range.start < range.end {
+            self.rng.gen_range(range)
+        } else {
+            range.start
+        }
+    }
+
+    fn schedule(&mut self, at: Duration, pending: Pending<A>) -> u64 {
+        let sequence = self.sequence;
+        self.sequence += 1;
+        self.queue.push(Reverse((at, sequence)));
+        self.pending.insert(sequence, pending);
+        sequence
+    }
+
+    fn process_commands(&mut self, id: Id, out: Out<A>) {
+        for command in out {
+            match command {
+                Command::Send(dst, msg) => self.send(id, dst, msg),
+                Command::SetTimer(timer, range) => {
+                    let at = self.now + self.sample(range);
+                    let sequence = self.schedule(at, Pending::Timeout(id, timer.clone()));
+                    if let Some(replaced) = self.timers.insert((id, timer), sequence) {
+                        self.pending.remove(&replaced);
+                    }
+                }
+                Command::CancelTimer(timer) => {
+                    if let Some(cancelled) = self.timers.remove(&(id, timer)) {
+                        self.pending.remove(&cancelled);
+                    }
+                }
+                Command::ChooseRandom(key, random) => {
+                    if let Some(replaced) = self.randoms.remove(&(id, key.clone())) {
+                        self.pending.remove(&replaced);
+                    }
+                    if let Some(random) = random.choose(&mut self.rng).cloned() {
+                        let at = self.now + self.sample(self.random_delay.clone());
+                        let sequence = self.schedule(at, Pending::Random(id, key.clone(), random));
+                        self.randoms.insert((id, key), sequence);
+                    }
+                }
+                Command::Persist => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Sends a ping whenever its timer fires, counting the pings sent.
+    struct Pinger {
+        peer: Id,
+    }
+
+    impl Actor for Pinger {
+        type Msg = bool;
+        type State = u32;
+        type Timer = ();
+        type Random = ();
+
+        fn on_start(&self, id: Id, o: &mut Out<Self>) -> Self::State {
+            if id == Id::from(0) {
+                o.set_timer((), Duration::from_millis(100)..Duration::from_millis(200));
+            }
+            0
+        }
+
+        fn on_msg(
+            &self,
+            _id: Id,
+            _state: &mut Cow<Self::State>,
+            src: Id,
+            is_ping: bool,
+            o: &mut Out<Self>,
+        ) {
+            if is_ping {
+                o.send(src, false);
+            }
+        }
+
+        fn on_timeout(&self, _id: Id, state: &mut Cow<Self::State>, _: &(), o: &mut Out<Self>) {
+            *state.to_mut() += 1;
+            o.send(self.peer, true);
+            o.set_timer((), Duration::from_millis(100)..Duration::from_millis(200));
+        }
+    }
+
+    fn run(seed: u64) -> Vec<(Duration, ActorModelAction<bool, (), ()>)> {
+        let mut simulator = Simulator::builder(
+            vec![Pinger { peer: Id::from(1) }, Pinger { peer: Id::from(0) }],
+            seed,
+        )
+        .latency(Duration::from_millis(5)..Duration::from_millis(10))
+        .start();
+        let mut steps = Vec::new();
+        while simulator.now() < Duration::from_secs(2) {
+            steps.push(simulator.step().unwrap());
+        }
+        steps
+    }
+
+    #[test]
+    fn honours_timer_ranges_and_latency() {
+        let steps = run(3);
+        let mut last_timeout = Duration::ZERO;
+        let mut last_ping = Duration::ZERO;
+        for (at, action) in steps {
+            match action {
+                ActorModelAction::Timeout(..) => {
+                    let elapsed = at - last_timeout;
+                    assert!(Duration::from_millis(100) <= elapsed);
+                    assert!(elapsed < Duration::from_millis(200));
+                    last_timeout = at;
+                }
+                ActorModelAction::Deliver { msg: true, .. } => {
+                    let latency = at - last_timeout;
+                    assert!(Duration::from_millis(5) <= latency);
+                    assert!(latency < Duration::from_millis(10));
+                    last_ping = at;
+                }
+                ActorModelAction::Deliver { msg: false, .. } => {
+                    let latency = at - last_ping;
+                    assert!(Duration::from_millis(5) <= latency);
+                    assert!(latency < Duration::from_millis(10));
+                }
+                action => panic!("unexpected {:?}", action),
+            }
+        }
+    }
+
+    #[test]
+    fn applies_configuration_to_start() {
+        struct Greeter;
+        impl Actor for Greeter {
+            type Msg = ();
+            type State = ();
+            type Timer = ();
+            type Random = ();
+
+            fn on_start(&self, id: Id, o: &mut Out<Self>) {
+                o.send(id, ());
+            }
+        }
+
+        let mut simulator = Simulator::builder(vec![Greeter], 0)
+            .latency(Duration::from_millis(50)..Duration::from_millis(60))
+            .start();
+        let (at, _) = simulator.step().unwrap();
+        assert!(Duration::from_millis(50) <= at);
+        assert!(at < Duration::from_millis(60));
+    }
+
+    #[test]
+    fn is_reproducible() {
+        assert_eq!(run(5), run(5));
+        assert_ne!(run(5), run(6));
+    }
+
+    #[test]
+    fn can_cancel_and_reset_timers() {
+        struct Resetter;
+        impl Actor for Resetter {
+            type Msg = ();
+            type State = u8;
+            type Timer = u8;
+            type Random = ();
+
+            fn on_start(&self, _id: Id, o: &mut Out<Self>) -> u8 {
+                o.set_timer(1, Duration::from_millis(10)..Duration::from_millis(10));
+                o.set_timer(1, Duration::from_millis(30)..Duration::from_millis(30));
+                o.set_timer(2, Duration::from_millis(20)..Duration::from_millis(20));
+                o.cancel_timer(2);
+                0
+            }
+
+            fn on_timeout(&self, _id: Id, state: &mut Cow<u8>, timer: &u8, _o: &mut Out<Self>) {
+                *state.to_mut() = *timer;
+            }
+        }
+
+        let mut simulator = Simulator::new(vec![Resetter], 0);
+        assert_eq!(
+            simulator.step(),
+            Some((
+                Duration::from_millis(30),
+                ActorModelAction::Timeout(Id::from(0), 1)
+            ))
+        );
+        assert_eq!(simulator.step(), None);
+        assert_eq!(simulator.state(Id::from(0)), &1);
+    }
+}
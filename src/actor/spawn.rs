@@ -1,10 +1,9 @@
 //! Private module for selective re-export.
 
 use crate::actor::*;
-use crossbeam_utils::thread;
 use std::collections::HashMap;
 use std::fmt::Debug;
-use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::net::{Ipv4Addr, SocketAddrV4};
 use std::time::{Duration, Instant};
 
 impl From<Id> for SocketAddrV4 {
@@ -34,11 +33,18 @@ impl From<SocketAddrV4> for Id {
 }
 
 /// 500 years in the future.
-fn practically_never() -> Instant {
+pub(crate) fn practically_never() -> Instant {
     Instant::now() + Duration::from_secs(3600 * 24 * 365 * 500)
 }
 
-/// Runs an actor, sending messages over UDP. Blocks the current thread.
+/// Runs an actor, sending messages over UDP. Returns once every actor is bound, with a
+/// [`SpawnHandle`] for controlling the actors and waiting for them to stop.
+///
+/// Delivery is best-effort with no ordering, and each serialized message must fit in a single
+/// datagram. See [`spawn_tcp`] for ordered delivery.
+///
+/// Each [`Id`] must encode an IPv4 address and port. For IPv6 addresses and hostnames, pass
+/// [`UdpTransport::bind_with`] an [`AddressBook`] via [`spawn_with_transport`].
 ///
 /// # Example
 ///
@@ -59,112 +65,348 @@ fn practically_never() -> Instant {
 ///     vec![
 ///         (id1, actor1),
 ///         (id2, actor2),
-///     ]);
+///     ]).unwrap().join().unwrap();
 /// ```
 pub fn spawn<A, E: Debug + 'static>(
     serialize: fn(&A::Msg) -> Result<Vec<u8>, E>,
     deserialize: fn(&[u8]) -> Result<A::Msg, E>,
     actors: Vec<(impl Into<Id>, A)>,
-) -> Result<(), Box<dyn std::any::Any + Send + 'static>>
+) -> std::io::Result<SpawnHandle<A>>
 where
     A: 'static + Send + Actor,
-    A::Msg: Debug,
-    A::State: Debug,
+    A::Msg: Debug + Send,
+    A::State: Debug + Send,
 {
-    thread::scope(|s| {
-        for (id, actor) in actors {
-            let id = id.into();
-            let addr = SocketAddrV4::from(id);
+    spawn_with_transport(UdpTransport::bind, serialize, deserialize, actors)
+}
 
-            // note that panics are returned as `Err` when `join`ing
-            s.spawn(move |_| {
-                let socket = UdpSocket::bind(addr).unwrap(); // panic if unable to bind
-                let mut in_buf = [0; 65_535];
-                let mut next_interrupts = HashMap::new();
+/// Runs an actor, sending messages over TCP. Returns once every actor is bound, with a
+/// [`SpawnHandle`] for controlling the actors and waiting for them to stop.
+///
+/// Messages are length-prefixed, so their size is not limited by a datagram, and messages from
+/// one actor to another arrive in the order they were sent, matching
+/// [`Network::new_ordered`]. A connection to each destination is opened on first send and
+/// reused thereafter. If a connection fails, it is reestablished on the next send, although
+/// messages still in flight on the failed connection may be lost.
+///
+/// Each [`Id`] must encode an IPv4 address and port. For IPv6 addresses and hostnames, pass
+/// [`TcpTransport::bind_with`] an [`AddressBook`] via [`spawn_with_transport`].
+///
+/// # Example
+///
+/// ```no_run
+/// use stateright::actor::{Id, spawn_tcp};
+/// use std::net::{Ipv4Addr, SocketAddrV4};
+/// # mod serde_json {
+/// #     pub fn to_vec(_: &()) -> Result<Vec<u8>, ()> { Ok(vec![]) }
+/// #     pub fn from_slice(_: &[u8]) -> Result<(), ()> { Ok(()) }
+/// # }
+/// # let actor1 = ();
+/// # let actor2 = ();
+/// let id1 = Id::from(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 3001));
+/// let id2 = Id::from(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 3002));
+/// spawn_tcp(
+///     serde_json::to_vec,
+///     |bytes| serde_json::from_slice(bytes),
+///     vec![
+///         (id1, actor1),
+///         (id2, actor2),
+///     ]).unwrap().join().unwrap();
+/// ```
+pub fn spawn_tcp<A, E: Debug + 'static>(
+    serialize: fn(&A::Msg) -> Result<Vec<u8>, E>,
+    deserialize: fn(&[u8]) -> Result<A::Msg, E>,
+    actors: Vec<(impl Into<Id>, A)>,
+) -> std::io::Result<SpawnHandle<A>>
+where
+    A: 'static + Send + Actor,
+    A::Msg: Debug + Send,
+    A::State: Debug + Send,
+{
+    spawn_with_transport(TcpTransport::bind, serialize, deserialize, actors)
+}
 
-                let mut out = Out::new();
-                let mut state = Cow::Owned(actor.on_start(id, &mut out));
-                log::info!("Actor started. id={}, state={:?}, out={:?}", addr, state, out);
-                for c in out {
-                    on_command::<A, E>(addr, c, serialize, &socket, &mut next_interrupts);
+/// Runs an actor, sending messages over a [`Transport`] that `bind` returns for each actor's
+/// [`Id`]. Returns once every actor is bound, with a [`SpawnHandle`] for controlling the actors
+/// and waiting for them to stop.
+///
+/// [`spawn`] and [`spawn_tcp`] use [`UdpTransport::bind`] and [`TcpTransport::bind`]
+/// respectively, while a [`ChannelNetwork`] runs actors within a single process without
+/// binding ports.
+///
+/// # Example
+///
+/// ```no_run
+/// use stateright::actor::{Id, TcpTransport, spawn_with_transport};
+/// use std::net::{Ipv4Addr, SocketAddrV4};
+/// # mod serde_json {
+/// #     pub fn to_vec(_: &()) -> Result<Vec<u8>, ()> { Ok(vec![]) }
+/// #     pub fn from_slice(_: &[u8]) -> Result<(), ()> { Ok(()) }
+/// # }
+/// # let actor1 = ();
+/// # let actor2 = ();
+/// let id1 = Id::from(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 3001));
+/// let id2 = Id::from(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 3002));
+/// spawn_with_transport(
+///     TcpTransport::bind,
+///     serde_json::to_vec,
+///     |bytes| serde_json::from_slice(bytes),
+///     vec![
+///         (id1, actor1),
+///         (id2, actor2),
+///     ]).unwrap().join().unwrap();
+/// ```
+pub fn spawn_with_transport<A, E: Debug + 'static, T: Transport + 'static>(
+    bind: impl Fn(Id) -> std::io::Result<T>,
+    serialize: fn(&A::Msg) -> Result<Vec<u8>, E>,
+    deserialize: fn(&[u8]) -> Result<A::Msg, E>,
+    actors: Vec<(impl Into<Id>, A)>,
+) -> std::io::Result<SpawnHandle<A>>
+where
+    A: 'static + Send + Actor,
+    A::Msg: Debug + Send,
+    A::State: Debug + Send,
+{
+    // Binds every actor before starting any, so that a bind failure leaves nothing running.
+    let mut bound = Vec::with_capacity(actors.len());
+    for (id, actor) in actors {
+        let id = id.into();
+        bound.push((id, actor, bind(id)?));
+    }
+
+    let mut handle = SpawnHandle {
+        controls: HashMap::new(),
+        threads: Vec::new(),
+    };
+    for (id, actor, mut transport) in bound {
+        let (control_sender, control) = crossbeam_channel::unbounded();
+        handle.controls.insert(id, control_sender);
+        // note that panics are returned as `Err` when `join`ing
+        handle.threads.push(std::thread::spawn(move || {
+            let mut next_interrupts = HashMap::new();
+            // Kept in memory, so it only survives restarts requested via the handle.
+            let mut durable_state: Option<A::State> = None;
+
+            let mut out = Out::new();
+            let mut state: Cow<A::State> = Cow::Owned(actor.on_start(id, &mut out));
+            log::info!("Actor started. id={}, state={:?}, out={:?}", id, state, out);
+            for c in out {
+                if let Command::Persist = c {
+                    durable_state = Some(state.clone().into_owned());
+                    continue;
                 }
+                on_command::<A, E>(
+                    id,
+                    c,
+                    serialize,
+                    &mut |dst, bytes| transport.send(dst, bytes),
+                    &mut next_interrupts,
+                );
+            }
 
-                loop {
-                    // Apply an interrupt if present, otherwise wait for a message.
-                    let mut out = Out::new();
-                    let (min_timer, min_instant) = next_interrupts
-                        .iter()
-                        .min_by_key(|(_, instant)| *instant)
-                        .map(|(t, i)| (Some(t.clone()), *i))
-                        .unwrap_or_else(|| (None, practically_never()));
-                    if let Some(max_wait) = min_instant.checked_duration_since(Instant::now()) {
-                        socket.set_read_timeout(Some(max_wait)).expect("set_read_timeout failed");
-                        match socket.recv_from(&mut in_buf) {
-                            Err(e) => {
-                                // Timeout (`WouldBlock`) ignored since next iteration will apply interrupt.
-                                if e.kind() != std::io::ErrorKind::WouldBlock {
-                                    log::warn!("Unable to read socket. Ignoring. id={}, err={:?}", addr, e);
+            loop {
+                // Apply a control request if present, then an interrupt if present, otherwise
+                // wait for a message.
+                let mut out = Out::new();
+                match control.try_recv() {
+                    Ok(Control::Stop) => {
+                        log::info!("Actor stopped. id={}, state={:?}", id, state);
+                        return;
+                    }
+                    Ok(Control::Snapshot(reply)) => {
+                        let _ = reply.send(state.clone().into_owned());
+                        continue;
+                    }
+                    Ok(Control::Restart) => {
+                        log::info!("Actor restarted. id={}, state={:?}", id, state);
+                        next_interrupts.clear();
+                        let durable_state = durable_state.as_ref();
+                        state = Cow::Owned(actor.on_restart(id, durable_state, &mut out));
+                    }
+                    Ok(Control::Deliver(src, msg)) => {
+                        log::info!(
+                            "Received injected message. id={}, src={}, msg={:?}",
+                            id,
+                            src,
+                            msg
+                        );
+                        actor.on_msg(id, &mut state, src, msg, &mut out);
+                    }
+                    // A dropped handle leaves the actor running.
+                    Err(_) => {
+                        let (min_timer, min_instant) = next_interrupts
+                            .iter()
+                            .min_by_key(|(_, instant)| *instant)
+                            .map(|(t, i)| (Some(t.clone()), *i))
+                            .unwrap_or_else(|| (None, practically_never()));
+                        if let Some(max_wait) = min_instant.checked_duration_since(Instant::now()) {
+                            match transport.recv(max_wait.min(CONTROL_POLL_INTERVAL)) {
+                                Err(e) => {
+                                    log::warn!(
+                                        "Unable to receive. Ignoring. id={}, err={:?}",
+                                        id,
+                                        e
+                                    );
+                                    continue;
                                 }
-                                continue;
-                            },
-                            Ok((count, src_addr)) => {
-                                match deserialize(&in_buf[..count]) {
+                                // Timeout ignored since next iteration will apply interrupt.
+                                Ok(None) => continue,
+                                Ok(Some((src, in_buf))) => match deserialize(&in_buf) {
                                     Ok(msg) => {
-                                        if let SocketAddr::V4(src_addr) = src_addr {
-                                            log::info!("Received message. id={}, src={}, msg={}",
-                                                        addr, src_addr, format!("{:?}", msg));
-                                            actor.on_msg(id, &mut state, Id::from(src_addr), msg, &mut out);
-                                        } else {
-                                            log::debug!("Received non-IPv4 message. Ignoring. id={}, src={}, msg={}",
-                                                       addr, src_addr, format!("{:?}", msg));
-                                            continue;
-                                        }
-                                    },
+                                        log::info!(
+                                            "Received message. id={}, src={}, msg={:?}",
+                                            id,
+                                            src,
+                                            msg
+                                        );
+                                        actor.on_msg(id, &mut state, src, msg, &mut out);
+                                    }
                                     Err(e) => {
-                                        log::debug!("Unable to parse message. Ignoring. id={}, src={}, buf={:?}, err={:?}",
-                                                   addr, src_addr, &in_buf[..count], e);
+                                        log::debug!(
+                                            "Unable to parse message. Ignoring. \
+                                             id={}, src={}, buf={:?}, err={:?}",
+                                            id,
+                                            src,
+                                            in_buf,
+                                            e
+                                        );
                                         continue;
                                     }
+                                },
+                            }
+                        } else {
+                            let min_timer = min_timer.unwrap();
+                            next_interrupts.remove(&min_timer); // timer is no longer valid
+                            match &min_timer {
+                                Interrupt::Timeout(min_timer) => {
+                                    actor.on_timeout(id, &mut state, min_timer, &mut out);
                                 }
-                            },
-                        }
-                    } else {
-                        let min_timer = min_timer.unwrap();
-                        next_interrupts.remove(&min_timer); // timer is no longer valid
-                        match &min_timer {
-                            Interrupt::Timeout(min_timer) => {
-                                actor.on_timeout(id, &mut state, min_timer, &mut out);
-                            },
-                            Interrupt::Random(random) => {
-                                actor.on_random(id, &mut state, random, &mut out);
-                            },
+                                Interrupt::Random(random) => {
+                                    actor.on_random(id, &mut state, random, &mut out);
+                                }
+                            }
                         }
                     }
+                }
 
-                    // Handle commands and update state.
-                    if !is_no_op(&state, &out) {
-                        log::debug!("Acted. id={}, state={:?}, out={:?}",
-                                    addr, state, out);
+                // Handle commands and update state.
+                if !is_no_op(&state, &out) {
+                    log::debug!("Acted. id={}, state={:?}, out={:?}", id, state, out);
+                }
+                for c in out {
+                    if let Command::Persist = c {
+                        durable_state = Some(state.clone().into_owned());
+                        continue;
                     }
-                    for c in out { on_command::<A, E>(addr, c, serialize, &socket, &mut next_interrupts); }
+                    on_command::<A, E>(
+                        id,
+                        c,
+                        serialize,
+                        &mut |dst, bytes| transport.send(dst, bytes),
+                        &mut next_interrupts,
+                    );
                 }
-            });
+            }
+        }));
+    }
+    Ok(handle)
+}
+
+/// The longest an actor waits for a message before checking for [`SpawnHandle`] requests.
+const CONTROL_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A request from a [`SpawnHandle`] to a spawned actor.
+enum Control<A: Actor> {
+    Deliver(Id, A::Msg),
+    Snapshot(crossbeam_channel::Sender<A::State>),
+    Restart,
+    Stop,
+}
+
+/// A handle to actors started by [`spawn`], [`spawn_tcp`], or [`spawn_with_transport`].
+/// Dropping the handle leaves the actors running.
+pub struct SpawnHandle<A: Actor> {
+    controls: HashMap<Id, crossbeam_channel::Sender<Control<A>>>,
+    threads: Vec<std::thread::JoinHandle<()>>,
+}
+
+impl<A: Actor> SpawnHandle<A> {
+    /// The [`Id`]s of the spawned actors, including any that have stopped.
+    pub fn ids(&self) -> Vec<Id> {
+        let mut ids: Vec<_> = self.controls.keys().copied().collect();
+        ids.sort();
+        ids
+    }
+
+    /// Delivers a message to the actor `dst` as if `src` had sent it, bypassing the transport.
+    /// Returns `false` if `dst` is unknown or has stopped.
+    pub fn send(&self, src: Id, dst: Id, msg: A::Msg) -> bool {
+        self.control(dst, Control::Deliver(src, msg))
+    }
+
+    /// Returns a copy of the actor's current state, or `None` if the actor is unknown or has
+    /// stopped.
+    pub fn state(&self, id: Id) -> Option<A::State> {
+        let (reply, snapshot) = crossbeam_channel::bounded(1);
+        if !self.control(id, Control::Snapshot(reply)) {
+            return None;
         }
-    })
+        snapshot.recv().ok()
+    }
+
+    /// Restarts an actor once it finishes handling its current event, as if it had crashed. Its
+    /// timers are cancelled, and [`Actor::on_restart`] receives the state it last persisted via
+    /// [`Out::persist`]. Returns `false` if the actor is unknown or has stopped.
+    pub fn restart(&self, id: Id) -> bool {
+        self.control(id, Control::Restart)
+    }
+
+    /// Stops an actor once it finishes handling its current event. Returns `false` if the actor
+    /// is unknown or has already stopped.
+    pub fn stop(&self, id: Id) -> bool {
+        self.control(id, Control::Stop)
+    }
+
+    /// Stops every actor, then waits for their threads to exit. Returns the first panic, if any.
+    pub fn shutdown(self) -> Result<(), Box<dyn std::any::Any + Send + 'static>> {
+        for control in self.controls.values() {
+            let _ = control.send(Control::Stop);
+        }
+        self.join()
+    }
+
+    /// Waits for every actor's thread to exit, which only happens after the actors are stopped or
+    /// panic. Returns the first panic, if any.
+    pub fn join(self) -> Result<(), Box<dyn std::any::Any + Send + 'static>> {
+        let mut result = Ok(());
+        for thread in self.threads {
+            if let Err(e) = thread.join() {
+                result = result.and(Err(e));
+            }
+        }
+        result
+    }
+
+    fn control(&self, id: Id, control: Control<A>) -> bool {
+        match self.controls.get(&id) {
+            Some(sender) => sender.send(control).is_ok(),
+            None => false,
+        }
+    }
 }
 
 #[derive(Hash, PartialEq, Eq, Clone)]
-enum Interrupt<T, R> {
+pub(crate) enum Interrupt<T, R> {
     Timeout(T),
     Random(R),
 }
 
 /// The effect to perform in response to spawned actor outputs.
-fn on_command<A, E>(
-    addr: SocketAddrV4,
+pub(crate) fn on_command<A, E>(
+    id: Id,
     command: Command<A::Msg, A::Timer, A::Random>,
     serialize: fn(&A::Msg) -> Result<Vec<u8>, E>,
-    socket: &UdpSocket,
+    send: &mut dyn FnMut(Id, &[u8]) -> std::io::Result<()>,
     next_interrupts: &mut HashMap<Interrupt<A::Timer, A::Random>, Instant>,
 ) where
     A: Actor,
@@ -172,31 +414,28 @@ fn on_command<A, E>(
     E: Debug,
 {
     match command {
-        Command::Send(dst, msg) => {
-            let dst_addr = SocketAddrV4::from(dst);
-            match serialize(&msg) {
-                Err(e) => {
+        Command::Send(dst, msg) => match serialize(&msg) {
+            Err(e) => {
+                log::warn!(
+                    "Unable to serialize. Ignoring. src={}, dst={}, msg={:?}, err={:?}",
+                    id,
+                    dst,
+                    msg,
+                    e
+                );
+            }
+            Ok(out_buf) => {
+                if let Err(e) = send(dst, &out_buf) {
                     log::warn!(
-                        "Unable to serialize. Ignoring. src={}, dst={}, msg={:?}, err={:?}",
-                        addr,
-                        dst_addr,
+                        "Unable to send. Ignoring. src={}, dst={}, msg={:?}, err={:?}",
+                        id,
+                        dst,
                         msg,
                         e
                     );
                 }
-                Ok(out_buf) => {
-                    if let Err(e) = socket.send_to(&out_buf, dst_addr) {
-                        log::warn!(
-                            "Unable to send. Ignoring. src={}, dst={}, msg={:?}, err={:?}",
-                            addr,
-                            dst_addr,
-                            msg,
-                            e
-                        );
-                    }
-                }
             }
-        }
+        },
         Command::SetTimer(timer, range) => {
             let duration = if range.start < range.end {
                 use rand::Rng;
@@ -228,13 +467,70 @@ fn on_command<A, E>(
                 .and_modify(|d| *d = Instant::now() + duration)
                 .or_insert_with(|| Instant::now() + duration);
         }
+        Command::Persist => {
+            // Only `spawn_with_transport` retains persisted state, as it can restart an actor.
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
     use crate::actor::*;
+    use std::borrow::Cow;
     use std::net::{Ipv4Addr, SocketAddrV4};
+    use std::time::{Duration, Instant};
+
+    /// Counts messages received, forwarding each to its peer with a decremented hop count.
+    struct Relay(Id);
+
+    impl Actor for Relay {
+        type Msg = u8;
+        type State = u8;
+        type Timer = ();
+        type Random = ();
+
+        fn on_start(&self, _id: Id, _o: &mut Out<Self>) -> u8 {
+            0
+        }
+
+        fn on_msg(&self, _id: Id, state: &mut Cow<u8>, _src: Id, hops: u8, o: &mut Out<Self>) {
+            *state.to_mut() += 1;
+            if hops > 0 {
+                o.send(self.0, hops - 1);
+            }
+        }
+    }
+
+    #[test]
+    fn can_control_spawned_actors() {
+        let network = ChannelNetwork::new();
+        let (id0, id1) = (Id::from(0), Id::from(1));
+        let handle = spawn_with_transport(
+            |id| network.bind(id),
+            |msg: &u8| Ok::<_, ()>(vec![*msg]),
+            |bytes| bytes.first().copied().ok_or(()),
+            vec![(id0, Relay(id1)), (id1, Relay(id0))],
+        )
+        .unwrap();
+        assert_eq!(handle.ids(), vec![id0, id1]);
+
+        assert!(handle.send(Id::from(99), id0, 4));
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while (handle.state(id0), handle.state(id1)) != (Some(3), Some(2)) {
+            assert!(Instant::now() < deadline, "messages not delivered");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        assert!(handle.stop(id1));
+        while handle.state(id1).is_some() {
+            assert!(Instant::now() < deadline, "actor not stopped");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(!handle.stop(id1));
+        assert_eq!(handle.state(id0), Some(3));
+        assert!(!handle.send(Id::from(99), Id::from(2), 0));
+        handle.shutdown().unwrap();
+    }
 
     #[test]
     fn can_encode_id() {
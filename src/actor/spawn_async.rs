@@ -0,0 +1,377 @@
+//! Private module for selective re-export.
+
+use crate::actor::spawn::{on_command, practically_never, Interrupt};
+use crate::actor::*;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::io;
+use std::net::{SocketAddr, SocketAddrV4};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::net::UdpSocket;
+use tokio::sync::watch;
+use tokio::task::{JoinError, JoinHandle};
+
+/// Runs actors as tasks on the current [tokio] runtime, sending messages over UDP. Resolves once
+/// every actor's socket is bound, returning a handle for stopping the actors. Requires the
+/// `tokio` feature.
+///
+/// Unlike [`spawn`], which dedicates an OS thread to each actor, actors only occupy a task, and
+/// timers are driven by the runtime. Actors behave the same as with [`spawn`]. Actors are
+/// addressed by the IPv4 address and port encoded in their [`Id`]s, or by an [`AddressBook`] via
+/// [`spawn_async_with`].
+///
+/// Only UDP is supported, so messages are limited to 65,535 bytes and may be lost, duplicated, or
+/// reordered. The [`Transport`]s accepted by [`spawn_with_transport`] block the calling thread,
+/// so they cannot be used here.
+///
+/// # Example
+///
+/// ```no_run
+/// use stateright::actor::{Id, spawn_async};
+/// use std::net::{Ipv4Addr, SocketAddrV4};
+/// # mod serde_json {
+/// #     pub fn to_vec(_: &()) -> Result<Vec<u8>, ()> { Ok(vec![]) }
+/// #     pub fn from_slice(_: &[u8]) -> Result<(), ()> { Ok(()) }
+/// # }
+/// # async fn run() -> std::io::Result<()> {
+/// # let actor1 = ();
+/// # let actor2 = ();
+/// let id1 = Id::from(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 3001));
+/// let id2 = Id::from(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 3002));
+/// let handle = spawn_async(
+///     serde_json::to_vec,
+///     |bytes| serde_json::from_slice(bytes),
+///     vec![
+///         (id1, actor1),
+///         (id2, actor2),
+///     ]).await?;
+/// // ...
+/// handle.shutdown().await.unwrap();
+/// # Ok(())
+/// # }
+/// ```
+pub async fn spawn_async<A, E: Debug + 'static>(
+    serialize: fn(&A::Msg) -> Result<Vec<u8>, E>,
+    deserialize: fn(&[u8]) -> Result<A::Msg, E>,
+    actors: Vec<(impl Into<Id>, A)>,
+) -> io::Result<AsyncSpawnHandle>
+where
+    A: 'static + Send + Actor,
+    A::Msg: Debug + Send,
+    A::State: Debug + Send + Sync,
+    A::Timer: Send,
+    A::Random: Send,
+{
+    spawn_async_with(serialize, deserialize, AddressBook::new(), actors).await
+}
+
+/// Like [`spawn_async`], but binds and addresses actors via an [`AddressBook`], which can map
+/// [`Id`]s to IPv6 addresses and hostnames. Every address in the book is resolved once before
+/// the actors start, and incoming messages from those addresses are attributed to their [`Id`]s.
+pub async fn spawn_async_with<A, E: Debug + 'static>(
+    serialize: fn(&A::Msg) -> Result<Vec<u8>, E>,
+    deserialize: fn(&[u8]) -> Result<A::Msg, E>,
+    addresses: AddressBook,
+    actors: Vec<(impl Into<Id>, A)>,
+) -> io::Result<AsyncSpawnHandle>
+where
+    A: 'static + Send + Actor,
+    A::Msg: Debug + Send,
+    A::State: Debug + Send + Sync,
+    A::Timer: Send,
+    A::Random: Send,
+{
+    let actors: Vec<(Id, A)> = actors.into_iter().map(|(id, a)| (id.into(), a)).collect();
+    let ids: Vec<Id> = actors.iter().map(|(id, _)| *id).collect();
+    // Hostnames are looked up on a blocking thread, as the lookup would otherwise block the
+    // runtime.
+    let addresses = tokio::task::spawn_blocking(move || ResolvedAddresses::new(addresses, ids))
+        .await
+        .map_err(io::Error::other)??;
+    let addresses = Arc::new(addresses);
+
+    let (shutdown, stopped) = watch::channel(false);
+    let mut sockets = Vec::with_capacity(actors.len());
+    for (id, actor) in actors {
+        let socket = UdpSocket::bind(addresses.addr_of(id)).await?;
+        sockets.push((id, actor, socket));
+    }
+    let tasks = sockets
+        .into_iter()
+        .map(|(id, actor, socket)| {
+            tokio::spawn(run(
+                id,
+                actor,
+                socket,
+                Arc::clone(&addresses),
+                serialize,
+                deserialize,
+                stopped.clone(),
+            ))
+        })
+        .collect();
+    Ok(AsyncSpawnHandle { shutdown, tasks })
+}
+
+/// The addresses of an [`AddressBook`], resolved ahead of time so that sending and receiving
+/// never wait for a lookup.
+struct ResolvedAddresses {
+    addrs: HashMap<Id, SocketAddr>,
+    ids: HashMap<SocketAddr, Id>,
+}
+
+impl ResolvedAddresses {
+    fn new(addresses: AddressBook, ids: Vec<Id>) -> io::Result<Self> {
+        let mut addrs = HashMap::new();
+        for id in addresses.ids().chain(ids) {
+            addrs.insert(id, addresses.resolve(id)?);
+        }
+        let ids = addrs.iter().map(|(id, addr)| (*addr, *id)).collect();
+        Ok(ResolvedAddresses { addrs, ids })
+    }
+
+    /// Falls back to the IPv4 address encoded by an [`Id`] without an entry.
+    fn addr_of(&self, id: Id) -> SocketAddr {
+        match self.addrs.get(&id) {
+            Some(addr) => *addr,
+            None => SocketAddrV4::from(id).into(),
+        }
+    }
+
+    /// Returns `None` for an IPv6 address without an entry, which no [`Id`] can encode.
+    fn id_of(&self, addr: SocketAddr) -> Option<Id> {
+        match (self.ids.get(&addr), addr) {
+            (Some(id), _) => Some(*id),
+            (None, SocketAddr::V4(addr)) => Some(Id::from(addr)),
+            (None, SocketAddr::V6(_)) => None,
+        }
+    }
+}
+
+/// A handle to actors started by [`spawn_async`]. Dropping the handle leaves them running.
+pub struct AsyncSpawnHandle {
+    shutdown: watch::Sender<bool>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl AsyncSpawnHandle {
+    /// Stops every actor, resolving once their tasks have exited. Returns an error if an actor
+    /// panicked.
+    pub async fn shutdown(self) -> Result<(), JoinError> {
+        let _ = self.shutdown.send(true);
+        self.join().await
+    }
+
+    /// Resolves once every actor's task has exited, which only happens after
+    /// [`AsyncSpawnHandle::shutdown`] or a panic. Returns an error if an actor panicked.
+    pub async fn join(self) -> Result<(), JoinError> {
+        let mut result = Ok(());
+        for task in self.tasks {
+            if let Err(e) = task.await {
+                result = result.and(Err(e));
+            }
+        }
+        result
+    }
+}
+
+async fn run<A, E>(
+    id: Id,
+    actor: A,
+    socket: UdpSocket,
+    addresses: Arc<ResolvedAddresses>,
+    serialize: fn(&A::Msg) -> Result<Vec<u8>, E>,
+    deserialize: fn(&[u8]) -> Result<A::Msg, E>,
+    mut stopped: watch::Receiver<bool>,
+) where
+    A: Actor,
+    A::Msg: Debug,
+    A::State: Debug,
+    E: Debug,
+{
+    let mut in_buf = vec![0; 65_535];
+    let mut next_interrupts = HashMap::new();
+    let mut send = |dst: Id, bytes: &[u8]| {
+        // UDP is best-effort, so a full send buffer is treated like a lost datagram.
+        socket.try_send_to(bytes, addresses.addr_of(dst))?;
+        Ok(())
+    };
+
+    let mut out = Out::new();
+    let mut state = Cow::Owned(actor.on_start(id, &mut out));
+    log::info!("Actor started. id={}, state={:?}, out={:?}", id, state, out);
+    for c in out {
+        on_command::<A, E>(id, c, serialize, &mut send, &mut next_interrupts);
+    }
+
+    loop {
+        // Apply an interrupt if present, otherwise wait for a message or shutdown.
+        let mut out = Out::new();
+        let (min_timer, min_instant) = next_interrupts
+            .iter()
+            .min_by_key(|(_, instant)| *instant)
+            .map(|(t, i)| (Some(t.clone()), *i))
+            .unwrap_or_else(|| (None, practically_never()));
+        if min_instant <= Instant::now() {
+            let min_timer = min_timer.unwrap();
+            next_interrupts.remove(&min_timer); // timer is no longer valid
+            match &min_timer {
+                Interrupt::Timeout(min_timer) => {
+                    actor.on_timeout(id, &mut state, min_timer, &mut out);
+                }
+                Interrupt::Random(random) => {
+                    actor.on_random(id, &mut state, random, &mut out);
+                }
+            }
+        } else {
+            let (count, src_addr) = tokio::select! {
+                _ = stopped.wait_for(|stopped| *stopped) => {
+                    log::info!("Actor stopped. id={}, state={:?}", id, state);
+                    return;
+                }
+                // Next iteration will apply the interrupt.
+                _ = tokio::time::sleep_until(min_instant.into()) => continue,
+                received = socket.recv_from(&mut in_buf) => match received {
+                    Ok(received) => received,
+                    Err(e) => {
+                        log::warn!("Unable to read socket. Ignoring. id={}, err={:?}", id, e);
+                        continue;
+                    }
+                },
+            };
+            match (addresses.id_of(src_addr), deserialize(&in_buf[..count])) {
+                (None, _) => {
+                    log::debug!(
+                        "Received message from unknown address. Ignoring. id={}, src={}",
+                        id,
+                        src_addr
+                    );
+                    continue;
+                }
+                (Some(src), Ok(msg)) => {
+                    log::info!("Received message. id={}, src={}, msg={:?}", id, src, msg);
+                    actor.on_msg(id, &mut state, src, msg, &mut out);
+                }
+                (Some(_), Err(e)) => {
+                    log::debug!(
+                        "Unable to parse message. Ignoring. id={}, src={}, buf={:?}, err={:?}",
+                        id,
+                        src_addr,
+                        &in_buf[..count],
+                        e
+                    );
+                    continue;
+                }
+            }
+        }
+
+        // Handle commands and update state.
+        if !is_no_op(&state, &out) {
+            log::debug!("Acted. id={}, state={:?}, out={:?}", id, state, out);
+        }
+        for c in out {
+            on_command::<A, E>(id, c, serialize, &mut send, &mut next_interrupts);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    /// Pings the actor with the `Id` if present, counting its pongs.
+    struct Pinger(Option<Id>, &'static AtomicUsize);
+
+    impl Actor for Pinger {
+        type Msg = u8;
+        type State = ();
+        type Timer = ();
+        type Random = ();
+
+        fn on_start(&self, _id: Id, o: &mut Out<Self>) {
+            if self.0.is_some() {
+                o.set_timer((), Duration::ZERO..Duration::ZERO);
+            }
+        }
+
+        fn on_msg(&self, _id: Id, _state: &mut Cow<()>, src: Id, msg: u8, o: &mut Out<Self>) {
+            match msg {
+                0 => o.send(src, 1),
+                _ => {
+                    self.1.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+        }
+
+        fn on_timeout(&self, _id: Id, _state: &mut Cow<()>, _timer: &(), o: &mut Out<Self>) {
+            o.send(self.0.unwrap(), 0);
+            o.set_timer((), Duration::from_millis(5)..Duration::from_millis(5));
+        }
+    }
+
+    fn free_id() -> Id {
+        let socket = std::net::UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        match socket.local_addr().unwrap() {
+            SocketAddr::V4(addr) => Id::from(addr),
+            SocketAddr::V6(_) => unreachable!(),
+        }
+    }
+
+    async fn await_pongs(pongs: &AtomicUsize) {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while pongs.load(Ordering::SeqCst) < 3 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn can_exchange_messages_and_shut_down() {
+        static PONG_COUNT: AtomicUsize = AtomicUsize::new(0);
+        let (ponger, pinger) = (free_id(), free_id());
+        let handle = spawn_async(
+            |msg: &u8| Ok::<_, ()>(vec![*msg]),
+            |bytes| bytes.first().copied().ok_or(()),
+            vec![
+                (ponger, Pinger(None, &PONG_COUNT)),
+                (pinger, Pinger(Some(ponger), &PONG_COUNT)),
+            ],
+        )
+        .await
+        .unwrap();
+        await_pongs(&PONG_COUNT).await;
+        handle.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn can_use_address_book_for_ipv6() {
+        static PONG_COUNT: AtomicUsize = AtomicUsize::new(0);
+        let free_port = || {
+            let socket = std::net::UdpSocket::bind((Ipv6Addr::LOCALHOST, 0)).unwrap();
+            socket.local_addr().unwrap().port()
+        };
+        let (ponger, pinger) = (Id::from(0), Id::from(1));
+        let addresses = AddressBook::new()
+            .address(ponger, format!("[::1]:{}", free_port()))
+            .address(pinger, format!("[::1]:{}", free_port()));
+        let handle = spawn_async_with(
+            |msg: &u8| Ok::<_, ()>(vec![*msg]),
+            |bytes| bytes.first().copied().ok_or(()),
+            addresses,
+            vec![
+                (ponger, Pinger(None, &PONG_COUNT)),
+                (pinger, Pinger(Some(ponger), &PONG_COUNT)),
+            ],
+        )
+        .await
+        .unwrap();
+        await_pongs(&PONG_COUNT).await;
+        handle.shutdown().await.unwrap();
+    }
+}
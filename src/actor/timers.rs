@@ -1,11 +1,24 @@
-use crate::{util::HashableHashSet, Rewrite, RewritePlan};
+use crate::util::{HashableHashMap, HashableHashSet};
+use crate::{Rewrite, RewritePlan};
 use std::hash::Hash;
+use std::ops::Range;
+use std::time::Duration;
 
 use super::Id;
 
 /// A collection of timers that have been set for a given actor.
-#[derive(Clone, Debug, Hash, PartialEq, Eq, serde::Serialize)]
-pub struct Timers<T: Hash + Eq>(HashableHashSet<T>);
+///
+/// Timers set via [`Timers::set_within`] also track the window in which they can fire, relative
+/// to the current time. See [`ActorModel::timed_timers`](crate::actor::ActorModel::timed_timers).
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Timers<T: Hash + Eq>(HashableHashSet<T>, HashableHashMap<T, Range<Duration>>);
+
+// Windows are omitted so that the serialized form remains the set of timers.
+impl<T: Hash + Eq + serde::Serialize> serde::Serialize for Timers<T> {
+    fn serialize<S: serde::Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(ser)
+    }
+}
 
 impl<T: Hash + Eq> Default for Timers<T> {
     fn default() -> Self {
@@ -19,7 +32,7 @@ where
 {
     /// Create a new timer set.
     pub fn new() -> Self {
-        Self(HashableHashSet::new())
+        Self(HashableHashSet::new(), HashableHashMap::new())
     }
 
     /// Set a timer.
@@ -27,14 +40,45 @@ where
         self.0.insert(timer)
     }
 
+    /// Set a timer that fires within the specified window, relative to the current time.
+    pub fn set_within(&mut self, timer: T, window: Range<Duration>) -> bool
+    where
+        T: Clone,
+    {
+        self.1.insert(timer.clone(), window);
+        self.0.insert(timer)
+    }
+
     /// Cancel a timer.
     pub fn cancel(&mut self, timer: &T) -> bool {
+        self.1.remove(timer);
         self.0.remove(timer)
     }
 
     /// Cancels all timers.
     pub fn cancel_all(&mut self) {
         self.0.clear();
+        self.1.clear();
+    }
+
+    /// The window in which a timer set via [`Timers::set_within`] can fire, relative to the
+    /// current time.
+    pub fn window(&self, timer: &T) -> Option<&Range<Duration>> {
+        self.1.get(timer)
+    }
+
+    /// The latest time by which some timer must fire, relative to the current time.
+    pub fn deadline(&self) -> Option<Duration> {
+        self.1.values().map(|w| w.start.max(w.end)).min()
+    }
+
+    /// Advances the current time by an unknown amount within `elapsed`, widening every window
+    /// to cover the possible outcomes.
+    pub fn advance(&mut self, elapsed: Range<Duration>) {
+        for window in self.1.values_mut() {
+            *window =
+                window.start.saturating_sub(elapsed.end)..window.end.saturating_sub(elapsed.start);
+        }
     }
 
     /// Iterate through the currently set timers.
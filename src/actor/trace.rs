@@ -0,0 +1,520 @@
+//! Private module for selective re-export.
+
+use crate::actor::{Actor, ActorModel, ActorModelAction, Command, Id, Out};
+use crate::Model;
+use parking_lot::Mutex;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt::{Debug, Display, Formatter};
+use std::fs::File;
+use std::hash::Hash;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+/// One step taken by a spawned actor, as recorded by a [`TraceRecorder`]. Each event captures
+/// the input, the actor's resulting state, and the messages it sent.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TraceEvent<Msg, Timer, Random, State> {
+    /// The actor started.
+    Start {
+        id: Id,
+        state: State,
+        sends: Vec<(Id, Msg)>,
+    },
+    /// The actor received a message.
+    Msg {
+        id: Id,
+        src: Id,
+        msg: Msg,
+        state: State,
+        sends: Vec<(Id, Msg)>,
+    },
+    /// A timer fired.
+    Timeout {
+        id: Id,
+        timer: Timer,
+        state: State,
+        sends: Vec<(Id, Msg)>,
+    },
+    /// A random choice was made.
+    Random {
+        id: Id,
+        random: Random,
+        state: State,
+        sends: Vec<(Id, Msg)>,
+    },
+}
+
+/// The [`TraceEvent`] type for an [`Actor`].
+pub type ActorTraceEvent<A> =
+    TraceEvent<<A as Actor>::Msg, <A as Actor>::Timer, <A as Actor>::Random, <A as Actor>::State>;
+
+/// Records the steps of spawned actors as newline-delimited JSON, which [`read_trace`] parses
+/// for [`ActorModel::validate_trace`]. Clones share the same output, so one recorder can wrap
+/// every actor of a system to capture a single interleaving.
+///
+/// # Example
+///
+/// ```no_run
+/// use stateright::actor::{Id, TraceRecorder, spawn};
+/// use std::net::{Ipv4Addr, SocketAddrV4};
+/// # mod serde_json {
+/// #     pub fn to_vec(_: &()) -> Result<Vec<u8>, ()> { Ok(vec![]) }
+/// #     pub fn from_slice(_: &[u8]) -> Result<(), ()> { Ok(()) }
+/// # }
+/// # let actor1 = ();
+/// # let actor2 = ();
+/// let recorder = TraceRecorder::create("trace.jsonl").unwrap();
+/// let id1 = Id::from(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 3001));
+/// let id2 = Id::from(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 3002));
+/// spawn(
+///     serde_json::to_vec,
+///     |bytes| serde_json::from_slice(bytes),
+///     vec![
+///         (id1, recorder.record(actor1)),
+///         (id2, recorder.record(actor2)),
+///     ]).unwrap().join().unwrap();
+/// ```
+#[derive(Clone)]
+pub struct TraceRecorder {
+    output: Arc<Mutex<Box<dyn Write + Send>>>,
+}
+
+impl TraceRecorder {
+    /// Records to the specified output.
+    pub fn new(output: impl Write + Send + 'static) -> Self {
+        TraceRecorder {
+            output: Arc::new(Mutex::new(Box::new(output))),
+        }
+    }
+
+    /// Records to a newly created file at the specified path.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+
+    /// Wraps an actor such that its steps are recorded.
+    pub fn record<A: Actor>(&self, actor: A) -> RecordedActor<A> {
+        RecordedActor {
+            actor,
+            recorder: self.clone(),
+        }
+    }
+
+    fn write<Msg, Timer, Random, State>(&self, event: TraceEvent<Msg, Timer, Random, State>)
+    where
+        TraceEvent<Msg, Timer, Random, State>: Serialize,
+    {
+        // Flushed per event so that the trace survives the process being killed.
+        let mut output = self.output.lock();
+        let result = serde_json::to_writer(&mut *output, &event)
+            .map_err(io::Error::from)
+            .and_then(|()| output.write_all(b"\n"))
+            .and_then(|()| output.flush());
+        if let Err(e) = result {
+            log::warn!("Unable to record trace event. Ignoring. err={:?}", e);
+        }
+    }
+}
+
+/// An [`Actor`] wrapped by [`TraceRecorder::record`]. Its events are recorded before the runtime
+/// processes the resulting commands, so a message is always sent before it is received in the
+/// trace.
+pub struct RecordedActor<A> {
+    actor: A,
+    recorder: TraceRecorder,
+}
+
+fn sends_in<A: Actor>(out: &Out<A>) -> Vec<(Id, A::Msg)> {
+    out.iter()
+        .filter_map(|c| match c {
+            Command::Send(dst, msg) => Some((*dst, msg.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+impl<A> Actor for RecordedActor<A>
+where
+    A: Actor,
+    A::Msg: Serialize,
+    A::Timer: Serialize,
+    A::Random: Serialize,
+    A::State: Serialize,
+{
+    type Msg = A::Msg;
+    type State = A::State;
+    type Timer = A::Timer;
+    type Random = A::Random;
+
+    fn on_start(&self, id: Id, o: &mut Out<Self>) -> Self::State {
+        let mut o_prime = Out::new();
+        let state = self.actor.on_start(id, &mut o_prime);
+        self.recorder.write(ActorTraceEvent::<A>::Start {
+            id,
+            state: state.clone(),
+            sends: sends_in(&o_prime),
+        });
+        o.append(&mut o_prime);
+        state
+    }
+
+    fn on_msg(
+        &self,
+        id: Id,
+        state: &mut Cow<Self::State>,
+        src: Id,
+        msg: Self::Msg,
+        o: &mut Out<Self>,
+    ) {
+        let mut o_prime = Out::new();
+        self.actor.on_msg(id, state, src, msg.clone(), &mut o_prime);
+        self.recorder.write(ActorTraceEvent::<A>::Msg {
+            id,
+            src,
+            msg,
+            state: (**state).clone(),
+            sends: sends_in(&o_prime),
+        });
+        o.append(&mut o_prime);
+    }
+
+    fn on_timeout(
+        &self,
+        id: Id,
+        state: &mut Cow<Self::State>,
+        timer: &Self::Timer,
+        o: &mut Out<Self>,
+    ) {
+        let mut o_prime = Out::new();
+        self.actor.on_timeout(id, state, timer, &mut o_prime);
+        self.recorder.write(ActorTraceEvent::<A>::Timeout {
+            id,
+            timer: timer.clone(),
+            state: (**state).clone(),
+            sends: sends_in(&o_prime),
+        });
+        o.append(&mut o_prime);
+    }
+
+    fn on_random(
+        &self,
+        id: Id,
+        state: &mut Cow<Self::State>,
+        random: &Self::Random,
+        o: &mut Out<Self>,
+    ) {
+        let mut o_prime = Out::new();
+        self.actor.on_random(id, state, random, &mut o_prime);
+        self.recorder.write(ActorTraceEvent::<A>::Random {
+            id,
+            random: random.clone(),
+            state: (**state).clone(),
+            sends: sends_in(&o_prime),
+        });
+        o.append(&mut o_prime);
+    }
+
+    fn name(&self) -> String {
+        self.actor.name()
+    }
+}
+
+/// Parses a trace written by a [`TraceRecorder`].
+pub fn read_trace<Msg, Timer, Random, State>(
+    input: impl BufRead,
+) -> io::Result<Vec<TraceEvent<Msg, Timer, Random, State>>>
+where
+    TraceEvent<Msg, Timer, Random, State>: DeserializeOwned,
+{
+    let mut trace = Vec::new();
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        trace.push(serde_json::from_str(&line)?);
+    }
+    Ok(trace)
+}
+
+/// Indicates the first step of a trace that the model does not permit. Returned by
+/// [`ActorModel::validate_trace`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceViolation {
+    /// The index of the offending event within the trace.
+    pub step: usize,
+    /// Why the model does not permit the event.
+    pub reason: String,
+}
+
+impl Display for TraceViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "trace step {} is impossible: {}", self.step, self.reason)
+    }
+}
+
+impl std::error::Error for TraceViolation {}
+
+impl<A, C, H> ActorModel<A, C, H>
+where
+    A: Actor,
+    H: Clone + Debug + Hash,
+{
+    /// Replays a trace recorded from spawned actors through the model, returning the first step
+    /// that the model deems impossible. A step is impossible if the model does not enable the
+    /// corresponding action, or if the actor's recorded state or sends differ from what the model
+    /// computes.
+    ///
+    /// `ids` lists the [`Id`] each actor was spawned with, in the same order as
+    /// [`ActorModel::actors`], and is used to translate sources and destinations. `Id`s embedded
+    /// within messages or states are not translated, so spawning with `Id`s that match the model
+    /// (for example via a [`ChannelNetwork`](crate::actor::ChannelNetwork)) avoids spurious
+    /// violations.
+    pub fn validate_trace(
+        &self,
+        ids: &[Id],
+        trace: &[ActorTraceEvent<A>],
+    ) -> Result<(), TraceViolation> {
+        let translate = |id: Id| ids.iter().position(|&i| i == id).map(Id::from);
+        let to_model = |step: usize, id: Id| {
+            translate(id).ok_or_else(|| TraceViolation {
+                step,
+                reason: format!("{} is not an actor in the model", id),
+            })
+        };
+        // Sends to recipients outside the model, such as clients, are compared untranslated.
+        let to_model_sends = |sends: &[(Id, A::Msg)]| {
+            sends
+                .iter()
+                .map(|(dst, msg)| (translate(*dst).unwrap_or(*dst), msg.clone()))
+                .collect::<Vec<_>>()
+        };
+
+        let mut state = self.init_states().remove(0);
+        for (step, event) in trace.iter().enumerate() {
+            let violation = |reason: String| TraceViolation { step, reason };
+            let (id, action, recorded_state, recorded_sends) = match event {
+                TraceEvent::Start { id, state, sends } => {
+                    // Every actor starts in the initial state, so there is no action to take.
+                    let id = to_model(step, *id)?;
+                    let mut out = Out::new();
+                    let expected_state = self.actors[usize::from(id)].on_start(id, &mut out);
+                    if &expected_state != state {
+                        return Err(violation(format!(
+                            "{} started in {:?} rather than {:?}",
+                            id, state, expected_state
+                        )));
+                    }
+                    if to_model_sends(sends) != sends_in(&out) {
+                        return Err(violation(format!(
+                            "{} sent {:?} on start rather than {:?}",
+                            id,
+                            sends,
+                            sends_in(&out)
+                        )));
+                    }
+                    continue;
+                }
+                TraceEvent::Msg {
+                    id,
+                    src,
+                    msg,
+                    state,
+                    sends,
+                } => {
+                    let dst = to_model(step, *id)?;
+                    let action = ActorModelAction::Deliver {
+                        src: to_model(step, *src)?,
+                        dst,
+                        msg: msg.clone(),
+                    };
+                    (dst, action, state, sends)
+                }
+                TraceEvent::Timeout {
+                    id,
+                    timer,
+                    state,
+                    sends,
+                } => {
+                    let id = to_model(step, *id)?;
+                    (
+                        id,
+                        ActorModelAction::Timeout(id, timer.clone()),
+                        state,
+                        sends,
+                    )
+                }
+                TraceEvent::Random {
+                    id,
+                    random,
+                    state: recorded_state,
+                    sends,
+                } => {
+                    // The runtime does not expose the key under which the choice was offered.
+                    let actor = to_model(step, *id)?;
+                    let key = state.random_choices[usize::from(actor)]
+                        .map
+                        .iter()
+                        .find(|(_, choices)| choices.contains(random))
+                        .map(|(key, _)| key.clone())
+                        .unwrap_or_default();
+                    let action = ActorModelAction::SelectRandom {
+                        actor,
+                        key,
+                        random: random.clone(),
+                    };
+                    (actor, action, recorded_state, sends)
+                }
+            };
+
+            let mut actions = Vec::new();
+            self.actions(&state, &mut actions);
+            if !actions.contains(&action) {
+                return Err(violation(format!("{:?} is not enabled", action)));
+            }
+
+            let index = usize::from(id);
+            let mut actor_state = Cow::Borrowed(&*state.actor_states[index]);
+            let mut out = Out::new();
+            match &action {
+                ActorModelAction::Deliver { src, msg, .. } => {
+                    self.actors[index].on_msg(id, &mut actor_state, *src, msg.clone(), &mut out)
+                }
+                ActorModelAction::Timeout(_, timer) => {
+                    self.actors[index].on_timeout(id, &mut actor_state, timer, &mut out)
+                }
+                ActorModelAction::SelectRandom { random, .. } => {
+                    self.actors[index].on_random(id, &mut actor_state, random, &mut out)
+                }
+                _ => unreachable!(),
+            }
+            if &*actor_state != recorded_state {
+                return Err(violation(format!(
+                    "{} transitioned to {:?} rather than {:?}",
+                    id, recorded_state, actor_state
+                )));
+            }
+            if to_model_sends(recorded_sends) != sends_in(&out) {
+                return Err(violation(format!(
+                    "{} sent {:?} rather than {:?}",
+                    id,
+                    recorded_sends,
+                    sends_in(&out)
+                )));
+            }
+
+            // The model omits no-op steps, which leave the system state unchanged.
+            if let Some(next_state) = self.next_state(&state, action) {
+                state = next_state;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::actor::Network;
+
+    /// Counts messages received, replying to each with a decremented hop count.
+    #[derive(Clone)]
+    struct Relay {
+        start_to: Option<Id>,
+    }
+
+    impl Actor for Relay {
+        type Msg = u8;
+        type State = u8;
+        type Timer = ();
+        type Random = ();
+
+        fn on_start(&self, _id: Id, o: &mut Out<Self>) -> u8 {
+            if let Some(dst) = self.start_to {
+                o.send(dst, 2);
+            }
+            0
+        }
+
+        fn on_msg(&self, _id: Id, state: &mut Cow<u8>, src: Id, hops: u8, o: &mut Out<Self>) {
+            *state.to_mut() += 1;
+            if hops > 0 {
+                o.send(src, hops - 1);
+            }
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn model() -> ActorModel<Relay> {
+        ActorModel::new((), ())
+            .actor(Relay {
+                start_to: Some(Id::from(1)),
+            })
+            .actor(Relay { start_to: None })
+            .init_network(Network::new_ordered(vec![]))
+    }
+
+    /// Runs the relay exchange to completion, returning the recorded trace.
+    fn record(ids: [Id; 2]) -> Vec<ActorTraceEvent<Relay>> {
+        let output = SharedOutput::default();
+        let recorder = TraceRecorder::new(output.clone());
+        let actors = [
+            recorder.record(Relay {
+                start_to: Some(ids[1]),
+            }),
+            recorder.record(Relay { start_to: None }),
+        ];
+        let mut out = Out::new();
+        let mut state0 = Cow::Owned(actors[0].on_start(ids[0], &mut out));
+        let mut state1 = Cow::Owned(actors[1].on_start(ids[1], &mut out));
+        actors[1].on_msg(ids[1], &mut state1, ids[0], 2, &mut out);
+        actors[0].on_msg(ids[0], &mut state0, ids[1], 1, &mut out);
+        actors[1].on_msg(ids[1], &mut state1, ids[0], 0, &mut out);
+        let output = output.0.lock();
+        read_trace(&output[..]).unwrap()
+    }
+
+    #[test]
+    fn can_validate_recorded_trace() {
+        let ids = [Id::from(7), Id::from(9)];
+        let trace = record(ids);
+        assert_eq!(trace.len(), 5);
+        assert_eq!(model().validate_trace(&ids, &trace), Ok(()));
+    }
+
+    #[test]
+    fn reports_first_impossible_step() {
+        let ids = [Id::from(0), Id::from(1)];
+        let trace = record(ids);
+
+        // The final message was already delivered, so it cannot be delivered again.
+        let mut redelivered = trace.clone();
+        redelivered.push(trace[4].clone());
+        let violation = model().validate_trace(&ids, &redelivered).unwrap_err();
+        assert_eq!(violation.step, 5);
+        assert!(violation.reason.contains("is not enabled"));
+
+        let mut diverged = trace;
+        if let TraceEvent::Msg { state, .. } = &mut diverged[3] {
+            *state = 42;
+        }
+        let violation = model().validate_trace(&ids, &diverged).unwrap_err();
+        assert_eq!(violation.step, 3);
+        assert!(violation.reason.contains("transitioned to 42"));
+    }
+}
@@ -0,0 +1,726 @@
+//! Private module for selective re-export.
+
+use crate::actor::{AddressBook, Id};
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{
+    IpAddr, Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr, TcpListener, TcpStream, UdpSocket,
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// A serialized message and the [`Id`] of its sender.
+type Received = (Id, Vec<u8>);
+/// The latest connection accepted from each sender, along with a channel that disconnects once
+/// the thread reading that connection exits.
+type Readers = Arc<Mutex<HashMap<Id, (TcpStream, Receiver<()>)>>>;
+
+/// The means by which a spawned actor exchanges serialized messages with its peers. Each actor
+/// owns one instance, bound to that actor's [`Id`].
+///
+/// See [`spawn_with_transport`](crate::actor::spawn_with_transport).
+pub trait Transport: Send {
+    /// The underlying address at which an actor is reachable.
+    type Address: Debug;
+
+    /// Resolves the underlying address of the actor with the specified [`Id`].
+    fn resolve(&self, id: Id) -> io::Result<Self::Address>;
+
+    /// Sends a serialized message to the actor with the specified [`Id`].
+    fn send(&mut self, dst: Id, bytes: &[u8]) -> io::Result<()>;
+
+    /// Waits up to `timeout` for a serialized message, returning `None` if none arrived in time.
+    fn recv(&mut self, timeout: Duration) -> io::Result<Option<(Id, Vec<u8>)>>;
+}
+
+/// How long a [`UdpTransport`] waits before retrying an address that failed to resolve.
+const RESOLVE_RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Best-effort datagram delivery with no ordering. Messages are limited to 65,535 bytes.
+pub struct UdpTransport {
+    socket: UdpSocket,
+    in_buf: Vec<u8>,
+    addresses: AddressBook,
+    resolved: HashMap<Id, SocketAddr>,
+    ids: HashMap<SocketAddr, Id>,
+    failed: HashMap<Id, Instant>,
+}
+
+impl UdpTransport {
+    /// Binds a socket at the address encoded by the specified [`Id`].
+    pub fn bind(id: Id) -> io::Result<Self> {
+        Self::bind_with(id, AddressBook::new())
+    }
+
+    /// Binds a socket at the address of the specified [`Id`] in the [`AddressBook`], which is also
+    /// used to address peers and to identify the senders of incoming messages. Every address in
+    /// the book is resolved here, and addresses that fail to resolve are retried at most every
+    /// few seconds.
+    pub fn bind_with(id: Id, addresses: AddressBook) -> io::Result<Self> {
+        let mut transport = UdpTransport {
+            socket: UdpSocket::bind(addresses.resolve(id)?)?,
+            in_buf: vec![0; 65_535],
+            addresses,
+            resolved: HashMap::new(),
+            ids: HashMap::new(),
+            failed: HashMap::new(),
+        };
+        let ids: Vec<Id> = transport.addresses.ids().collect();
+        for id in ids {
+            if let Err(e) = transport.resolve_cached(id) {
+                log::warn!("Unable to resolve address. id={}, err={:?}", id, e);
+            }
+        }
+        Ok(transport)
+    }
+
+    /// Resolves each address once, as hostnames would otherwise be looked up for every message.
+    fn resolve_cached(&mut self, id: Id) -> io::Result<SocketAddr> {
+        if let Some(addr) = self.resolved.get(&id) {
+            return Ok(*addr);
+        }
+        if let Some(failed_at) = self.failed.get(&id) {
+            if failed_at.elapsed() < RESOLVE_RETRY_INTERVAL {
+                return Err(io::Error::new(
+                    ErrorKind::NotFound,
+                    format!("address of {} recently failed to resolve", id),
+                ));
+            }
+        }
+        match self.addresses.resolve(id) {
+            Ok(addr) => {
+                self.failed.remove(&id);
+                self.resolved.insert(id, addr);
+                self.ids.insert(addr, id);
+                Ok(addr)
+            }
+            Err(e) => {
+                self.failed.insert(id, Instant::now());
+                Err(e)
+            }
+        }
+    }
+
+    fn id_of(&mut self, addr: SocketAddr) -> Option<Id> {
+        if !self.ids.contains_key(&addr) {
+            // The sender may be a peer whose address failed to resolve earlier.
+            let retryable: Vec<Id> = self
+                .failed
+                .iter()
+                .filter(|(_, failed_at)| failed_at.elapsed() >= RESOLVE_RETRY_INTERVAL)
+                .map(|(id, _)| *id)
+                .collect();
+            for id in retryable {
+                if let Err(e) = self.resolve_cached(id) {
+                    log::debug!("Unable to resolve address. id={}, err={:?}", id, e);
+                }
+            }
+        }
+        match (self.ids.get(&addr), addr) {
+            (Some(id), _) => Some(*id),
+            (None, SocketAddr::V4(addr)) => Some(Id::from(addr)),
+            (None, SocketAddr::V6(_)) => None,
+        }
+    }
+}
+
+impl Transport for UdpTransport {
+    type Address = SocketAddr;
+
+    fn resolve(&self, id: Id) -> io::Result<SocketAddr> {
+        match self.resolved.get(&id) {
+            Some(addr) => Ok(*addr),
+            None => self.addresses.resolve(id),
+        }
+    }
+
+    fn send(&mut self, dst: Id, bytes: &[u8]) -> io::Result<()> {
+        let addr = self.resolve_cached(dst)?;
+        self.socket.send_to(bytes, addr)?;
+        Ok(())
+    }
+
+    fn recv(&mut self, timeout: Duration) -> io::Result<Option<(Id, Vec<u8>)>> {
+        if timeout.is_zero() {
+            return Ok(None); // a zero read timeout is rejected by the socket
+        }
+        self.socket.set_read_timeout(Some(timeout))?;
+        match self.socket.recv_from(&mut self.in_buf) {
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => Ok(None),
+            Err(e) => Err(e),
+            Ok((count, src_addr)) => match self.id_of(src_addr) {
+                Some(src) => Ok(Some((src, self.in_buf[..count].to_vec()))),
+                None => {
+                    log::debug!(
+                        "Received message from unknown address. Ignoring. src={}",
+                        src_addr
+                    );
+                    Ok(None)
+                }
+            },
+        }
+    }
+}
+
+/// Delivery that preserves the order of messages sent from one actor to another, matching
+/// [`Network::Ordered`](crate::actor::Network::Ordered).
+///
+/// Each frame is a 4-byte big-endian length followed by that many bytes, so message size is not
+/// bounded by a datagram, although frames over 16 MiB are rejected. The first frame on a
+/// connection carries the sender's [`Id`] because the connection originates from an ephemeral
+/// port. Outgoing connections are reused for subsequent messages to the same destination and are
+/// reestablished once if a send fails, and connecting or writing fails after 5 seconds. Upon
+/// accepting a new connection from a sender, the receiver closes the previous one and waits for
+/// its frames to be forwarded before forwarding any from the new one, so messages are never
+/// reordered, although messages in flight when a connection fails are lost. The listener is
+/// closed when the transport is dropped.
+pub struct TcpTransport {
+    id: Id,
+    addresses: AddressBook,
+    connections: HashMap<Id, TcpStream>,
+    inbox: Receiver<Received>,
+    /// Stops accepting connections when dropped.
+    _listener: Listener,
+}
+
+/// The thread accepting connections for a [`TcpTransport`].
+struct Listener {
+    addr: SocketAddr,
+    is_shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl TcpTransport {
+    /// Listens at the address encoded by the specified [`Id`].
+    pub fn bind(id: Id) -> io::Result<Self> {
+        Self::bind_with(id, AddressBook::new())
+    }
+
+    /// Listens at the address of the specified [`Id`] in the [`AddressBook`], which is also used
+    /// to connect to peers.
+    pub fn bind_with(id: Id, addresses: AddressBook) -> io::Result<Self> {
+        let listener = TcpListener::bind(addresses.resolve(id)?)?;
+        Self::from_listener(id, addresses, listener)
+    }
+
+    fn from_listener(id: Id, addresses: AddressBook, listener: TcpListener) -> io::Result<Self> {
+        let (sender, inbox) = crossbeam_channel::unbounded();
+        let addr = listener.local_addr()?;
+        let is_shutdown = Arc::new(AtomicBool::new(false));
+        let readers = Readers::default();
+        let thread = {
+            let is_shutdown = Arc::clone(&is_shutdown);
+            std::thread::Builder::new()
+                .name(format!("tcp-accept-{}", id))
+                .spawn(move || {
+                    for stream in listener.incoming() {
+                        if is_shutdown.load(Ordering::Relaxed) {
+                            return; // dropping the listener frees the port
+                        }
+                        match stream {
+                            Ok(stream) => {
+                                let sender = sender.clone();
+                                let readers = Arc::clone(&readers);
+                                std::thread::spawn(move || {
+                                    read_frames(id, stream, sender, readers)
+                                });
+                            }
+                            Err(e) => {
+                                log::warn!(
+                                    "Unable to accept connection. Ignoring. id={}, err={:?}",
+                                    id,
+                                    e
+                                );
+                            }
+                        }
+                    }
+                })?
+        };
+        Ok(TcpTransport {
+            id,
+            addresses,
+            connections: HashMap::new(),
+            inbox,
+            _listener: Listener {
+                addr,
+                is_shutdown,
+                thread: Some(thread),
+            },
+        })
+    }
+
+    fn connect(&self, dst: Id) -> io::Result<TcpStream> {
+        let mut stream = TcpStream::connect_timeout(&self.resolve(dst)?, TCP_TIMEOUT)?;
+        stream.set_nodelay(true)?;
+        stream.set_write_timeout(Some(TCP_TIMEOUT))?;
+        write_frame(&mut stream, &self.id.0.to_be_bytes())?;
+        Ok(stream)
+    }
+}
+
+impl Transport for TcpTransport {
+    type Address = SocketAddr;
+
+    fn resolve(&self, id: Id) -> io::Result<SocketAddr> {
+        self.addresses.resolve(id)
+    }
+
+    fn send(&mut self, dst: Id, bytes: &[u8]) -> io::Result<()> {
+        if let Some(stream) = self.connections.get_mut(&dst) {
+            match write_frame(stream, bytes) {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    log::debug!(
+                        "Connection failed. Reconnecting. src={}, dst={}, err={:?}",
+                        self.id,
+                        dst,
+                        e
+                    );
+                    self.connections.remove(&dst);
+                }
+            }
+        }
+        let mut stream = self.connect(dst)?;
+        write_frame(&mut stream, bytes)?;
+        self.connections.insert(dst, stream);
+        Ok(())
+    }
+
+    fn recv(&mut self, timeout: Duration) -> io::Result<Option<(Id, Vec<u8>)>> {
+        recv_timeout(&self.inbox, timeout)
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        self.is_shutdown.store(true, Ordering::Relaxed);
+
+        // Wakes the thread, which is blocked until the next connection.
+        let mut addr = self.addr;
+        if addr.ip().is_unspecified() {
+            addr.set_ip(match addr {
+                SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+                SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            });
+        }
+        match TcpStream::connect(addr) {
+            Ok(_) => {
+                if let Some(thread) = self.thread.take() {
+                    let _ = thread.join();
+                }
+            }
+            Err(e) => {
+                log::warn!(
+                    "Unable to stop accepting connections. addr={}, err={:?}",
+                    addr,
+                    e
+                );
+            }
+        }
+    }
+}
+
+/// An in-process network of [`ChannelTransport`]s, which lets actors spawned within a single
+/// process communicate without binding ports. Delivery is reliable and ordered between each pair
+/// of actors. Clones share the same network.
+///
+/// # Example
+///
+/// ```no_run
+/// use stateright::actor::{ChannelNetwork, Id, spawn_with_transport};
+/// # mod serde_json {
+/// #     pub fn to_vec(_: &()) -> Result<Vec<u8>, ()> { Ok(vec![]) }
+/// #     pub fn from_slice(_: &[u8]) -> Result<(), ()> { Ok(()) }
+/// # }
+/// # let actor1 = ();
+/// # let actor2 = ();
+/// let network = ChannelNetwork::new();
+/// spawn_with_transport(
+///     |id| network.bind(id),
+///     serde_json::to_vec,
+///     |bytes| serde_json::from_slice(bytes),
+///     vec![
+///         (Id::from(0), actor1),
+///         (Id::from(1), actor2),
+///     ]).unwrap().join().unwrap();
+/// ```
+#[derive(Clone, Default)]
+pub struct ChannelNetwork {
+    inboxes: Arc<Mutex<HashMap<Id, Sender<Received>>>>,
+}
+
+impl ChannelNetwork {
+    /// Constructs an empty network.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Registers an inbox for the actor with the specified [`Id`]. Fails if another transport
+    /// is already bound to that [`Id`].
+    pub fn bind(&self, id: Id) -> io::Result<ChannelTransport> {
+        let mut inboxes = self.inboxes.lock();
+        if inboxes.contains_key(&id) {
+            return Err(io::Error::new(
+                ErrorKind::AddrInUse,
+                format!("{:?} is already bound", id),
+            ));
+        }
+        let (sender, inbox) = crossbeam_channel::unbounded();
+        inboxes.insert(id, sender);
+        Ok(ChannelTransport {
+            id,
+            network: self.clone(),
+            inbox,
+        })
+    }
+}
+
+/// A [`Transport`] bound by [`ChannelNetwork::bind`]. Unbinds its [`Id`] when dropped.
+pub struct ChannelTransport {
+    id: Id,
+    network: ChannelNetwork,
+    inbox: Receiver<Received>,
+}
+
+impl Transport for ChannelTransport {
+    type Address = Id;
+
+    fn resolve(&self, id: Id) -> io::Result<Id> {
+        Ok(id)
+    }
+
+    fn send(&mut self, dst: Id, bytes: &[u8]) -> io::Result<()> {
+        let inboxes = self.network.inboxes.lock();
+        let sender = inboxes.get(&dst).ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::ConnectionRefused,
+                format!("{:?} is not bound", dst),
+            )
+        })?;
+        // Cannot be disconnected because the receiver unbinds before it is dropped.
+        let _ = sender.send((self.id, bytes.to_vec()));
+        Ok(())
+    }
+
+    fn recv(&mut self, timeout: Duration) -> io::Result<Option<(Id, Vec<u8>)>> {
+        recv_timeout(&self.inbox, timeout)
+    }
+}
+
+impl Drop for ChannelTransport {
+    fn drop(&mut self) {
+        self.network.inboxes.lock().remove(&self.id);
+    }
+}
+
+/// The largest frame that a [`TcpTransport`] sends or receives, which prevents a corrupt or
+/// malicious length prefix from causing a huge allocation.
+const MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+/// How long a [`TcpTransport`] waits to connect to a peer or to write a frame, which bounds how
+/// long an actor is blocked by an unresponsive peer.
+const TCP_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn write_frame(stream: &mut TcpStream, bytes: &[u8]) -> io::Result<()> {
+    if bytes.len() > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            "message too large for frame",
+        ));
+    }
+    stream.write_all(&(bytes.len() as u32).to_be_bytes())?;
+    stream.write_all(bytes)
+}
+
+/// Returns `None` if the connection closed cleanly between frames.
+fn read_frame(stream: &mut TcpStream) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0; 4];
+    match stream.read_exact(&mut len) {
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        result => result?,
+    }
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("frame length {} exceeds maximum of {}", len, MAX_FRAME_LEN),
+        ));
+    }
+    let mut bytes = vec![0; len];
+    stream.read_exact(&mut bytes)?;
+    Ok(Some(bytes))
+}
+
+/// Returns an error if the inbox has no remaining senders.
+fn recv_timeout(
+    inbox: &Receiver<Received>,
+    timeout: Duration,
+) -> io::Result<Option<(Id, Vec<u8>)>> {
+    match inbox.recv_timeout(timeout) {
+        Ok(received) => Ok(Some(received)),
+        Err(RecvTimeoutError::Timeout) => Ok(None),
+        Err(RecvTimeoutError::Disconnected) => Err(io::Error::new(
+            ErrorKind::NotConnected,
+            "inbox disconnected",
+        )),
+    }
+}
+
+/// Forwards the frames of an accepted connection until it closes or is replaced by a newer
+/// connection from the same sender.
+fn read_frames(id: Id, mut stream: TcpStream, sender: Sender<Received>, readers: Readers) {
+    let src = match read_frame(&mut stream) {
+        Ok(Some(bytes)) if bytes.len() == 8 => Id(u64::from_be_bytes(bytes.try_into().unwrap())),
+        result => {
+            log::debug!(
+                "Invalid connection header. Closing. id={}, result={:?}",
+                id,
+                result
+            );
+            return;
+        }
+    };
+
+    // The sender only reconnects after the previous connection failed, so that connection is
+    // closed, and its frames are forwarded before any from this one.
+    let (_is_running, is_stopped) = crossbeam_channel::bounded::<()>(0);
+    let clone = match stream.try_clone() {
+        Ok(clone) => clone,
+        Err(e) => {
+            log::warn!(
+                "Unable to track connection. Closing. id={}, src={}, err={:?}",
+                id,
+                src,
+                e
+            );
+            return;
+        }
+    };
+    let previous = readers.lock().insert(src, (clone, is_stopped.clone()));
+    if let Some((previous_stream, previous_is_stopped)) = previous {
+        let _ = previous_stream.shutdown(Shutdown::Both);
+        let _ = previous_is_stopped.recv(); // disconnects once the previous reader exits
+    }
+    let _untrack = Untrack {
+        readers,
+        src,
+        is_stopped,
+    };
+
+    loop {
+        match read_frame(&mut stream) {
+            Ok(Some(bytes)) => {
+                if sender.send((src, bytes)).is_err() {
+                    return; // transport dropped
+                }
+            }
+            Ok(None) => return,
+            Err(e) => {
+                log::debug!(
+                    "Unable to read frame. Closing. id={}, src={}, err={:?}",
+                    id,
+                    src,
+                    e
+                );
+                return;
+            }
+        }
+    }
+}
+
+/// Forgets a connection once its reader exits, unless a newer connection replaced it.
+struct Untrack {
+    readers: Readers,
+    src: Id,
+    is_stopped: Receiver<()>,
+}
+
+impl Drop for Untrack {
+    fn drop(&mut self) {
+        let mut readers = self.readers.lock();
+        if let Some((_, is_stopped)) = readers.get(&self.src) {
+            if is_stopped.same_channel(&self.is_stopped) {
+                readers.remove(&self.src);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::SocketAddrV4;
+
+    fn bind_ephemeral() -> TcpTransport {
+        let listener = TcpListener::bind(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let id = match listener.local_addr().unwrap() {
+            SocketAddr::V4(addr) => Id::from(addr),
+            SocketAddr::V6(_) => unreachable!(),
+        };
+        TcpTransport::from_listener(id, AddressBook::new(), listener).unwrap()
+    }
+
+    fn free_port(ip: impl Into<std::net::IpAddr>) -> u16 {
+        let socket = UdpSocket::bind((ip.into(), 0)).unwrap();
+        socket.local_addr().unwrap().port()
+    }
+
+    #[test]
+    fn channel_delivers_in_order() {
+        let network = ChannelNetwork::new();
+        let mut a = network.bind(Id::from(0)).unwrap();
+        let mut b = network.bind(Id::from(1)).unwrap();
+        assert_eq!(
+            network.bind(Id::from(1)).err().map(|e| e.kind()),
+            Some(ErrorKind::AddrInUse)
+        );
+
+        a.send(b.id, b"first").unwrap();
+        a.send(b.id, b"second").unwrap();
+        b.send(a.id, b"reply").unwrap();
+        let timeout = Duration::from_secs(10);
+        assert_eq!(b.recv(timeout).unwrap(), Some((a.id, b"first".to_vec())));
+        assert_eq!(b.recv(timeout).unwrap(), Some((a.id, b"second".to_vec())));
+        assert_eq!(a.recv(timeout).unwrap(), Some((b.id, b"reply".to_vec())));
+        assert_eq!(a.recv(Duration::from_millis(10)).unwrap(), None);
+
+        drop(b);
+        assert_eq!(
+            a.send(Id::from(1), b"unbound").err().map(|e| e.kind()),
+            Some(ErrorKind::ConnectionRefused)
+        );
+    }
+
+    #[test]
+    fn tcp_preserves_order_and_large_messages() {
+        let mut a = bind_ephemeral();
+        let mut b = bind_ephemeral();
+        let large = vec![7; 200_000];
+        a.send(b.id, b"first").unwrap();
+        a.send(b.id, &large).unwrap();
+        a.send(b.id, b"last").unwrap();
+        b.send(a.id, b"reply").unwrap();
+
+        let timeout = Duration::from_secs(10);
+        assert_eq!(b.recv(timeout).unwrap(), Some((a.id, b"first".to_vec())));
+        assert_eq!(b.recv(timeout).unwrap(), Some((a.id, large)));
+        assert_eq!(b.recv(timeout).unwrap(), Some((a.id, b"last".to_vec())));
+        assert_eq!(a.recv(timeout).unwrap(), Some((b.id, b"reply".to_vec())));
+        assert_eq!(a.recv(Duration::from_millis(10)).unwrap(), None);
+        assert_eq!(a.connections.len(), 1);
+    }
+
+    #[test]
+    fn tcp_reconnects_after_connection_loss() {
+        let mut a = bind_ephemeral();
+        let mut b = bind_ephemeral();
+        a.send(b.id, b"before").unwrap();
+        let timeout = Duration::from_secs(10);
+        assert_eq!(b.recv(timeout).unwrap(), Some((a.id, b"before".to_vec())));
+
+        a.connections[&b.id]
+            .shutdown(std::net::Shutdown::Both)
+            .unwrap();
+        a.send(b.id, b"after").unwrap();
+        let received = b.recv(timeout).unwrap().unwrap();
+        assert_eq!(received, (a.id, b"after".to_vec()));
+    }
+
+    #[test]
+    fn tcp_closes_previous_connection_from_reconnected_sender() {
+        let mut a = bind_ephemeral();
+        let mut b = bind_ephemeral();
+        a.send(b.id, b"first").unwrap();
+        let timeout = Duration::from_secs(10);
+        assert_eq!(b.recv(timeout).unwrap(), Some((a.id, b"first".to_vec())));
+
+        // Keeps the previous connection open from the sender's side.
+        let mut previous = a.connections.remove(&b.id).unwrap();
+        a.send(b.id, b"second").unwrap();
+        assert_eq!(b.recv(timeout).unwrap(), Some((a.id, b"second".to_vec())));
+        previous.set_read_timeout(Some(timeout)).unwrap();
+        assert_eq!(previous.read(&mut [0; 1]).unwrap(), 0);
+    }
+
+    #[test]
+    fn tcp_rejects_oversized_frames() {
+        let mut a = bind_ephemeral();
+        let b = bind_ephemeral();
+        assert_eq!(
+            a.send(b.id, &vec![0; MAX_FRAME_LEN + 1])
+                .err()
+                .map(|e| e.kind()),
+            Some(ErrorKind::InvalidInput)
+        );
+
+        let listener = TcpListener::bind(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut server, _) = listener.accept().unwrap();
+        client.write_all(&u32::MAX.to_be_bytes()).unwrap();
+        assert_eq!(
+            read_frame(&mut server).err().map(|e| e.kind()),
+            Some(ErrorKind::InvalidData)
+        );
+    }
+
+    #[test]
+    fn tcp_frees_port_when_dropped() {
+        let a = bind_ephemeral();
+        let addr = SocketAddr::from(SocketAddrV4::from(a.id));
+        drop(a);
+        TcpListener::bind(addr).unwrap();
+    }
+
+    #[test]
+    fn udp_uses_address_book_for_ipv6() {
+        let (id0, id1) = (Id::from(0), Id::from(1));
+        let addresses = AddressBook::new()
+            .address(id0, format!("[::1]:{}", free_port(Ipv6Addr::LOCALHOST)))
+            .address(id1, format!("[::1]:{}", free_port(Ipv6Addr::LOCALHOST)));
+        let mut a = UdpTransport::bind_with(id0, addresses.clone()).unwrap();
+        let mut b = UdpTransport::bind_with(id1, addresses).unwrap();
+        assert!(a.resolve(id1).unwrap().is_ipv6());
+
+        // The receiver identifies the sender before ever addressing it.
+        a.send(id1, b"ping").unwrap();
+        let timeout = Duration::from_secs(10);
+        assert_eq!(b.recv(timeout).unwrap(), Some((id0, b"ping".to_vec())));
+        b.send(id0, b"pong").unwrap();
+        assert_eq!(a.recv(timeout).unwrap(), Some((id1, b"pong".to_vec())));
+    }
+
+    #[test]
+    fn udp_caches_addresses_that_fail_to_resolve() {
+        let (id0, id1) = (Id::from(0), Id::from(1));
+        let addresses = AddressBook::new()
+            .address(id0, format!("127.0.0.1:{}", free_port(Ipv4Addr::LOCALHOST)))
+            .address(id1, "missing-port");
+        let mut a = UdpTransport::bind_with(id0, addresses).unwrap();
+        assert!(a.failed.contains_key(&id1));
+
+        // The failure is reported without resolving again until the retry interval elapses.
+        let err = a.send(id1, b"ping").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn tcp_uses_address_book_for_hostnames() {
+        let (id0, id1) = (Id::from(0), Id::from(1));
+        let addresses = AddressBook::new()
+            .address(id0, format!("localhost:{}", free_port(Ipv4Addr::LOCALHOST)))
+            .address(id1, format!("localhost:{}", free_port(Ipv4Addr::LOCALHOST)));
+        let mut a = TcpTransport::bind_with(id0, addresses.clone()).unwrap();
+        let mut b = TcpTransport::bind_with(id1, addresses).unwrap();
+
+        a.send(id1, b"ping").unwrap();
+        let timeout = Duration::from_secs(10);
+        assert_eq!(b.recv(timeout).unwrap(), Some((id0, b"ping".to_vec())));
+        b.send(id0, b"pong").unwrap();
+        assert_eq!(a.recv(timeout).unwrap(), Some((id1, b"pong".to_vec())));
+    }
+}
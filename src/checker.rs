@@ -1,23 +1,31 @@
 //! Private module for selective re-export.
 
 mod bfs;
+mod checkpoint;
 mod dfs;
+mod distributed;
 mod explorer;
+mod liveness;
 mod on_demand;
 mod path;
 mod representative;
 mod rewrite;
 mod rewrite_plan;
 mod simulation;
+mod swarm;
+mod visited;
 mod visitor;
 
 use crate::has_discoveries::HasDiscoveries;
 use crate::report::{ReportData, ReportDiscovery, Reporter};
-use crate::{Expectation, Fingerprint, Model};
+use crate::{Expectation, Fingerprint, Model, Property};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use std::collections::{BTreeMap, HashMap};
 use std::fmt::{Debug, Display};
 use std::hash::Hash;
 use std::num::NonZeroUsize;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
@@ -27,6 +35,7 @@ pub use representative::*;
 pub use rewrite::*;
 pub use rewrite_plan::*;
 pub use simulation::{Chooser, UniformChooser};
+pub use visited::*;
 pub use visitor::*;
 
 #[derive(Clone, Copy)]
@@ -72,6 +81,10 @@ pub struct CheckerBuilder<M: Model> {
     visitor: Option<Box<dyn CheckerVisitor<M> + Send + Sync>>,
     finish_when: HasDiscoveries,
     timeout: Option<Duration>,
+    partial_order_reduction: bool,
+    checkpoint_to: Option<(PathBuf, Duration)>,
+    resume_from: Option<PathBuf>,
+    visited_store: Box<dyn VisitedStore>,
 }
 impl<M: Model> CheckerBuilder<M> {
     pub(crate) fn new(model: M) -> Self {
@@ -84,6 +97,10 @@ impl<M: Model> CheckerBuilder<M> {
             visitor: None,
             finish_when: HasDiscoveries::All,
             timeout: None,
+            partial_order_reduction: false,
+            checkpoint_to: None,
+            resume_from: None,
+            visited_store: Box::new(InMemoryStore::new()),
         }
     }
 
@@ -155,6 +172,12 @@ impl<M: Model> CheckerBuilder<M> {
     /// checking is single threadeded (the default behavior, which [`CheckerBuilder::threads`]
     /// overrides).
     ///
+    /// [`Property::temporal`] properties are checked once exploration stops against the product
+    /// of the explored state graph and an automaton for each property, so the checker retains the
+    /// successors of every visited state when the model has such a property. Unlike
+    /// [`CheckerBuilder::spawn_liveness`], [`Model::fairness`] constraints are not taken into
+    /// account.
+    ///
     /// This call does not block the current thread. Call [`Checker::join`] to block until checking
     /// completes.
     #[must_use = "Checkers run on background threads. \
@@ -167,9 +190,62 @@ impl<M: Model> CheckerBuilder<M> {
         bfs::BfsChecker::spawn(self)
     }
 
+    /// Spawns the coordinator of a breadth-first search that is distributed across
+    /// `worker_count` processes, each of which calls [`CheckerBuilder::serve_distributed_worker`]
+    /// with the same model. The coordinator listens on the specified address for the workers,
+    /// which split the states by fingerprint and exchange them over TCP in a JSON encoding. The
+    /// returned checker merges the counts and discoveries from every worker.
+    ///
+    /// Unlike [`CheckerBuilder::spawn_bfs`], discoveries do not necessarily have the shortest
+    /// [`Path`]. Symmetry reduction, visitors, and checkpoints are not supported.
+    ///
+    /// This call does not block the current thread. Call [`Checker::join`] to block until checking
+    /// completes.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use stateright::*; let model = || ();
+    /// // On one machine.
+    /// let checker = model().checker().spawn_distributed_bfs("0.0.0.0:3001", 2);
+    ///
+    /// // On each of two other machines.
+    /// model().checker().serve_distributed_worker("coordinator:3001").unwrap();
+    /// ```
+    #[must_use = "Checkers run on background threads. \
+                  Consider calling join() or report(...), for example."]
+    pub fn spawn_distributed_bfs(
+        self,
+        addresses: impl std::net::ToSocketAddrs,
+        worker_count: usize,
+    ) -> impl Checker<M>
+    where
+        M: Model + Send + Sync + 'static,
+        M::State: Hash + Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        self.assert_no_temporal_properties("spawn_distributed_bfs");
+        distributed::DistributedChecker::spawn(self, addresses, worker_count)
+    }
+
+    /// Connects to a coordinator spawned by [`CheckerBuilder::spawn_distributed_bfs`] and checks
+    /// the states that it assigns, blocking until the coordinator indicates that checking is
+    /// complete. The worker is single threaded, and it honors [`CheckerBuilder::target_max_depth`]
+    /// and [`CheckerBuilder::visited_store`].
+    pub fn serve_distributed_worker(
+        self,
+        coordinator: impl std::net::ToSocketAddrs,
+    ) -> std::io::Result<()>
+    where
+        M::State: Hash + Serialize + DeserializeOwned + Send + 'static,
+    {
+        self.assert_no_temporal_properties("serve_distributed_worker");
+        distributed::serve_worker(self, coordinator)
+    }
+
     /// Spawns an on-demand model checker. This traversal strategy doesn't compute any states until
     /// it is asked to, useful for lightweight exploration. Internally the exploration strategy is
-    /// very similar to that of [`CheckerBuilder::spawn_bfs`].
+    /// very similar to that of [`CheckerBuilder::spawn_bfs`], but [`Property::temporal`]
+    /// properties are skipped, as exploration may never stop.
     ///
     /// This call does not block the current thread. Call [`Checker::join`] to block until checking
     /// completes.
@@ -180,12 +256,22 @@ impl<M: Model> CheckerBuilder<M> {
         M: Model + Send + Sync + 'static,
         M::State: Hash + Send + Sync + 'static,
     {
+        for property in self.model.properties() {
+            if property.expectation == Expectation::Temporal {
+                log::warn!(
+                    "Temporal property \"{}\" is not checked by the on-demand checker.",
+                    property.name
+                );
+            }
+        }
         on_demand::OnDemandChecker::spawn(self)
     }
 
     /// Spawns a depth-first search model checker. This traversal strategy uses dramatically less
     /// memory than [`CheckerBuilder::spawn_bfs`] at the cost of not finding the shortest [`Path`]
-    /// to each discovery.
+    /// to each discovery. Like [`CheckerBuilder::spawn_bfs`], it checks [`Property::temporal`]
+    /// properties once exploration stops, in which case symmetry reduction and
+    /// [`CheckerBuilder::partial_order_reduction`] are ignored.
     ///
     /// This call does not block the current thread. Call [`Checker::join`] to block until
     /// checking completes.
@@ -195,10 +281,35 @@ impl<M: Model> CheckerBuilder<M> {
     where
         M: Model + Send + Sync + 'static,
         M::State: Hash + Send + Sync + 'static,
+        M::Action: Hash,
     {
         dfs::DfsChecker::spawn(self)
     }
 
+    /// Spawns a liveness model checker. Like [`CheckerBuilder::spawn_bfs`], this traversal
+    /// strategy checks `always` and `sometimes` properties, but it also retains the full graph of
+    /// visited states so that `eventually` properties are falsified by paths that end in a cycle
+    /// (a "lasso") in addition to paths that end in a terminal state. Cycles that violate one of
+    /// the [`Model::fairness`] constraints are not counterexamples. This is also the only checker
+    /// that takes fairness into account for [`Property::temporal`] properties.
+    ///
+    /// A counterexample that ends in a cycle is reported as a [`Path`] whose last state also
+    /// appears earlier in the path. The checker is single threaded, does not apply symmetry
+    /// reduction, and only finds `eventually` discoveries after exploration stops.
+    ///
+    /// This call does not block the current thread. Call [`Checker::join`] to block until
+    /// checking completes.
+    #[must_use = "Checkers run on background threads. \
+                  Consider calling join() or report(...), for example."]
+    pub fn spawn_liveness(self) -> impl Checker<M>
+    where
+        M: Model + Send + Sync + 'static,
+        M::State: Hash + Send + Sync + 'static,
+        M::Action: Hash,
+    {
+        liveness::LivenessChecker::spawn(self)
+    }
+
     /// Spawns a simulation model checker. This repeatedly traverses the model from initial states
     /// to a terminal state. This aims to provide faster coverage of deep states for models that
     /// cannot practically be checked exhaustively.
@@ -213,9 +324,61 @@ impl<M: Model> CheckerBuilder<M> {
         M::State: Hash + Send + Sync + 'static,
         C: Chooser<M>,
     {
+        self.assert_no_temporal_properties("spawn_simulation");
         simulation::SimulationChecker::spawn::<C>(self, seed, chooser)
     }
 
+    /// Spawns a swarm of `member_count` small depth-first searches rather than one exhaustive
+    /// search. Each member tracks its own visited states and is diversified by a seed (its index)
+    /// that permutes the order in which it explores actions and picks its depth bound between
+    /// half of [`CheckerBuilder::target_max_depth`] and the full target. The
+    /// [`CheckerBuilder::target_state_count`] is split evenly between the members. Members are
+    /// run by the threads specified via [`CheckerBuilder::threads`], and their discoveries and
+    /// counts are merged, with [`Checker::unique_state_count`] counting the states that any
+    /// member visited according to the [`CheckerBuilder::visited_store`].
+    ///
+    /// For models that are too large to check exhaustively, a swarm tends to find discoveries
+    /// much sooner than [`CheckerBuilder::spawn_dfs`], which can spend a long time in one region
+    /// of the state space. Members only differ in their action order and depth bound. Other ways
+    /// of diversifying them, such as varying the search algorithm or the [`Chooser`], are out of
+    /// scope.
+    ///
+    /// Panics unless a target max depth or target state count bounds the members, as each member
+    /// would otherwise search the entire state space.
+    ///
+    /// This call does not block the current thread. Call [`Checker::join`] to block until
+    /// checking completes.
+    #[must_use = "Checkers run on background threads. \
+                  Consider calling join() or report(...), for example."]
+    pub fn spawn_swarm(self, member_count: usize) -> impl Checker<M>
+    where
+        M: Model + Send + Sync + 'static,
+        M::State: Hash + Send + Sync + 'static,
+    {
+        self.assert_no_temporal_properties("spawn_swarm");
+        assert!(
+            self.target_max_depth.is_some() || self.target_state_count.is_some(),
+            "`spawn_swarm` requires a `target_max_depth` or `target_state_count` to bound its \
+             members."
+        );
+        swarm::SwarmChecker::spawn(self, member_count)
+    }
+
+    /// Panics if the model has a [`Property::temporal`] property, as only the BFS, DFS, and
+    /// liveness checkers evaluate the automaton product that those properties require. Otherwise
+    /// they would be silently skipped and appear to hold.
+    fn assert_no_temporal_properties(&self, checker: &str) {
+        for property in self.model.properties() {
+            assert!(
+                property.expectation != Expectation::Temporal,
+                "Temporal property \"{}\" is only checked by `spawn_bfs`, `spawn_dfs`, and \
+                 `spawn_liveness`, not `{}`.",
+                property.name,
+                checker
+            );
+        }
+    }
+
     /// Enables symmetry reduction. Requires the [model state] to implement [`Representative`].
     ///
     /// [model state]: crate::Model::State
@@ -236,6 +399,69 @@ impl<M: Model> CheckerBuilder<M> {
         }
     }
 
+    /// Enables partial order reduction for [`CheckerBuilder::spawn_dfs`]. The checker tracks a
+    /// "sleep set" of [independent](Model::independent) actions for each state so that it can
+    /// skip interleavings that are equivalent to ones it has already explored. Every reachable
+    /// state is still visited as long as [`Model::independent`] is accurate, but fewer states are
+    /// generated along the way, as indicated by [`Checker::pruned_state_count`].
+    ///
+    /// Sleep sets are kept in memory for visited states that were reached with a nonempty sleep
+    /// set, so memory usage is not fully bounded by the [`CheckerBuilder::visited_store`].
+    /// Transition properties are not checked for skipped steps, and this option is ignored if
+    /// symmetry reduction is also enabled or if the model has temporal properties.
+    pub fn partial_order_reduction(self) -> Self {
+        Self {
+            partial_order_reduction: true,
+            ..self
+        }
+    }
+
+    /// Periodically saves the progress of [`CheckerBuilder::spawn_bfs`] or
+    /// [`CheckerBuilder::spawn_dfs`] to a file so that an interrupted check can be continued via
+    /// [`CheckerBuilder::resume_from`]. Progress is also saved when the check stops because of a
+    /// [`CheckerBuilder::timeout`]. Saving briefly pauses all checker threads.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use stateright::*; let model = ();
+    /// # use std::time::Duration;
+    /// model.checker()
+    ///     .checkpoint_to("check.json", Duration::from_secs(600))
+    ///     .resume_from("check.json")
+    ///     .spawn_bfs().join();
+    /// ```
+    pub fn checkpoint_to(self, path: impl Into<PathBuf>, interval: Duration) -> Self {
+        Self {
+            checkpoint_to: Some((path.into(), interval)),
+            ..self
+        }
+    }
+
+    /// Continues a check from a file saved via [`CheckerBuilder::checkpoint_to`] rather than
+    /// starting from the initial states. States are recovered by replaying the model, so the
+    /// model must be the same as the one that was being checked, and the checker must be spawned
+    /// with the same method. The checker panics upon spawning if the checkpoint cannot be loaded.
+    pub fn resume_from(self, path: impl Into<PathBuf>) -> Self {
+        Self {
+            resume_from: Some(path.into()),
+            ..self
+        }
+    }
+
+    /// Specifies how [`CheckerBuilder::spawn_bfs`] and [`CheckerBuilder::spawn_dfs`] track the
+    /// states that they have visited. Defaults to an [`InMemoryStore`]. A [`DiskStore`] allows
+    /// checking state spaces whose fingerprints do not fit in memory, while a [`BitstateStore`]
+    /// uses a fixed amount of memory at the cost of possibly skipping states, as indicated by
+    /// [`Checker::coverage`]. `spawn_bfs` panics if the store does not
+    /// [retain sources](VisitedStore::retains_sources).
+    pub fn visited_store(self, store: impl VisitedStore + 'static) -> Self {
+        Self {
+            visited_store: Box::new(store),
+            ..self
+        }
+    }
+
     /// When to finish the checker run.
     pub fn finish_when(self, has_discoveries: HasDiscoveries) -> Self {
         Self {
@@ -316,6 +542,17 @@ pub trait Checker<M: Model> {
     /// Indicates the maximum depth that has been explored.
     fn max_depth(&self) -> usize;
 
+    /// Indicates how many states were not generated because of
+    /// [`CheckerBuilder::partial_order_reduction`].
+    fn pruned_state_count(&self) -> usize {
+        0
+    }
+
+    /// Indicates whether every generated state was checked. See [`CheckerBuilder::visited_store`].
+    fn coverage(&self) -> Coverage {
+        Coverage::Exhaustive
+    }
+
     /// Returns a map from property name to corresponding "discovery" (indicated
     /// by a [`Path`]).
     fn discoveries(&self) -> HashMap<&'static str, Path<M::State, M::Action>>;
@@ -369,6 +606,8 @@ pub trait Checker<M: Model> {
                     reporter_mutex.lock().unwrap().report_checking(ReportData {
                         total_states: slf.state_count(),
                         unique_states: slf.unique_state_count(),
+                        pruned_states: slf.pruned_state_count(),
+                        coverage: slf.coverage(),
                         max_depth: slf.max_depth(),
                         duration: method_start.elapsed(),
                         done: false,
@@ -386,6 +625,8 @@ pub trait Checker<M: Model> {
             reporter_mutex2.lock().unwrap().report_checking(ReportData {
                 total_states: self.state_count(),
                 unique_states: self.unique_state_count(),
+                pruned_states: self.pruned_state_count(),
+                coverage: self.coverage(),
                 max_depth: self.max_depth(),
                 duration: method_start2.elapsed(),
                 done: true,
@@ -422,6 +663,8 @@ pub trait Checker<M: Model> {
             reporter.report_checking(ReportData {
                 total_states: self.state_count(),
                 unique_states: self.unique_state_count(),
+                pruned_states: self.pruned_state_count(),
+                coverage: self.coverage(),
                 max_depth: self.max_depth(),
                 duration: method_start.elapsed(),
                 done: false,
@@ -432,6 +675,8 @@ pub trait Checker<M: Model> {
         reporter.report_checking(ReportData {
             total_states: self.state_count(),
             unique_states: self.unique_state_count(),
+            pruned_states: self.pruned_state_count(),
+            coverage: self.coverage(),
             max_depth: self.max_depth(),
             duration: method_start.elapsed(),
             done: true,
@@ -456,9 +701,10 @@ pub trait Checker<M: Model> {
         let properties = self.model().properties();
         let property = properties.iter().find(|p| p.name == name).unwrap();
         match property.expectation {
-            Expectation::Always | Expectation::Eventually => {
-                DiscoveryClassification::Counterexample
-            }
+            Expectation::Always
+            | Expectation::Eventually
+            | Expectation::Temporal
+            | Expectation::Transition => DiscoveryClassification::Counterexample,
             Expectation::Sometimes => DiscoveryClassification::Example,
         }
     }
@@ -477,6 +723,8 @@ pub trait Checker<M: Model> {
                 Expectation::Sometimes => {
                     self.assert_any_discovery(p.name);
                 }
+                Expectation::Temporal => self.assert_no_discovery(p.name),
+                Expectation::Transition => self.assert_no_discovery(p.name),
             }
         }
     }
@@ -544,15 +792,20 @@ pub trait Checker<M: Model> {
                             self.model().actions(states.last().unwrap(), &mut actions);
                             actions.is_empty()
                         };
-                        if !is_liveness_satisfied && is_path_terminal {
+                        let is_path_cyclic = {
+                            let (last, prefix) = states.split_last().unwrap();
+                            prefix.contains(last)
+                        };
+                        if !is_liveness_satisfied && (is_path_terminal || is_path_cyclic) {
                             return;
                         }
                         if is_liveness_satisfied {
                             additional_info
                                 .push("incorrect counterexample satisfies eventually property");
                         }
-                        if !is_path_terminal {
-                            additional_info.push("incorrect counterexample is nonterminal");
+                        if !is_path_terminal && !is_path_cyclic {
+                            additional_info
+                                .push("incorrect counterexample is nonterminal and acyclic");
                         }
                     }
                     Expectation::Sometimes => {
@@ -560,6 +813,41 @@ pub trait Checker<M: Model> {
                             return;
                         }
                     }
+                    Expectation::Temporal => {
+                        let states = path.into_states();
+                        let (last, prefix) = states.split_last().unwrap();
+                        // A cyclic path repeats its cycle forever, while a terminal path repeats
+                        // its last state forever.
+                        let lasso = if let Some(i) = prefix.iter().position(|s| s == last) {
+                            Some((prefix, i))
+                        } else {
+                            let mut actions = Vec::new();
+                            self.model().actions(last, &mut actions);
+                            actions.is_empty().then_some((&states[..], prefix.len()))
+                        };
+                        match lasso {
+                            None => additional_info
+                                .push("incorrect counterexample is nonterminal and acyclic"),
+                            Some((states, loop_start)) => {
+                                let formula = property.formula.as_ref().unwrap();
+                                if !formula.holds_on_lasso(self.model(), states, loop_start) {
+                                    return;
+                                }
+                                additional_info
+                                    .push("incorrect counterexample satisfies temporal formula");
+                            }
+                        }
+                    }
+                    Expectation::Transition => {
+                        let (transition, _) = property.transition.unwrap();
+                        if let [.., (last_state, Some(action)), (next_state, None)] =
+                            &path.into_vec()[..]
+                        {
+                            if !transition(self.model(), last_state, action, next_state) {
+                                return;
+                            }
+                        }
+                    }
                 }
             }
         }
@@ -577,6 +865,36 @@ pub trait Checker<M: Model> {
     }
 }
 
+/// Returns the state that follows an action along with the names of undiscovered `transition`
+/// properties that the step violates. Steps that leave the boundary are not checked. The action
+/// is only cloned (as `next_state` consumes it) while such a property remains undiscovered.
+pub(crate) fn next_state_checking_transitions<M: Model>(
+    model: &M,
+    properties: &[Property<M>],
+    last_state: &M::State,
+    action: M::Action,
+    is_discovered: impl Fn(&'static str) -> bool,
+) -> Option<(M::State, Vec<&'static str>)> {
+    let awaited: Vec<_> = properties
+        .iter()
+        .filter_map(|p| p.transition.map(|t| (p.name, t)))
+        .filter(|(name, _)| !is_discovered(name))
+        .collect();
+    let step_action = awaited.first().map(|(_, (_, clone))| clone(&action));
+    let next_state = model.next_state(last_state, action)?;
+    let mut violations = Vec::new();
+    if let Some(action) = step_action {
+        if model.within_boundary(&next_state) {
+            for (name, (transition, _)) in awaited {
+                if !transition(model, last_state, &action, &next_state) {
+                    violations.push(name);
+                }
+            }
+        }
+    }
+    Some((next_state, violations))
+}
+
 // EventuallyBits tracks one bit per 'eventually' property being checked. Properties are assigned
 // bit-numbers just by counting the 'eventually' properties up from 0 in the properties list. If a
 // bit is present in a bitset, the property has _not_ been found on this path yet. Bits are removed
@@ -668,7 +986,7 @@ mod test_eventually_property_checker {
                 .check()
                 .discovery("odd"),
             None
-        ); // FIXME: `unwrap().into_states()` should be [0, 2, 4, 2]
+        ); // FIXME: `unwrap().into_states()` should be [0, 2, 4, 2] (as with `spawn_liveness`)
         assert_eq!(
             DGraph::with_property(eventually_odd())
                 .with_path(vec![0, 2, 4])
@@ -676,7 +994,71 @@ mod test_eventually_property_checker {
                 .check()
                 .discovery("odd"),
             None
-        ); // FIXME: `unwrap().into_states()` should be [0, 2, 4, 6]
+        ); // FIXME: `unwrap().into_states()` should be [0, 2, 4, 6] (as with `spawn_liveness`)
+    }
+}
+
+#[cfg(test)]
+mod test_transition_property_checker {
+    use crate::test_util::dgraph::DGraph;
+    use crate::{Checker, Model, Property, UniformChooser};
+
+    fn increasing() -> Property<DGraph> {
+        Property::transition("increasing", |_, last, _, next| last < next)
+    }
+
+    fn discoveries(graph: impl Fn() -> DGraph) -> Vec<Option<Vec<u8>>> {
+        fn discovery(checker: impl Checker<DGraph>) -> Option<Vec<u8>> {
+            checker
+                .join()
+                .discovery("increasing")
+                .map(|p| p.into_states())
+        }
+        vec![
+            discovery(graph().checker().spawn_bfs()),
+            discovery(graph().checker().spawn_dfs()),
+            discovery(
+                graph()
+                    .checker()
+                    .target_state_count(100)
+                    .spawn_simulation(0, UniformChooser),
+            ),
+            discovery(graph().checker().spawn_liveness()),
+        ]
+    }
+
+    #[test]
+    fn can_validate() {
+        assert_eq!(
+            discoveries(|| DGraph::with_property(increasing())
+                .with_path(vec![0, 1, 3])
+                .with_path(vec![0, 2, 3])),
+            vec![None; 4]
+        );
+    }
+
+    #[test]
+    fn can_discover_counterexample() {
+        assert_eq!(
+            discoveries(|| DGraph::with_property(increasing()).with_path(vec![0, 1, 2, 1])),
+            vec![Some(vec![0, 1, 2, 1]); 4]
+        );
+    }
+
+    #[test]
+    fn can_discover_counterexample_revisiting_a_state() {
+        assert_eq!(
+            discoveries(|| DGraph::with_property(increasing()).with_path(vec![0, 1, 0])),
+            vec![Some(vec![0, 1, 0]); 4]
+        );
+    }
+
+    #[test]
+    fn can_assert_discovery() {
+        let checker = DGraph::with_property(increasing())
+            .with_path(vec![0, 2, 1])
+            .check();
+        checker.assert_discovery("increasing", vec![2, 1]);
     }
 }
 
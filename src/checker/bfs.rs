@@ -1,15 +1,19 @@
 //! Private module for selective re-export.
 
-use crate::checker::{Checker, EventuallyBits, Expectation, Path};
+use crate::checker::checkpoint::{resume_failed, Checkpoint};
+use crate::checker::liveness::TemporalGraph;
+use crate::checker::{
+    next_state_checking_transitions, Checker, Coverage, EventuallyBits, Expectation, Path,
+    VisitedStore,
+};
 use crate::job_market::JobBroker;
 use crate::{fingerprint, CheckerBuilder, CheckerVisitor, Fingerprint, Model, Property};
-use dashmap::mapref::entry::Entry;
 use dashmap::DashMap;
-use nohash_hasher::NoHashHasher;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
-use std::hash::{BuildHasherDefault, Hash};
+use std::hash::Hash;
 use std::num::NonZeroUsize;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::thread::JoinHandle;
 use std::time::SystemTime;
@@ -26,11 +30,27 @@ pub(crate) struct BfsChecker<M: Model> {
     job_broker: JobBroker<Job<M::State>>,
     state_count: Arc<AtomicUsize>,
     max_depth: Arc<AtomicUsize>,
-    generated:
-        Arc<DashMap<Fingerprint, Option<Fingerprint>, BuildHasherDefault<NoHashHasher<u64>>>>,
-    discoveries: Arc<DashMap<&'static str, Fingerprint>>,
+    generated: Arc<dyn VisitedStore>,
+    discoveries: Arc<DashMap<&'static str, Discovery>>,
+    is_analyzing: Arc<AtomicBool>,
 }
 type Job<State> = (State, Fingerprint, EventuallyBits, NonZeroUsize);
+#[derive(Clone, Deserialize, Serialize)]
+enum Discovery {
+    /// The fingerprint of a discovered state, along with the fingerprint of a subsequent state if
+    /// the discovery is a step (for a `transition` property).
+    State(Fingerprint, Option<Fingerprint>),
+    /// A counterexample to a `temporal` property, which may end in a cycle, so it is not
+    /// reconstructed from the visited store.
+    Path(Vec<Fingerprint>),
+}
+/// Pending states are saved as fingerprints (with the same metadata as a [`Job`]), and the
+/// visited store is saved in full so that the states can be reconstructed.
+type BfsCheckpoint = Checkpoint<
+    (Fingerprint, Option<Fingerprint>),
+    Discovery,
+    (Fingerprint, Vec<usize>, NonZeroUsize),
+>;
 
 impl<M> BfsChecker<M>
 where
@@ -53,13 +73,25 @@ where
             .collect();
         let state_count = Arc::new(AtomicUsize::new(init_states.len()));
         let max_depth = Arc::new(AtomicUsize::new(0));
-        let generated = Arc::new({
-            let generated = DashMap::default();
-            for s in &init_states {
-                generated.insert(fingerprint(s), None);
-            }
-            generated
-        });
+        let generated: Arc<dyn VisitedStore> = options.visited_store.into();
+        if !generated.retains_sources() {
+            panic!(
+                "The BFS checker requires a visited store that retains sources. \
+                 Consider calling spawn_dfs() instead."
+            );
+        }
+        for s in &init_states {
+            generated.insert(fingerprint(s), None);
+        }
+        let temporal =
+            TemporalGraph::new(&properties, init_states.iter().map(fingerprint).collect())
+                .map(Arc::new);
+        if temporal.is_some() && options.resume_from.is_some() {
+            log::warn!(
+                "Checkpoints do not include the state graph for temporal properties, so states \
+                 visited before resuming are treated as unknown."
+            );
+        }
         let ebits = {
             let mut ebits = EventuallyBits::new();
             for (i, p) in model.properties().iter().enumerate() {
@@ -73,19 +105,69 @@ where
             }
             ebits
         };
-        let pending: VecDeque<_> = init_states
-            .into_iter()
-            .map(|s| {
-                let fp = fingerprint(&s);
-                (s, fp, ebits.clone(), NonZeroUsize::new(1).unwrap())
-            })
-            .collect();
         let discoveries = Arc::new(DashMap::default());
+        let pending: VecDeque<_> = match &options.resume_from {
+            Some(path) => Self::resume(
+                &model,
+                path,
+                &state_count,
+                &max_depth,
+                &*generated,
+                &discoveries,
+            ),
+            None => init_states
+                .into_iter()
+                .map(|s| {
+                    let fp = fingerprint(&s);
+                    (s, fp, ebits.clone(), NonZeroUsize::new(1).unwrap())
+                })
+                .collect(),
+        };
         let mut handles = Vec::new();
 
         let close_at = options.timeout.map(|t| SystemTime::now() + t);
         let mut job_broker = JobBroker::new(thread_count, close_at);
         job_broker.push(pending);
+        if let Some((path, interval)) = options.checkpoint_to {
+            let state_count = Arc::clone(&state_count);
+            let max_depth = Arc::clone(&max_depth);
+            let generated = Arc::clone(&generated);
+            let discoveries = Arc::clone(&discoveries);
+            job_broker.checkpoint_every(interval, move |job_batches| {
+                let checkpoint = BfsCheckpoint {
+                    checker: "bfs".to_owned(),
+                    state_count: state_count.load(Ordering::Relaxed),
+                    max_depth: max_depth.load(Ordering::Relaxed),
+                    pruned_state_count: 0,
+                    generated: {
+                        let mut entries = Vec::with_capacity(generated.len());
+                        if !generated.for_each(&mut |fp, source| entries.push((fp, source))) {
+                            log::error!(
+                                "Unable to save checkpoint as the visited store cannot \
+                                 enumerate its fingerprints."
+                            );
+                            return;
+                        }
+                        entries
+                    },
+                    discoveries: discoveries
+                        .iter()
+                        .map(|r| (r.key().to_string(), r.value().clone()))
+                        .collect(),
+                    pending: job_batches
+                        .iter()
+                        .flatten()
+                        .map(|(_, fp, ebits, depth)| (*fp, ebits.iter().collect(), *depth))
+                        .collect(),
+                };
+                match checkpoint.save(&path) {
+                    Ok(()) => log::info!("Saved checkpoint to {}.", path.display()),
+                    Err(err) => {
+                        log::error!("Unable to save checkpoint to {}: {}", path.display(), err)
+                    }
+                }
+            });
+        }
 
         for t in 0..thread_count {
             let model = Arc::clone(&model);
@@ -97,6 +179,7 @@ where
             let max_depth = Arc::clone(&max_depth);
             let generated = Arc::clone(&generated);
             let discoveries = Arc::clone(&discoveries);
+            let temporal = temporal.clone();
             handles.push(
                 std::thread::Builder::new()
                     .name(format!("checker-{}", t))
@@ -123,13 +206,14 @@ where
                             Self::check_block(
                                 &model,
                                 &state_count,
-                                &generated,
+                                &*generated,
                                 &mut pending,
                                 &discoveries,
                                 &visitor,
                                 1500,
                                 target_max_depth,
                                 &max_depth,
+                                temporal.as_deref(),
                             );
                             if finish_when.matches(
                                 &discoveries.iter().map(|r| *r.key()).collect(),
@@ -157,11 +241,35 @@ where
                             if pending.len() > 1 && thread_count > 1 {
                                 job_broker.split_and_push(&mut pending);
                             }
+
+                            // Step 3: Return work if it is about to be saved.
+                            if !pending.is_empty() && job_broker.is_checkpoint_due() {
+                                job_broker.push(std::mem::take(&mut pending));
+                            }
                         }
                     })
                     .expect("Failed to spawn a thread"),
             );
         }
+        let is_analyzing = Arc::new(AtomicBool::new(temporal.is_some()));
+        if let Some(temporal) = temporal {
+            let is_discovered = {
+                let discoveries = Arc::clone(&discoveries);
+                move |name| discoveries.contains_key(name)
+            };
+            let discover = {
+                let discoveries = Arc::clone(&discoveries);
+                move |name, fingerprints| {
+                    discoveries.insert(name, Discovery::Path(fingerprints));
+                }
+            };
+            handles = vec![temporal.spawn_analysis(
+                handles,
+                Arc::clone(&is_analyzing),
+                is_discovered,
+                discover,
+            )];
+        }
         BfsChecker {
             model,
             handles,
@@ -170,27 +278,56 @@ where
             max_depth,
             generated,
             discoveries,
+            is_analyzing,
         }
     }
 
+    /// Restores the state of a previous run from a checkpoint, returning the pending jobs.
+    fn resume(
+        model: &M,
+        path: &std::path::Path,
+        state_count: &AtomicUsize,
+        max_depth: &AtomicUsize,
+        generated: &dyn VisitedStore,
+        discoveries: &DashMap<&'static str, Discovery>,
+    ) -> VecDeque<Job<M::State>> {
+        let mut checkpoint =
+            BfsCheckpoint::load(path, "bfs").unwrap_or_else(|err| resume_failed(path, err));
+        state_count.store(checkpoint.state_count, Ordering::Relaxed);
+        max_depth.store(checkpoint.max_depth, Ordering::Relaxed);
+        generated.clear();
+        for (fp, source) in checkpoint.generated.drain(..) {
+            generated.insert(fp, source);
+        }
+        for (name, discovery) in checkpoint.discoveries(model) {
+            discoveries.insert(name, discovery);
+        }
+        checkpoint
+            .pending
+            .into_iter()
+            .map(|(fp, ebits, depth)| {
+                let fingerprints = reconstruct_fingerprints(generated, fp);
+                let state = Path::final_state(model, fingerprints)
+                    .unwrap_or_else(|| resume_failed(path, "unable to reconstruct a state"));
+                (state, fp, ebits.into_iter().collect(), depth)
+            })
+            .collect()
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn check_block(
         model: &M,
         state_count: &AtomicUsize,
-        generated: &DashMap<
-            Fingerprint,
-            Option<Fingerprint>,
-            BuildHasherDefault<NoHashHasher<u64>>,
-        >,
+        generated: &dyn VisitedStore,
         pending: &mut VecDeque<Job<M::State>>,
-        discoveries: &DashMap<&'static str, Fingerprint>,
+        discoveries: &DashMap<&'static str, Discovery>,
         visitor: &Option<Box<dyn CheckerVisitor<M> + Send + Sync>>,
         mut max_count: usize,
         target_max_depth: Option<NonZeroUsize>,
         global_max_depth: &AtomicUsize,
+        temporal: Option<&TemporalGraph<M>>,
     ) {
         let properties = model.properties();
-
         let mut current_max_depth = global_max_depth.load(Ordering::Relaxed);
         let mut actions = Vec::new();
         loop {
@@ -241,7 +378,7 @@ where
                     } => {
                         if !always(model, &state) {
                             // Races other threads, but that's fine.
-                            discoveries.insert(property.name, state_fp);
+                            discoveries.insert(property.name, Discovery::State(state_fp, None));
                         } else {
                             is_awaiting_discoveries = true;
                         }
@@ -253,7 +390,7 @@ where
                     } => {
                         if sometimes(model, &state) {
                             // Races other threads, but that's fine.
-                            discoveries.insert(property.name, state_fp);
+                            discoveries.insert(property.name, Discovery::State(state_fp, None));
                         } else {
                             is_awaiting_discoveries = true;
                         }
@@ -273,6 +410,20 @@ where
                             ebits.remove(i);
                         }
                     }
+                    Property {
+                        expectation: Expectation::Temporal,
+                        ..
+                    } => {
+                        // Checked against the recorded graph once exploration stops.
+                        is_awaiting_discoveries = true;
+                    }
+                    Property {
+                        expectation: Expectation::Transition,
+                        ..
+                    } => {
+                        // Checked below for each step.
+                        is_awaiting_discoveries = true;
+                    }
                 }
             }
             if !is_awaiting_discoveries {
@@ -281,14 +432,35 @@ where
 
             // Otherwise enqueue newly generated states (with related metadata).
             let mut is_terminal = true;
+            let mut successors = Vec::new();
             model.actions(&state, &mut actions);
-            let next_states = actions.drain(..).flat_map(|a| model.next_state(&state, a));
-            for next_state in next_states {
+            for action in actions.drain(..) {
+                let is_discovered = |name| discoveries.contains_key(name);
+                let (next_state, violations) = match next_state_checking_transitions(
+                    model,
+                    &properties,
+                    &state,
+                    action,
+                    is_discovered,
+                ) {
+                    None => continue,
+                    Some(step) => step,
+                };
+
                 // Skip if outside boundary.
                 if !model.within_boundary(&next_state) {
                     continue;
                 }
                 state_count.fetch_add(1, Ordering::Relaxed);
+                let next_fingerprint = fingerprint(&next_state);
+                if temporal.is_some() {
+                    successors.push(next_fingerprint);
+                }
+
+                for name in violations {
+                    // Races other threads, but that's fine.
+                    discoveries.insert(name, Discovery::State(state_fp, Some(next_fingerprint)));
+                }
 
                 // Skip if already generated.
                 //
@@ -298,10 +470,7 @@ where
                 // property held on the path leading to the first visit as meaning
                 // that it holds in the path leading to the second visit -- another
                 // possible false-negative.
-                let next_fingerprint = fingerprint(&next_state);
-                if let Entry::Vacant(next_entry) = generated.entry(next_fingerprint) {
-                    next_entry.insert(Some(state_fp));
-                } else {
+                if !generated.insert(next_fingerprint, Some(state_fp)) {
                     // FIXME: arriving at an already-known state may be a loop (in which case it
                     // could, in a fancier implementation, be considered a terminal state for
                     // purposes of eventually-property checking) but it might also be a join in
@@ -323,11 +492,14 @@ where
                     NonZeroUsize::new(max_depth.get() + 1).unwrap(),
                 ));
             }
+            if let Some(temporal) = temporal {
+                temporal.record(model, &state, state_fp, successors);
+            }
             if is_terminal {
                 for (i, property) in properties.iter().enumerate() {
                     if ebits.contains(i) {
                         // Races other threads, but that's fine.
-                        discoveries.insert(property.name, state_fp);
+                        discoveries.insert(property.name, Discovery::State(state_fp, None));
                     }
                 }
             }
@@ -352,6 +524,10 @@ where
         self.generated.len()
     }
 
+    fn coverage(&self) -> Coverage {
+        self.generated.coverage()
+    }
+
     fn max_depth(&self) -> usize {
         self.max_depth.load(Ordering::Relaxed)
     }
@@ -362,7 +538,20 @@ where
             .map(|mapref| {
                 (
                     <&'static str>::clone(mapref.key()),
-                    reconstruct_path(self.model(), &self.generated, *mapref.value()),
+                    match mapref.value() {
+                        Discovery::State(fp, None) => {
+                            reconstruct_path(self.model(), &*self.generated, *fp)
+                        }
+                        Discovery::State(fp, Some(next_fp)) => {
+                            let mut fingerprints = reconstruct_fingerprints(&*self.generated, *fp);
+                            fingerprints.push_back(*next_fp);
+                            Path::from_fingerprints(self.model(), fingerprints)
+                        }
+                        Discovery::Path(fingerprints) => Path::from_fingerprints(
+                            self.model(),
+                            VecDeque::from(fingerprints.clone()),
+                        ),
+                    },
                 )
             })
             .collect()
@@ -373,19 +562,27 @@ where
     }
 
     fn is_done(&self) -> bool {
-        self.job_broker.is_closed() || self.discoveries.len() == self.model.properties().len()
+        (self.job_broker.is_closed() && !self.is_analyzing.load(Ordering::Relaxed))
+            || self.discoveries.len() == self.model.properties().len()
     }
 }
 
 fn reconstruct_path<M>(
     model: &M,
-    generated: &DashMap<Fingerprint, Option<Fingerprint>, BuildHasherDefault<NoHashHasher<u64>>>,
+    generated: &dyn VisitedStore,
     fp: Fingerprint,
 ) -> Path<M::State, M::Action>
 where
     M: Model,
     M::State: Hash,
 {
+    Path::from_fingerprints(model, reconstruct_fingerprints(generated, fp))
+}
+
+fn reconstruct_fingerprints(
+    generated: &dyn VisitedStore,
+    fp: Fingerprint,
+) -> VecDeque<Fingerprint> {
     // First build a stack of digests representing the path (with the init digest at top of
     // stack). Then unwind the stack of digests into a vector of states. The TLC model checker
     // uses a similar technique, which is documented in the paper "Model Checking TLA+
@@ -393,8 +590,8 @@ where
 
     let mut fingerprints = VecDeque::new();
     let mut next_fp = fp;
-    while let Some(source) = generated.get(&next_fp) {
-        match *source {
+    while let Some(source) = generated.source(next_fp) {
+        match source {
             Some(prev_fingerprint) => {
                 fingerprints.push_front(next_fp);
                 next_fp = prev_fingerprint;
@@ -405,7 +602,7 @@ where
             }
         }
     }
-    Path::from_fingerprints(model, fingerprints)
+    fingerprints
 }
 
 #[cfg(test)]
@@ -475,6 +672,64 @@ mod test {
         );
     }
 
+    #[test]
+    fn can_spill_visited_states_to_disk() {
+        let dir =
+            std::env::temp_dir().join(format!("stateright-bfs-visited-{}", std::process::id()));
+        let checker = LinearEquation { a: 2, b: 4, c: 7 }
+            .checker()
+            .visited_store(DiskStore::new(&dir, 1_000).unwrap())
+            .spawn_bfs()
+            .join();
+        checker.assert_no_discovery("solvable");
+        assert_eq!(checker.unique_state_count(), 256 * 256);
+        assert_eq!(checker.coverage(), Coverage::Exhaustive);
+        drop(checker);
+
+        let checker = LinearEquation { a: 2, b: 10, c: 14 }
+            .checker()
+            .visited_store(DiskStore::new(&dir, 5).unwrap())
+            .spawn_bfs()
+            .join();
+        assert_eq!(
+            checker.discovery("solvable").unwrap().into_actions(),
+            vec![Guess::IncreaseX, Guess::IncreaseX, Guess::IncreaseY,]
+        );
+        drop(checker);
+        std::fs::remove_dir(dir).unwrap();
+    }
+
+    #[test]
+    fn can_resume_from_checkpoint() {
+        let path = std::env::temp_dir().join(format!(
+            "stateright-bfs-checkpoint-{}.json",
+            std::process::id()
+        ));
+        let checker = || {
+            LinearEquation { a: 2, b: 4, c: 7 }
+                .checker()
+                .target_max_depth(80)
+        };
+
+        // Stops after checking two blocks of states, but a checkpoint is saved after the first.
+        let interrupted = checker()
+            .target_state_count(4_000)
+            .checkpoint_to(&path, std::time::Duration::ZERO)
+            .spawn_bfs()
+            .join();
+        let resumed = checker().resume_from(&path).spawn_bfs().join();
+        std::fs::remove_file(&path).unwrap();
+
+        let uninterrupted = checker().spawn_bfs().join();
+        assert!(interrupted.unique_state_count() < uninterrupted.unique_state_count());
+        assert_eq!(resumed.state_count(), uninterrupted.state_count());
+        assert_eq!(
+            resumed.unique_state_count(),
+            uninterrupted.unique_state_count()
+        );
+        resumed.assert_no_discovery("solvable");
+    }
+
     // test that the checker shuts down all threads properly after a checker thread encounters a
     // panic in the model execution.
     #[test]
@@ -0,0 +1,90 @@
+//! Private module for selective re-export.
+
+use crate::Model;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Write};
+use std::path::Path;
+
+/// The progress of a checker, as saved to disk by [`CheckerBuilder::checkpoint_to`] and loaded by
+/// [`CheckerBuilder::resume_from`].
+///
+/// Pending states are identified by their fingerprints rather than serialized, so the checker
+/// recovers them by replaying the model from its initial states. This means that a checkpoint can
+/// only be resumed with the same model.
+///
+/// [`CheckerBuilder::checkpoint_to`]: crate::CheckerBuilder::checkpoint_to
+/// [`CheckerBuilder::resume_from`]: crate::CheckerBuilder::resume_from
+#[derive(Deserialize, Serialize)]
+pub(crate) struct Checkpoint<Generated, Discovery, Job> {
+    /// The kind of checker that saved the checkpoint, such as `"bfs"`.
+    pub(crate) checker: String,
+    pub(crate) state_count: usize,
+    pub(crate) max_depth: usize,
+    #[serde(default)]
+    pub(crate) pruned_state_count: usize,
+    pub(crate) generated: Vec<Generated>,
+    pub(crate) discoveries: Vec<(String, Discovery)>,
+    pub(crate) pending: Vec<Job>,
+}
+
+impl<Generated, Discovery, Job> Checkpoint<Generated, Discovery, Job> {
+    /// Writes the checkpoint to a file. The previous checkpoint is only replaced once the new one
+    /// has been written in full.
+    pub(crate) fn save(&self, path: &Path) -> std::io::Result<()>
+    where
+        Self: Serialize,
+    {
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        serde_json::to_writer(&mut writer, self)?;
+        writer.flush()?;
+        writer.into_inner()?.sync_all()?;
+        std::fs::rename(&tmp_path, path)
+    }
+
+    /// Reads a checkpoint from a file, verifying that it was saved by the same kind of checker.
+    pub(crate) fn load(path: &Path, checker: &str) -> std::io::Result<Self>
+    where
+        Self: DeserializeOwned,
+    {
+        let reader = BufReader::new(File::open(path)?);
+        let checkpoint: Self = serde_json::from_reader(reader)?;
+        if checkpoint.checker != checker {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "expected a checkpoint from the {} checker but found one from the {} checker",
+                    checker, checkpoint.checker
+                ),
+            ));
+        }
+        Ok(checkpoint)
+    }
+
+    /// Looks up the discoveries by property name, ignoring any for properties that the model no
+    /// longer has.
+    pub(crate) fn discoveries<M: Model>(
+        &mut self,
+        model: &M,
+    ) -> impl Iterator<Item = (&'static str, Discovery)> {
+        let properties = model.properties();
+        std::mem::take(&mut self.discoveries)
+            .into_iter()
+            .filter_map(move |(name, discovery)| {
+                let property = properties.iter().find(|p| p.name == name)?;
+                Some((property.name, discovery))
+            })
+    }
+}
+
+/// Panics with a consistent message if a checkpoint cannot be resumed.
+pub(crate) fn resume_failed(path: &Path, reason: impl std::fmt::Display) -> ! {
+    panic!(
+        "Unable to resume from checkpoint {}: {}",
+        path.display(),
+        reason
+    )
+}
@@ -1,14 +1,20 @@
 //! Private module for selective re-export.
 
-use crate::checker::{Checker, EventuallyBits, Expectation, Path};
+use crate::checker::checkpoint::{resume_failed, Checkpoint};
+use crate::checker::liveness::TemporalGraph;
+use crate::checker::{
+    next_state_checking_transitions, Checker, Coverage, EventuallyBits, Expectation, Path,
+    VisitedStore,
+};
 use crate::job_market::JobBroker;
 use crate::{fingerprint, CheckerBuilder, CheckerVisitor, Fingerprint, Model, Property};
-use dashmap::{DashMap, DashSet};
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
 use nohash_hasher::NoHashHasher;
 use std::collections::{HashMap, VecDeque};
 use std::hash::{BuildHasherDefault, Hash};
 use std::num::NonZeroUsize;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::thread::JoinHandle;
 use std::time::SystemTime;
@@ -25,25 +31,66 @@ pub(crate) struct DfsChecker<M: Model> {
     job_broker: JobBroker<Job<M::State>>,
     state_count: Arc<AtomicUsize>,
     max_depth: Arc<AtomicUsize>,
-    generated: Arc<DashSet<Fingerprint, BuildHasherDefault<NoHashHasher<u64>>>>,
+    generated: Arc<dyn VisitedStore>,
     discoveries: Arc<DashMap<&'static str, Vec<Fingerprint>>>,
+    pruned_state_count: Arc<AtomicUsize>,
+    is_analyzing: Arc<AtomicBool>,
 }
-type Job<State> = (State, Vec<Fingerprint>, EventuallyBits, NonZeroUsize);
+type Job<State> = (
+    State,
+    Vec<Fingerprint>,
+    EventuallyBits,
+    NonZeroUsize,
+    SleepSet,
+);
+
+/// Fingerprints of actions that need not be explored from a state when checking with partial
+/// order reduction.
+type SleepSet = Vec<Fingerprint>;
+type SleepSets = DashMap<Fingerprint, SleepSet, BuildHasherDefault<NoHashHasher<u64>>>;
+/// Pending states are saved as the fingerprint paths that lead to them (with the same metadata as
+/// a [`Job`]), and generated fingerprints are saved with their sources and sleep sets (if any).
+type DfsCheckpoint = Checkpoint<
+    (Fingerprint, Option<Fingerprint>, Option<SleepSet>),
+    Vec<Fingerprint>,
+    (Vec<Fingerprint>, Vec<usize>, NonZeroUsize, SleepSet),
+>;
 
 impl<M> DfsChecker<M>
 where
     M: Model + Send + Sync + 'static,
     M::State: Hash + Send + 'static,
+    M::Action: Hash,
 {
     pub(crate) fn spawn(options: CheckerBuilder<M>) -> Self {
         let model = Arc::new(options.model);
-        let symmetry = options.symmetry;
         let target_state_count = options.target_state_count;
         let target_max_depth = options.target_max_depth;
         let thread_count = options.thread_count;
         let visitor = Arc::new(options.visitor);
         let finish_when = Arc::new(options.finish_when);
         let properties = Arc::new(model.properties());
+        let has_temporal_properties = properties
+            .iter()
+            .any(|p| p.expectation == Expectation::Temporal);
+        let symmetry = match options.symmetry {
+            Some(_) if has_temporal_properties => {
+                log::warn!("Symmetry reduction is ignored with temporal properties.");
+                None
+            }
+            symmetry => symmetry,
+        };
+        let partial_order_reduction = match options.partial_order_reduction {
+            true if has_temporal_properties => {
+                log::warn!("Partial order reduction is ignored with temporal properties.");
+                false
+            }
+            true if symmetry.is_some() => {
+                log::warn!("Partial order reduction is ignored with symmetry reduction.");
+                false
+            }
+            partial_order_reduction => partial_order_reduction,
+        };
 
         let init_states: Vec<_> = model
             .init_states()
@@ -52,17 +99,25 @@ where
             .collect();
         let state_count = Arc::new(AtomicUsize::new(init_states.len()));
         let max_depth = Arc::new(AtomicUsize::new(0));
-        let generated = Arc::new({
-            let generated = DashSet::default();
-            for s in &init_states {
-                if let Some(representative) = symmetry {
-                    generated.insert(fingerprint(&representative(s)));
-                } else {
-                    generated.insert(fingerprint(s));
-                }
+        let generated: Arc<dyn VisitedStore> = options.visited_store.into();
+        for s in &init_states {
+            if let Some(representative) = symmetry {
+                generated.insert(fingerprint(&representative(s)), None);
+            } else {
+                generated.insert(fingerprint(s), None);
             }
-            generated
-        });
+        }
+        let temporal =
+            TemporalGraph::new(&properties, init_states.iter().map(fingerprint).collect())
+                .map(Arc::new);
+        if temporal.is_some() && options.resume_from.is_some() {
+            log::warn!(
+                "Checkpoints do not include the state graph for temporal properties, so states \
+                 visited before resuming are treated as unknown."
+            );
+        }
+        let sleep_sets = Arc::new(SleepSets::default());
+        let pruned_state_count = Arc::new(AtomicUsize::new(0));
         let ebits = {
             let mut ebits = EventuallyBits::new();
             for (i, p) in properties.iter().enumerate() {
@@ -76,19 +131,93 @@ where
             }
             ebits
         };
-        let pending: VecDeque<_> = init_states
-            .into_iter()
-            .map(|s| {
-                let fp = fingerprint(&s);
-                (s, vec![fp], ebits.clone(), NonZeroUsize::new(1).unwrap())
-            })
-            .collect();
         let discoveries = Arc::new(DashMap::default());
+        let pending: VecDeque<_> = match &options.resume_from {
+            Some(path) => Self::resume(
+                &model,
+                path,
+                &state_count,
+                &max_depth,
+                &pruned_state_count,
+                &*generated,
+                &sleep_sets,
+                &discoveries,
+            ),
+            None => init_states
+                .into_iter()
+                .map(|s| {
+                    let fp = fingerprint(&s);
+                    (
+                        s,
+                        vec![fp],
+                        ebits.clone(),
+                        NonZeroUsize::new(1).unwrap(),
+                        SleepSet::new(),
+                    )
+                })
+                .collect(),
+        };
         let mut handles = Vec::new();
 
         let close_at = options.timeout.map(|t| SystemTime::now() + t);
         let mut job_broker = JobBroker::new(thread_count, close_at);
         job_broker.push(pending);
+        if let Some((path, interval)) = options.checkpoint_to {
+            let state_count = Arc::clone(&state_count);
+            let max_depth = Arc::clone(&max_depth);
+            let pruned_state_count = Arc::clone(&pruned_state_count);
+            let generated = Arc::clone(&generated);
+            let sleep_sets = Arc::clone(&sleep_sets);
+            let discoveries = Arc::clone(&discoveries);
+            job_broker.checkpoint_every(interval, move |job_batches| {
+                let checkpoint = DfsCheckpoint {
+                    checker: "dfs".to_owned(),
+                    state_count: state_count.load(Ordering::Relaxed),
+                    max_depth: max_depth.load(Ordering::Relaxed),
+                    pruned_state_count: pruned_state_count.load(Ordering::Relaxed),
+                    generated: {
+                        let mut entries = Vec::with_capacity(generated.len());
+                        let is_enumerable = generated.for_each(&mut |fp, source| {
+                            entries.push((
+                                fp,
+                                source,
+                                sleep_sets.get(&fp).map(|r| r.value().clone()),
+                            ));
+                        });
+                        if !is_enumerable {
+                            log::error!(
+                                "Unable to save checkpoint as the visited store cannot \
+                                 enumerate its fingerprints."
+                            );
+                            return;
+                        }
+                        entries
+                    },
+                    discoveries: discoveries
+                        .iter()
+                        .map(|r| (r.key().to_string(), r.value().clone()))
+                        .collect(),
+                    pending: job_batches
+                        .iter()
+                        .flatten()
+                        .map(|(_, fingerprints, ebits, depth, sleep_set)| {
+                            (
+                                fingerprints.clone(),
+                                ebits.iter().collect(),
+                                *depth,
+                                sleep_set.clone(),
+                            )
+                        })
+                        .collect(),
+                };
+                match checkpoint.save(&path) {
+                    Ok(()) => log::info!("Saved checkpoint to {}.", path.display()),
+                    Err(err) => {
+                        log::error!("Unable to save checkpoint to {}: {}", path.display(), err)
+                    }
+                }
+            });
+        }
 
         for t in 0..thread_count {
             let model = Arc::clone(&model);
@@ -100,6 +229,9 @@ where
             let max_depth = Arc::clone(&max_depth);
             let generated = Arc::clone(&generated);
             let discoveries = Arc::clone(&discoveries);
+            let sleep_sets = Arc::clone(&sleep_sets);
+            let pruned_state_count = Arc::clone(&pruned_state_count);
+            let temporal = temporal.clone();
             handles.push(
                 std::thread::Builder::new()
                     .name(format!("checker-{}", t))
@@ -126,7 +258,7 @@ where
                             Self::check_block(
                                 &model,
                                 &state_count,
-                                &generated,
+                                &*generated,
                                 &mut pending,
                                 &discoveries,
                                 &visitor,
@@ -134,6 +266,10 @@ where
                                 target_max_depth,
                                 &max_depth,
                                 symmetry,
+                                partial_order_reduction,
+                                &sleep_sets,
+                                &pruned_state_count,
+                                temporal.as_deref(),
                             );
                             if finish_when.matches(
                                 &discoveries.iter().map(|r| *r.key()).collect(),
@@ -161,11 +297,35 @@ where
                             if pending.len() > 1 && thread_count > 1 {
                                 job_broker.split_and_push(&mut pending);
                             }
+
+                            // Step 3: Return work if it is about to be saved.
+                            if !pending.is_empty() && job_broker.is_checkpoint_due() {
+                                job_broker.push(std::mem::take(&mut pending));
+                            }
                         }
                     })
                     .expect("Failed to spawn a thread"),
             );
         }
+        let is_analyzing = Arc::new(AtomicBool::new(temporal.is_some()));
+        if let Some(temporal) = temporal {
+            let is_discovered = {
+                let discoveries = Arc::clone(&discoveries);
+                move |name| discoveries.contains_key(name)
+            };
+            let discover = {
+                let discoveries = Arc::clone(&discoveries);
+                move |name, fingerprints| {
+                    discoveries.insert(name, fingerprints);
+                }
+            };
+            handles = vec![temporal.spawn_analysis(
+                handles,
+                Arc::clone(&is_analyzing),
+                is_discovered,
+                discover,
+            )];
+        }
         DfsChecker {
             model,
             handles,
@@ -174,7 +334,54 @@ where
             max_depth,
             generated,
             discoveries,
+            pruned_state_count,
+            is_analyzing,
+        }
+    }
+
+    /// Restores the state of a previous run from a checkpoint, returning the pending jobs.
+    #[allow(clippy::too_many_arguments)]
+    fn resume(
+        model: &M,
+        path: &std::path::Path,
+        state_count: &AtomicUsize,
+        max_depth: &AtomicUsize,
+        pruned_state_count: &AtomicUsize,
+        generated: &dyn VisitedStore,
+        sleep_sets: &SleepSets,
+        discoveries: &DashMap<&'static str, Vec<Fingerprint>>,
+    ) -> VecDeque<Job<M::State>> {
+        let mut checkpoint =
+            DfsCheckpoint::load(path, "dfs").unwrap_or_else(|err| resume_failed(path, err));
+        state_count.store(checkpoint.state_count, Ordering::Relaxed);
+        max_depth.store(checkpoint.max_depth, Ordering::Relaxed);
+        pruned_state_count.store(checkpoint.pruned_state_count, Ordering::Relaxed);
+        generated.clear();
+        sleep_sets.clear();
+        for (fp, source, sleep_set) in checkpoint.generated.drain(..) {
+            generated.insert(fp, source);
+            if let Some(sleep_set) = sleep_set {
+                sleep_sets.insert(fp, sleep_set);
+            }
         }
+        for (name, discovery) in checkpoint.discoveries(model) {
+            discoveries.insert(name, discovery);
+        }
+        checkpoint
+            .pending
+            .into_iter()
+            .map(|(fingerprints, ebits, depth, sleep_set)| {
+                let state = Path::final_state(model, VecDeque::from(fingerprints.clone()))
+                    .unwrap_or_else(|| resume_failed(path, "unable to reconstruct a state"));
+                (
+                    state,
+                    fingerprints,
+                    ebits.into_iter().collect(),
+                    depth,
+                    sleep_set,
+                )
+            })
+            .collect()
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -182,7 +389,7 @@ where
     fn check_block(
         model: &M,
         state_count: &AtomicUsize,
-        generated: &DashSet<Fingerprint, BuildHasherDefault<NoHashHasher<u64>>>,
+        generated: &dyn VisitedStore,
         pending: &mut VecDeque<Job<M::State>>,
         discoveries: &DashMap<&'static str, Vec<Fingerprint>>,
         visitor: &Option<Box<dyn CheckerVisitor<M> + Send + Sync>>,
@@ -190,9 +397,12 @@ where
         target_max_depth: Option<NonZeroUsize>,
         global_max_depth: &AtomicUsize,
         symmetry: Option<fn(&M::State) -> M::State>,
+        partial_order_reduction: bool,
+        sleep_sets: &SleepSets,
+        pruned_state_count: &AtomicUsize,
+        temporal: Option<&TemporalGraph<M>>,
     ) {
         let properties = model.properties();
-
         let mut current_max_depth = global_max_depth.load(Ordering::Relaxed);
         let mut actions = Vec::new();
         loop {
@@ -203,7 +413,7 @@ where
             max_count -= 1;
 
             // Done if none pending.
-            let (state, fingerprints, mut ebits, max_depth) = match pending.pop_back() {
+            let (state, fingerprints, mut ebits, max_depth, sleep_set) = match pending.pop_back() {
                 None => return,
                 Some(pair) => pair,
             };
@@ -277,6 +487,20 @@ where
                             ebits.remove(i);
                         }
                     }
+                    Property {
+                        expectation: Expectation::Temporal,
+                        ..
+                    } => {
+                        // Checked against the recorded graph once exploration stops.
+                        is_awaiting_discoveries = true;
+                    }
+                    Property {
+                        expectation: Expectation::Transition,
+                        ..
+                    } => {
+                        // Checked below for each step.
+                        is_awaiting_discoveries = true;
+                    }
                 }
             }
             if !is_awaiting_discoveries {
@@ -285,11 +509,52 @@ where
 
             // Otherwise enqueue newly generated states (with related metadata).
             let mut is_terminal = true;
+            let mut successors = Vec::new();
             model.actions(&state, &mut actions);
-            for action in actions.drain(..) {
-                let next_state = match model.next_state(&state, action) {
+            // With partial order reduction, an action is "done" once it has been explored from
+            // this state or if it is in the sleep set, which means an equivalent interleaving
+            // has already been explored. As `next_state` consumes each action, the actions that
+            // are independent of it and might be done by the time it is explored are determined
+            // up front.
+            let mut action_fingerprints = Vec::new();
+            let mut is_done = Vec::new();
+            let mut independent = Vec::new();
+            if partial_order_reduction {
+                action_fingerprints = actions.iter().map(fingerprint).collect();
+                is_done = action_fingerprints
+                    .iter()
+                    .map(|f| sleep_set.contains(f))
+                    .collect();
+                independent = (0..actions.len())
+                    .map(|i| {
+                        if is_done[i] {
+                            return Vec::new(); // pruned below
+                        }
+                        (0..actions.len())
+                            .filter(|&j| {
+                                (j < i || is_done[j])
+                                    && model.independent(&state, &actions[j], &actions[i])
+                            })
+                            .collect()
+                    })
+                    .collect();
+            }
+            for (i, action) in actions.drain(..).enumerate() {
+                if partial_order_reduction && is_done[i] {
+                    pruned_state_count.fetch_add(1, Ordering::Relaxed);
+                    is_terminal = false;
+                    continue;
+                }
+                let is_discovered = |name| discoveries.contains_key(name);
+                let (next_state, violations) = match next_state_checking_transitions(
+                    model,
+                    &properties,
+                    &state,
+                    action,
+                    is_discovered,
+                ) {
                     None => continue,
-                    Some(next_state) => next_state,
+                    Some(step) => step,
                 };
 
                 // Skip if outside boundary.
@@ -297,6 +562,27 @@ where
                     continue;
                 }
                 state_count.fetch_add(1, Ordering::Relaxed);
+                if temporal.is_some() {
+                    successors.push(fingerprint(&next_state));
+                }
+
+                for name in violations {
+                    let mut fingerprints = fingerprints.clone();
+                    fingerprints.push(fingerprint(&next_state));
+                    // Races other threads, but that's fine.
+                    discoveries.insert(name, fingerprints);
+                }
+
+                // Independent actions that are done remain done after this action.
+                let mut next_sleep_set = SleepSet::new();
+                if partial_order_reduction {
+                    for &j in &independent[i] {
+                        if is_done[j] {
+                            next_sleep_set.push(action_fingerprints[j]);
+                        }
+                    }
+                    is_done[i] = true;
+                }
 
                 // Skip if already generated.
                 //
@@ -306,9 +592,10 @@ where
                 // property held on the path leading to the first visit as meaning
                 // that it holds in the path leading to the second visit -- another
                 // possible false-negative.
+                let state_fp = *fingerprints.last().unwrap();
                 let next_fingerprint = if let Some(representative) = symmetry {
                     let representative_fingerprint = fingerprint(&representative(&next_state));
-                    if !generated.insert(representative_fingerprint) {
+                    if !generated.insert(representative_fingerprint, Some(state_fp)) {
                         is_terminal = false;
                         continue;
                     }
@@ -316,9 +603,40 @@ where
                     // avoid jumping to another part of the state space for which there may not be
                     // a path extension from the previously collected path.
                     fingerprint(&next_state)
+                } else if partial_order_reduction {
+                    // Only nonempty sleep sets are recorded, as a state that was explored with an
+                    // empty sleep set never needs to be revisited.
+                    let next_fingerprint = fingerprint(&next_state);
+                    match sleep_sets.entry(next_fingerprint) {
+                        Entry::Vacant(entry) => {
+                            if !generated.insert(next_fingerprint, Some(state_fp)) {
+                                // Same caveat as below regarding loops and terminal states.
+                                is_terminal = false;
+                                continue;
+                            }
+                            if !next_sleep_set.is_empty() {
+                                entry.insert(next_sleep_set.clone());
+                            }
+                        }
+                        Entry::Occupied(mut entry) => {
+                            is_terminal = false;
+
+                            // The state must be revisited if it was previously explored with a
+                            // sleep set that skipped actions which this path does not skip.
+                            if entry.get().iter().all(|f| next_sleep_set.contains(f)) {
+                                continue;
+                            }
+                            entry.get_mut().retain(|f| next_sleep_set.contains(f));
+                            next_sleep_set = entry.get().clone();
+                            if next_sleep_set.is_empty() {
+                                entry.remove();
+                            }
+                        }
+                    }
+                    next_fingerprint
                 } else {
                     let next_fingerprint = fingerprint(&next_state);
-                    if !generated.insert(next_fingerprint) {
+                    if !generated.insert(next_fingerprint, Some(state_fp)) {
                         // FIXME: arriving at an already-known state may be a loop (in which case it
                         // could, in a fancier implementation, be considered a terminal state for
                         // purposes of eventually-property checking) but it might also be a join in
@@ -345,8 +663,12 @@ where
                     next_fingerprints,
                     ebits.clone(),
                     NonZeroUsize::new(max_depth.get() + 1).unwrap(),
+                    next_sleep_set,
                 ));
             }
+            if let Some(temporal) = temporal {
+                temporal.record(model, &state, *fingerprints.last().unwrap(), successors);
+            }
             if is_terminal {
                 for (i, property) in properties.iter().enumerate() {
                     if ebits.contains(i) {
@@ -380,6 +702,14 @@ where
         self.max_depth.load(Ordering::Relaxed)
     }
 
+    fn coverage(&self) -> Coverage {
+        self.generated.coverage()
+    }
+
+    fn pruned_state_count(&self) -> usize {
+        self.pruned_state_count.load(Ordering::Relaxed)
+    }
+
     fn discoveries(&self) -> HashMap<&'static str, Path<M::State, M::Action>> {
         self.discoveries
             .iter()
@@ -397,7 +727,8 @@ where
     }
 
     fn is_done(&self) -> bool {
-        self.job_broker.is_closed() || self.discoveries.len() == self.model.properties().len()
+        (self.job_broker.is_closed() && !self.is_analyzing.load(Ordering::Relaxed))
+            || self.discoveries.len() == self.model.properties().len()
     }
 }
 
@@ -572,6 +903,52 @@ mod test {
         assert_eq!(checker.unique_state_count(), 6);
     }
 
+    #[test]
+    fn can_use_bitstate_store() {
+        let checker = LinearEquation { a: 2, b: 10, c: 14 }
+            .checker()
+            .visited_store(BitstateStore::new(1 << 16, 3))
+            .spawn_dfs()
+            .join();
+        checker.assert_properties();
+        assert_eq!(checker.unique_state_count(), 55);
+        assert!(matches!(
+            checker.coverage(),
+            Coverage::Probabilistic { false_positive_rate } if false_positive_rate < 1e-6
+        ));
+    }
+
+    #[test]
+    fn can_resume_from_checkpoint() {
+        let path = std::env::temp_dir().join(format!(
+            "stateright-dfs-checkpoint-{}.json",
+            std::process::id()
+        ));
+        let checker = || {
+            LinearEquation { a: 2, b: 4, c: 7 }
+                .checker()
+                .target_max_depth(80)
+        };
+
+        // Stops after checking two blocks of states, but a checkpoint is saved after the first.
+        let interrupted = checker()
+            .target_state_count(4_000)
+            .checkpoint_to(&path, std::time::Duration::ZERO)
+            .spawn_dfs()
+            .join();
+        let resumed = checker().resume_from(&path).spawn_dfs().join();
+        std::fs::remove_file(&path).unwrap();
+
+        let uninterrupted = checker().spawn_dfs().join();
+        assert!(interrupted.unique_state_count() < uninterrupted.unique_state_count());
+        assert_eq!(resumed.state_count(), uninterrupted.state_count());
+        assert_eq!(
+            resumed.unique_state_count(),
+            uninterrupted.unique_state_count()
+        );
+        resumed.assert_no_discovery("solvable");
+    }
+
     // test that the checker shuts down all threads properly after a checker thread encounters a
     // panic in the model execution.
     #[test]
@@ -0,0 +1,750 @@
+//! Private module for selective re-export.
+
+use crate::checker::{
+    next_state_checking_transitions, Checker, EventuallyBits, Expectation, Path, VisitedStore,
+};
+use crate::has_discoveries::HasDiscoveries;
+use crate::{fingerprint, CheckerBuilder, Fingerprint, Model, Property};
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::hash::Hash;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// A coordinator for a breadth-first search that is split across worker processes. Each worker
+/// owns the states whose fingerprints map to its index, so a worker only deduplicates and checks
+/// its own states, sending the others to the coordinator to be forwarded to their owners.
+///
+/// Workers report their progress after each block of states, and checking is complete once every
+/// worker has reported that it is idle after receiving every batch of states sent to it.
+pub(crate) struct DistributedChecker<M: Model> {
+    model: Arc<M>,
+    handles: Vec<JoinHandle<()>>,
+    init_state_count: usize,
+    progress: Arc<Mutex<Vec<Progress>>>,
+    discoveries: Arc<DashMap<&'static str, Vec<Fingerprint>>>,
+    is_done: Arc<AtomicBool>,
+}
+
+/// The fingerprint of a discovered state, along with the fingerprint of a subsequent state if the
+/// discovery is a step (for a `transition` property).
+type Discovery = (Fingerprint, Option<Fingerprint>);
+
+/// A state along with the fingerprint of its source (if any), its depth, and the indices of
+/// `eventually` properties that have not held on the path to it.
+#[derive(Deserialize, Serialize)]
+struct Job<State> {
+    state: State,
+    fingerprint: Fingerprint,
+    source: Option<Fingerprint>,
+    depth: usize,
+    ebits: Vec<usize>,
+}
+
+#[derive(Deserialize, Serialize)]
+enum Message<State> {
+    /// Sent by the coordinator when a worker connects.
+    Assign { index: usize, worker_count: usize },
+    /// States owned by worker `to`, which the coordinator forwards.
+    Jobs { to: usize, jobs: Vec<Job<State>> },
+    /// Sent by a worker after each block of states.
+    Progress(Progress),
+    /// Sent by a worker when a property first has a discovery.
+    Discovery { name: String, discovery: Discovery },
+    /// Sent by the coordinator to reconstruct the path to a discovery.
+    Lookup { fingerprint: Fingerprint },
+    /// A worker's reply to [`Message::Lookup`].
+    Source {
+        fingerprint: Fingerprint,
+        is_known: bool,
+        source: Option<Fingerprint>,
+    },
+    /// Sent by the coordinator once checking is complete.
+    Stop,
+}
+
+#[derive(Clone, Copy, Default, Deserialize, Serialize)]
+struct Progress {
+    state_count: usize,
+    unique_state_count: usize,
+    max_depth: usize,
+    /// The number of [`Message::Jobs`] that the worker has received.
+    received_count: usize,
+    /// Whether the worker has checked every state that it has received.
+    is_idle: bool,
+}
+
+impl<M> DistributedChecker<M>
+where
+    M: Model + Send + Sync + 'static,
+    M::State: Hash + Serialize + DeserializeOwned + Send + 'static,
+{
+    pub(crate) fn spawn(
+        options: CheckerBuilder<M>,
+        addresses: impl ToSocketAddrs,
+        worker_count: usize,
+    ) -> Self {
+        assert!(worker_count > 0, "A distributed check requires a worker.");
+        let listener = TcpListener::bind(addresses)
+            .unwrap_or_else(|err| panic!("Unable to listen for distributed workers: {}", err));
+        let model = Arc::new(options.model);
+        let init_states: Vec<_> = model
+            .init_states()
+            .into_iter()
+            .filter(|s| model.within_boundary(s))
+            .collect();
+        let init_state_count = init_states.len();
+        let progress = Arc::new(Mutex::new(vec![Progress::default(); worker_count]));
+        let discoveries = Arc::new(DashMap::default());
+        let is_done = Arc::new(AtomicBool::new(false));
+
+        let handle = {
+            let model = Arc::clone(&model);
+            let progress = Arc::clone(&progress);
+            let discoveries = Arc::clone(&discoveries);
+            let is_done = Arc::clone(&is_done);
+            let finish_when = options.finish_when;
+            let target_state_count = options.target_state_count;
+            let close_at = options.timeout.map(|t| Instant::now() + t);
+            std::thread::Builder::new()
+                .name("checker-0".to_string())
+                .spawn(move || {
+                    let mut coordinator = Coordinator {
+                        model: &*model,
+                        progress: &progress,
+                        writers: Vec::new(),
+                        sent_counts: vec![0; worker_count],
+                        found: BTreeMap::new(),
+                    };
+                    let result = coordinator.run(
+                        listener,
+                        init_states,
+                        &finish_when,
+                        target_state_count,
+                        close_at,
+                    );
+                    coordinator.stop();
+                    match result {
+                        Ok(found) => {
+                            for (name, fingerprints) in found {
+                                discoveries.insert(name, fingerprints);
+                            }
+                        }
+                        Err(err) => log::error!("Distributed check failed: {}", err),
+                    }
+                    is_done.store(true, Ordering::Relaxed);
+                })
+                .expect("Failed to spawn a thread")
+        };
+        DistributedChecker {
+            model,
+            handles: vec![handle],
+            init_state_count,
+            progress,
+            discoveries,
+            is_done,
+        }
+    }
+}
+
+struct Coordinator<'a, M: Model> {
+    model: &'a M,
+    progress: &'a Mutex<Vec<Progress>>,
+    writers: Vec<BufWriter<TcpStream>>,
+    /// The number of [`Message::Jobs`] sent to each worker.
+    sent_counts: Vec<usize>,
+    found: BTreeMap<&'static str, Discovery>,
+}
+
+impl<M> Coordinator<'_, M>
+where
+    M: Model,
+    M::State: Hash + Serialize + DeserializeOwned + Send + 'static,
+{
+    /// Accepts connections from workers and then forwards states between them until checking is
+    /// complete, returning the paths to discoveries.
+    fn run(
+        &mut self,
+        listener: TcpListener,
+        init_states: Vec<M::State>,
+        finish_when: &HasDiscoveries,
+        target_state_count: Option<NonZeroUsize>,
+        close_at: Option<Instant>,
+    ) -> std::io::Result<Vec<(&'static str, Vec<Fingerprint>)>> {
+        let worker_count = self.sent_counts.len();
+        let (sender, receiver) = channel();
+        for index in 0..worker_count {
+            let (stream, address) = listener.accept()?;
+            log::info!("Worker {} connected from {}.", index, address);
+            receive(stream.try_clone()?, index, sender.clone());
+            self.writers.push(BufWriter::new(stream));
+            self.send(
+                index,
+                &Message::Assign {
+                    index,
+                    worker_count,
+                },
+            )?;
+        }
+        drop(sender);
+
+        // Initial states are sent to their owners like any other states.
+        let properties = self.model.properties();
+        let ebits: Vec<_> = properties
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.expectation == Expectation::Eventually)
+            .map(|(i, _)| i)
+            .collect();
+        let mut batches: Vec<Vec<_>> = (0..worker_count).map(|_| Vec::new()).collect();
+        for state in init_states {
+            let fp = fingerprint(&state);
+            batches[owner(fp, worker_count)].push(Job {
+                state,
+                fingerprint: fp,
+                source: None,
+                depth: 1,
+                ebits: ebits.clone(),
+            });
+        }
+        for (to, jobs) in batches.into_iter().enumerate() {
+            if !jobs.is_empty() {
+                self.send(to, &Message::Jobs { to, jobs })?;
+            }
+        }
+
+        loop {
+            let (is_complete, state_count) = {
+                let progress = self.progress.lock();
+                (
+                    progress
+                        .iter()
+                        .zip(&self.sent_counts)
+                        .all(|(p, sent_count)| p.is_idle && p.received_count == *sent_count),
+                    progress.iter().map(|p| p.state_count).sum::<usize>(),
+                )
+            };
+            if is_complete
+                || finish_when.matches(&self.found.keys().copied().collect(), &properties)
+                || matches!(target_state_count, Some(t) if t.get() <= state_count)
+                || matches!(close_at, Some(t) if t <= Instant::now())
+            {
+                break;
+            }
+            let (index, message) = match receiver.recv_timeout(Duration::from_millis(100)) {
+                Ok(received) => received,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => unreachable!("receivers outlive writers"),
+            };
+            match message {
+                Some(Message::Jobs { to, jobs }) => self.send(to, &Message::Jobs { to, jobs })?,
+                Some(Message::Progress(update)) => self.progress.lock()[index] = update,
+                Some(Message::Discovery { name, discovery }) => {
+                    if let Some(property) = properties.iter().find(|p| p.name == name) {
+                        self.found.entry(property.name).or_insert(discovery);
+                    }
+                }
+                Some(_) => log::warn!("Ignoring unexpected message from worker {}.", index),
+                None => return Err(lost_connection(index)),
+            }
+        }
+
+        // Workers only report the final state of each discovery, so each path is reconstructed
+        // by asking the owners of its states for their sources.
+        let mut paths = Vec::new();
+        for (name, (fp, next_fp)) in std::mem::take(&mut self.found) {
+            let mut fingerprints = VecDeque::from_iter(next_fp);
+            let mut source = Some(fp);
+            while let Some(fp) = source {
+                fingerprints.push_front(fp);
+                self.send(
+                    owner(fp, worker_count),
+                    &Message::Lookup { fingerprint: fp },
+                )?;
+                source = loop {
+                    match receiver.recv() {
+                        Ok((
+                            _,
+                            Some(Message::Source {
+                                fingerprint,
+                                is_known,
+                                source,
+                            }),
+                        )) if fingerprint == fp => {
+                            if !is_known {
+                                return Err(Error::new(
+                                    ErrorKind::InvalidData,
+                                    format!("worker has no record of state {}", fp),
+                                ));
+                            }
+                            break source;
+                        }
+                        Ok((_, Some(_))) => continue, // Workers may still be checking.
+                        Ok((index, None)) => return Err(lost_connection(index)),
+                        Err(_) => unreachable!("receivers outlive writers"),
+                    }
+                };
+            }
+            paths.push((name, fingerprints.into()));
+        }
+        Ok(paths)
+    }
+
+    /// Tells the workers to stop, which is necessary even if checking failed.
+    fn stop(&mut self) {
+        for index in 0..self.writers.len() {
+            let _ = self.send(index, &Message::Stop);
+        }
+    }
+
+    fn send(&mut self, index: usize, message: &Message<M::State>) -> std::io::Result<()> {
+        if let Message::Jobs { .. } = message {
+            self.sent_counts[index] += 1;
+        }
+        send(&mut self.writers[index], message)
+    }
+}
+
+/// Connects to a coordinator and checks the states that it assigns until it sends
+/// [`Message::Stop`].
+pub(crate) fn serve_worker<M>(
+    options: CheckerBuilder<M>,
+    coordinator: impl ToSocketAddrs,
+) -> std::io::Result<()>
+where
+    M: Model,
+    M::State: Hash + Serialize + DeserializeOwned + Send + 'static,
+{
+    if !options.visited_store.retains_sources() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "a distributed worker requires a visited store that retains sources",
+        ));
+    }
+    let stream = TcpStream::connect(coordinator)?;
+    let (sender, receiver) = channel();
+    receive(stream.try_clone()?, 0, sender);
+    let mut writer = BufWriter::new(stream);
+    let result = Worker {
+        model: options.model,
+        generated: options.visited_store,
+        target_max_depth: options.target_max_depth,
+        progress: Progress::default(),
+        discoveries: HashMap::new(),
+    }
+    .run(&receiver, &mut writer);
+    match result {
+        // The coordinator disconnects as soon as it has sent `Stop`, so sending may fail first.
+        Err(_)
+            if receiver
+                .iter()
+                .any(|(_, m)| matches!(m, Some(Message::Stop))) =>
+        {
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+struct Worker<M: Model> {
+    model: M,
+    generated: Box<dyn VisitedStore>,
+    target_max_depth: Option<NonZeroUsize>,
+    progress: Progress,
+    discoveries: HashMap<&'static str, Discovery>,
+}
+
+impl<M> Worker<M>
+where
+    M: Model,
+    M::State: Hash + Serialize + DeserializeOwned,
+{
+    fn run(
+        &mut self,
+        receiver: &Receiver<(usize, Option<Message<M::State>>)>,
+        writer: &mut BufWriter<TcpStream>,
+    ) -> std::io::Result<()> {
+        let (index, worker_count) = match receiver.recv() {
+            Ok((
+                _,
+                Some(Message::Assign {
+                    index,
+                    worker_count,
+                }),
+            )) => (index, worker_count),
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "expected an assignment from the coordinator",
+                ))
+            }
+        };
+        log::info!("Checking as worker {} of {}.", index, worker_count);
+
+        let mut pending = VecDeque::new();
+        let mut outgoing: Vec<Vec<_>> = (0..worker_count).map(|_| Vec::new()).collect();
+        loop {
+            // Step 1: Receive work, only blocking if there is nothing else to do.
+            loop {
+                let message = if pending.is_empty() {
+                    if !self.progress.is_idle {
+                        self.progress.is_idle = true;
+                        send(writer, &Message::<M::State>::Progress(self.progress))?;
+                    }
+                    receiver.recv().ok().and_then(|(_, m)| m)
+                } else {
+                    match receiver.try_recv() {
+                        Ok((_, message)) => message,
+                        Err(TryRecvError::Empty) => break,
+                        Err(TryRecvError::Disconnected) => None,
+                    }
+                };
+                match message {
+                    Some(Message::Jobs { jobs, .. }) => {
+                        self.progress.received_count += 1;
+                        self.progress.is_idle = false;
+                        for job in jobs {
+                            if self.generated.insert(job.fingerprint, job.source) {
+                                pending.push_back(job);
+                            }
+                        }
+                    }
+                    Some(Message::Lookup { fingerprint }) => {
+                        let source = self.generated.source(fingerprint);
+                        send(
+                            writer,
+                            &Message::<M::State>::Source {
+                                fingerprint,
+                                is_known: source.is_some(),
+                                source: source.flatten(),
+                            },
+                        )?;
+                    }
+                    Some(Message::Stop) => return Ok(()),
+                    Some(_) => log::warn!("Ignoring unexpected message from the coordinator."),
+                    None => return Err(lost_connection(0)),
+                }
+            }
+
+            // Step 2: Do work.
+            let discovery_count = self.discoveries.len();
+            self.check_block(&mut pending, &mut outgoing, index, 1500);
+
+            // Step 3: Share work and progress.
+            for (to, jobs) in outgoing.iter_mut().enumerate() {
+                if !jobs.is_empty() {
+                    let jobs = std::mem::take(jobs);
+                    send(writer, &Message::Jobs { to, jobs })?;
+                }
+            }
+            if self.discoveries.len() > discovery_count {
+                for (name, discovery) in &self.discoveries {
+                    send(
+                        writer,
+                        &Message::<M::State>::Discovery {
+                            name: name.to_string(),
+                            discovery: *discovery,
+                        },
+                    )?;
+                }
+            }
+            self.progress.unique_state_count = self.generated.len();
+            send(writer, &Message::<M::State>::Progress(self.progress))?;
+        }
+    }
+
+    fn check_block(
+        &mut self,
+        pending: &mut VecDeque<Job<M::State>>,
+        outgoing: &mut [Vec<Job<M::State>>],
+        index: usize,
+        max_count: usize,
+    ) {
+        let model = &self.model;
+        let properties = model.properties();
+        let mut actions = Vec::new();
+        for _ in 0..max_count {
+            // Done if none pending.
+            let Some(Job {
+                state,
+                fingerprint: state_fp,
+                depth,
+                ebits,
+                ..
+            }) = pending.pop_front()
+            else {
+                return;
+            };
+            let mut ebits: EventuallyBits = ebits.into_iter().collect();
+            self.progress.max_depth = self.progress.max_depth.max(depth);
+            if let Some(target_max_depth) = self.target_max_depth {
+                if depth >= target_max_depth.get() {
+                    log::trace!("Skipping state as past max depth {}", depth);
+                    continue;
+                }
+            }
+
+            for (i, property) in properties.iter().enumerate() {
+                if self.discoveries.contains_key(property.name) {
+                    continue;
+                }
+                match property {
+                    Property {
+                        expectation: Expectation::Always,
+                        condition: always,
+                        ..
+                    } => {
+                        if !always(model, &state) {
+                            self.discoveries.insert(property.name, (state_fp, None));
+                        }
+                    }
+                    Property {
+                        expectation: Expectation::Sometimes,
+                        condition: sometimes,
+                        ..
+                    } => {
+                        if sometimes(model, &state) {
+                            self.discoveries.insert(property.name, (state_fp, None));
+                        }
+                    }
+                    Property {
+                        expectation: Expectation::Eventually,
+                        condition: eventually,
+                        ..
+                    } => {
+                        if eventually(model, &state) {
+                            ebits.remove(i);
+                        }
+                    }
+                    Property {
+                        expectation: Expectation::Temporal | Expectation::Transition,
+                        ..
+                    } => {
+                        // Temporal properties are rejected when spawned, as no worker has the
+                        // full state graph, and transition properties are checked below for each
+                        // step.
+                    }
+                }
+            }
+
+            // Otherwise share newly generated states with their owners.
+            let mut is_terminal = true;
+            model.actions(&state, &mut actions);
+            for action in actions.drain(..) {
+                let is_discovered = |name| self.discoveries.contains_key(name);
+                let (next_state, violations) = match next_state_checking_transitions(
+                    model,
+                    &properties,
+                    &state,
+                    action,
+                    is_discovered,
+                ) {
+                    None => continue,
+                    Some(step) => step,
+                };
+
+                // Skip if outside boundary.
+                if !model.within_boundary(&next_state) {
+                    continue;
+                }
+                self.progress.state_count += 1;
+                is_terminal = false;
+                let next_fingerprint = fingerprint(&next_state);
+
+                for name in violations {
+                    self.discoveries
+                        .insert(name, (state_fp, Some(next_fingerprint)));
+                }
+
+                let job = Job {
+                    state: next_state,
+                    fingerprint: next_fingerprint,
+                    source: Some(state_fp),
+                    depth: depth + 1,
+                    ebits: ebits.iter().collect(),
+                };
+                let owner = owner(next_fingerprint, outgoing.len());
+                if owner != index {
+                    outgoing[owner].push(job);
+                } else if self.generated.insert(next_fingerprint, Some(state_fp)) {
+                    pending.push_back(job);
+                }
+            }
+            if is_terminal {
+                for (i, property) in properties.iter().enumerate() {
+                    if ebits.contains(i) {
+                        self.discoveries
+                            .entry(property.name)
+                            .or_insert((state_fp, None));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The index of the worker that checks a state.
+fn owner(fingerprint: Fingerprint, worker_count: usize) -> usize {
+    (fingerprint.get() % worker_count as u64) as usize
+}
+
+fn lost_connection(index: usize) -> Error {
+    Error::new(
+        ErrorKind::ConnectionAborted,
+        format!("lost connection to peer {}", index),
+    )
+}
+
+fn send<State: Serialize>(
+    writer: &mut BufWriter<TcpStream>,
+    message: &Message<State>,
+) -> std::io::Result<()> {
+    serde_json::to_writer(&mut *writer, message)?;
+    writer.write_all(b"\n")?;
+    writer.flush()
+}
+
+/// Forwards messages from a connection to a channel, followed by `None` once the connection
+/// closes.
+fn receive<State>(stream: TcpStream, index: usize, sender: Sender<(usize, Option<Message<State>>)>)
+where
+    State: DeserializeOwned + Send + 'static,
+{
+    std::thread::Builder::new()
+        .name(format!("receiver-{}", index))
+        .spawn(move || {
+            let reader = BufReader::new(stream);
+            for message in serde_json::Deserializer::from_reader(reader).into_iter() {
+                match message {
+                    Ok(message) => {
+                        if sender.send((index, Some(message))).is_err() {
+                            return;
+                        }
+                    }
+                    Err(err) => {
+                        if !err.is_eof() {
+                            log::debug!("{}: Unable to receive message: {}", index, err);
+                        }
+                        break;
+                    }
+                }
+            }
+            let _ = sender.send((index, None));
+        })
+        .expect("Failed to spawn a thread");
+}
+
+impl<M> Checker<M> for DistributedChecker<M>
+where
+    M: Model,
+    M::State: Hash,
+{
+    fn model(&self) -> &M {
+        &self.model
+    }
+
+    fn state_count(&self) -> usize {
+        self.init_state_count
+            + self
+                .progress
+                .lock()
+                .iter()
+                .map(|p| p.state_count)
+                .sum::<usize>()
+    }
+
+    fn unique_state_count(&self) -> usize {
+        self.progress
+            .lock()
+            .iter()
+            .map(|p| p.unique_state_count)
+            .sum()
+    }
+
+    fn max_depth(&self) -> usize {
+        self.progress
+            .lock()
+            .iter()
+            .map(|p| p.max_depth)
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn discoveries(&self) -> HashMap<&'static str, Path<M::State, M::Action>> {
+        self.discoveries
+            .iter()
+            .map(|mapref| {
+                (
+                    <&'static str>::clone(mapref.key()),
+                    Path::from_fingerprints(self.model(), VecDeque::from(mapref.value().clone())),
+                )
+            })
+            .collect()
+    }
+
+    fn handles(&mut self) -> Vec<JoinHandle<()>> {
+        std::mem::take(&mut self.handles)
+    }
+
+    fn is_done(&self) -> bool {
+        self.is_done.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test_util::linear_equation_solver::*;
+    use crate::*;
+    use std::net::{SocketAddr, TcpListener};
+
+    fn check(equation: LinearEquation, worker_count: usize) -> impl Checker<LinearEquation> {
+        let address: SocketAddr = {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap()
+        };
+        let checker = equation
+            .clone()
+            .checker()
+            .spawn_distributed_bfs(address, worker_count);
+        let workers: Vec<_> = (0..worker_count)
+            .map(|_| {
+                let equation = equation.clone();
+                std::thread::spawn(move || equation.checker().serve_distributed_worker(address))
+            })
+            .collect();
+        let checker = checker.join();
+        for worker in workers {
+            worker.join().unwrap().unwrap();
+        }
+        checker
+    }
+
+    #[test]
+    fn can_complete_by_enumerating_all_states() {
+        let equation = LinearEquation { a: 2, b: 4, c: 7 };
+        let checker = check(equation.clone(), 3);
+        assert!(checker.is_done());
+        checker.assert_no_discovery("solvable");
+        assert_eq!(checker.unique_state_count(), 256 * 256);
+
+        let bfs = equation.checker().spawn_bfs().join();
+        assert_eq!(checker.state_count(), bfs.state_count());
+    }
+
+    #[test]
+    fn can_complete_by_eliminating_properties() {
+        let checker = check(LinearEquation { a: 2, b: 10, c: 14 }, 2);
+        checker.assert_properties();
+        let (x, y) = *checker.discovery("solvable").unwrap().last_state();
+        assert_eq!((2 * x as u32 + 10 * y as u32) % 256, 14);
+    }
+}
@@ -428,6 +428,8 @@ mod test {
                         timers_set: vec![Timers::new(); 2],
                         random_choices: vec![RandomChoices::default(); 2],
                         crashed: vec![false; 2],
+                        durable_states: vec![None; 2],
+                        recoveries: 0,
                         network: Network::new_unordered_nonduplicating([
                             Envelope { src: Id::from(0), dst: Id::from(1), msg: Ping(0) },
                         ]),
@@ -453,6 +455,8 @@ mod test {
                     timers_set: vec![Timers::new(); 2],
                     random_choices: vec![RandomChoices::default(); 2],
                     crashed: vec![false; 2],
+                    durable_states: vec![None; 2],
+                    recoveries: 0,
                     network: Network::new_unordered_nonduplicating([Envelope {
                         src: Id::from(0),
                         dst: Id::from(1),
@@ -475,6 +479,8 @@ mod test {
                     timers_set: vec![Timers::new(); 2],
                     random_choices: vec![RandomChoices::default(); 2],
                     crashed: vec![false; 2],
+                    durable_states: vec![None; 2],
+                    recoveries: 0,
                     network: Network::new_unordered_nonduplicating([]),
                 }),
                 properties: vec![
@@ -501,6 +507,8 @@ mod test {
                     timers_set: vec![Timers::new(); 2],
                     random_choices: vec![RandomChoices::default(); 2],
                     crashed: vec![false; 2],
+                    durable_states: vec![None; 2],
+                    recoveries: 0,
                     network: Network::new_unordered_nonduplicating([
                         Envelope { src: Id::from(1), dst: Id::from(0), msg: Pong(0) },
                     ]),
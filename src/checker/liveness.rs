@@ -0,0 +1,1103 @@
+//! Private module for selective re-export.
+
+use crate::checker::{next_state_checking_transitions, Checker, Expectation, Path};
+use crate::formula::{Automaton, Nnf};
+use crate::{fingerprint, CheckerBuilder, FairnessKind, Fingerprint, Model, Property};
+use dashmap::DashMap;
+use id_set::IdSet;
+use nohash_hasher::NoHashHasher;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::hash::{BuildHasherDefault, Hash};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+type FingerprintMap<V> = HashMap<Fingerprint, V, BuildHasherDefault<NoHashHasher<u64>>>;
+
+pub(crate) struct LivenessChecker<M: Model> {
+    model: Arc<M>,
+    handles: Vec<JoinHandle<()>>,
+    state_count: Arc<AtomicUsize>,
+    unique_state_count: Arc<AtomicUsize>,
+    max_depth: Arc<AtomicUsize>,
+    discoveries: Arc<DashMap<&'static str, Vec<Fingerprint>>>,
+    is_done: Arc<AtomicBool>,
+}
+
+/// A visited state, along with the outgoing edges needed to search for cycles.
+struct Node {
+    parent: Option<Fingerprint>,
+    edges: Vec<Edge>,
+    is_expanded: bool,
+    /// The liveness property predicates that hold in this state.
+    predicate_bits: IdSet,
+}
+
+struct Edge {
+    action: Fingerprint,
+    fairness: Option<FairnessKind>,
+    dst: Fingerprint,
+}
+
+/// A state of the product of the state graph and an [`Automaton`].
+struct Vertex {
+    state: Fingerprint,
+    automaton_state: usize,
+    parent: Option<usize>,
+    edges: Vec<VertexEdge>,
+}
+
+/// An edge of the product. The action is absent when repeating a terminal state.
+struct VertexEdge {
+    action: Option<Fingerprint>,
+    fairness: Option<FairnessKind>,
+    dst: usize,
+}
+
+impl<M> LivenessChecker<M>
+where
+    M: Model + Send + Sync + 'static,
+    M::State: Hash + Send + Sync + 'static,
+    M::Action: Hash,
+{
+    pub(crate) fn spawn(options: CheckerBuilder<M>) -> Self {
+        let model = Arc::new(options.model);
+        let state_count = Arc::new(AtomicUsize::new(0));
+        let unique_state_count = Arc::new(AtomicUsize::new(0));
+        let max_depth = Arc::new(AtomicUsize::new(0));
+        let discoveries: Arc<DashMap<&'static str, Vec<Fingerprint>>> =
+            Arc::new(DashMap::default());
+        let is_done = Arc::new(AtomicBool::new(false));
+
+        let handle = {
+            let model = Arc::clone(&model);
+            let state_count = Arc::clone(&state_count);
+            let unique_state_count = Arc::clone(&unique_state_count);
+            let max_depth = Arc::clone(&max_depth);
+            let discoveries = Arc::clone(&discoveries);
+            let is_done = Arc::clone(&is_done);
+            let visitor = options.visitor;
+            let finish_when = options.finish_when;
+            let target_state_count = options.target_state_count;
+            let target_max_depth = options.target_max_depth;
+            let close_at = options.timeout.map(|t| Instant::now() + t);
+            std::thread::Builder::new()
+                .name("checker-0".to_string())
+                .spawn(move || {
+                    // Ensures `is_done` is set even if the model panics.
+                    struct SetOnDrop(Arc<AtomicBool>);
+                    impl Drop for SetOnDrop {
+                        fn drop(&mut self) {
+                            self.0.store(true, Ordering::Relaxed);
+                        }
+                    }
+                    let _set_on_drop = SetOnDrop(is_done);
+
+                    let properties = model.properties();
+                    let fairness = model.fairness();
+
+                    // Liveness properties are checked against automata for their negations, which
+                    // refer to state predicates by index.
+                    let mut predicates = Vec::new();
+                    let automata: Vec<Option<Automaton>> = properties
+                        .iter()
+                        .map(|property| match property.expectation {
+                            Expectation::Eventually => {
+                                predicates.push(property.condition);
+                                let never = Nnf::Atom(predicates.len() - 1, false);
+                                let always_never =
+                                    Nnf::Release(Box::new(Nnf::False), Box::new(never));
+                                Some(Automaton::new(always_never))
+                            }
+                            Expectation::Temporal => {
+                                let formula = property
+                                    .formula
+                                    .as_ref()
+                                    .expect("temporal property lacks a formula");
+                                Some(Automaton::new(formula.to_nnf(true, &mut predicates)))
+                            }
+                            Expectation::Always
+                            | Expectation::Sometimes
+                            | Expectation::Transition => None,
+                        })
+                        .collect();
+
+                    let mut graph = FingerprintMap::default();
+                    let mut inits = Vec::new();
+                    let mut pending = VecDeque::new();
+                    for s in model.init_states() {
+                        if !model.within_boundary(&s) {
+                            continue;
+                        }
+                        state_count.fetch_add(1, Ordering::Relaxed);
+                        let fp = fingerprint(&s);
+                        if graph.contains_key(&fp) {
+                            continue;
+                        }
+                        graph.insert(fp, Node::new(None));
+                        inits.push(fp);
+                        pending.push_back((s, fp, 1));
+                    }
+                    unique_state_count.store(graph.len(), Ordering::Relaxed);
+
+                    // Step 1: Explore the state space, checking safety properties along the way.
+                    let mut actions = Vec::new();
+                    while let Some((state, fp, depth)) = pending.pop_front() {
+                        if depth > max_depth.load(Ordering::Relaxed) {
+                            max_depth.store(depth, Ordering::Relaxed);
+                        }
+                        if let Some(target_max_depth) = target_max_depth {
+                            if depth >= target_max_depth.get() {
+                                continue;
+                            }
+                        }
+                        if let Some(visitor) = &visitor {
+                            visitor.visit(
+                                &*model,
+                                Path::from_fingerprints(&*model, trace(&graph, fp)),
+                            );
+                        }
+                        for property in &properties {
+                            match property {
+                                Property {
+                                    expectation: Expectation::Always,
+                                    condition: always,
+                                    ..
+                                } => {
+                                    if !discoveries.contains_key(property.name)
+                                        && !always(&*model, &state)
+                                    {
+                                        discoveries.insert(property.name, trace(&graph, fp).into());
+                                    }
+                                }
+                                Property {
+                                    expectation: Expectation::Sometimes,
+                                    condition: sometimes,
+                                    ..
+                                } => {
+                                    if !discoveries.contains_key(property.name)
+                                        && sometimes(&*model, &state)
+                                    {
+                                        discoveries.insert(property.name, trace(&graph, fp).into());
+                                    }
+                                }
+                                Property {
+                                    expectation: Expectation::Eventually | Expectation::Temporal,
+                                    ..
+                                } => {} // see `predicates`
+                                Property {
+                                    expectation: Expectation::Transition,
+                                    ..
+                                } => {} // see below
+                            }
+                        }
+                        let mut predicate_bits = IdSet::new();
+                        for (i, predicate) in predicates.iter().enumerate() {
+                            if predicate(&*model, &state) {
+                                predicate_bits.insert(i);
+                            }
+                        }
+
+                        let mut edges = Vec::new();
+                        model.actions(&state, &mut actions);
+                        for action in actions.drain(..) {
+                            let action_fp = fingerprint(&action);
+                            let action_fairness = fairness
+                                .iter()
+                                .filter(|f| (f.actions)(&*model, &action))
+                                .map(|f| f.kind)
+                                .max();
+                            let is_discovered = |name| discoveries.contains_key(name);
+                            let (next_state, violations) = match next_state_checking_transitions(
+                                &*model,
+                                &properties,
+                                &state,
+                                action,
+                                is_discovered,
+                            ) {
+                                None => continue,
+                                Some(step) => step,
+                            };
+                            if !model.within_boundary(&next_state) {
+                                continue;
+                            }
+                            state_count.fetch_add(1, Ordering::Relaxed);
+                            let next_fp = fingerprint(&next_state);
+                            for name in violations {
+                                let mut fingerprints = trace(&graph, fp);
+                                fingerprints.push_back(next_fp);
+                                discoveries.insert(name, fingerprints.into());
+                            }
+                            edges.push(Edge {
+                                action: action_fp,
+                                fairness: action_fairness,
+                                dst: next_fp,
+                            });
+                            if let Entry::Vacant(entry) = graph.entry(next_fp) {
+                                entry.insert(Node::new(Some(fp)));
+                                pending.push_back((next_state, next_fp, depth + 1));
+                            }
+                        }
+                        let node = graph.get_mut(&fp).unwrap();
+                        node.edges = edges;
+                        node.is_expanded = true;
+                        node.predicate_bits = predicate_bits;
+                        unique_state_count.store(graph.len(), Ordering::Relaxed);
+
+                        if finish_when
+                            .matches(&discoveries.iter().map(|r| *r.key()).collect(), &properties)
+                        {
+                            log::debug!("Discovery complete. Shutting down...");
+                            return;
+                        }
+                        if let Some(target_state_count) = target_state_count {
+                            if target_state_count.get() <= state_count.load(Ordering::Relaxed) {
+                                log::debug!("Reached target state count. Analyzing...");
+                                break;
+                            }
+                        }
+                        if let Some(close_at) = close_at {
+                            if Instant::now() >= close_at {
+                                log::debug!("Reached timeout. Analyzing...");
+                                break;
+                            }
+                        }
+                    }
+
+                    // Step 2: Search the product of the state graph and the automaton for each
+                    //         negated liveness property, which accepts the counterexamples. States
+                    //         that were never expanded are treated as unknown, so they neither
+                    //         terminate nor continue a counterexample.
+                    for (property, automaton) in properties.iter().zip(&automata) {
+                        let automaton = match automaton {
+                            Some(automaton) if !discoveries.contains_key(property.name) => {
+                                automaton
+                            }
+                            _ => continue,
+                        };
+                        let product = product(&graph, &inits, automaton);
+                        if let Some(fingerprints) = find_counterexample(&graph, &product, automaton)
+                        {
+                            discoveries.insert(property.name, fingerprints);
+                        }
+                    }
+                })
+                .expect("Failed to spawn a thread")
+        };
+
+        LivenessChecker {
+            model,
+            handles: vec![handle],
+            state_count,
+            unique_state_count,
+            max_depth,
+            discoveries,
+            is_done,
+        }
+    }
+}
+
+impl Node {
+    fn new(parent: Option<Fingerprint>) -> Self {
+        Node {
+            parent,
+            edges: Vec::new(),
+            is_expanded: false,
+            predicate_bits: IdSet::new(),
+        }
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.is_expanded && self.edges.is_empty()
+    }
+}
+
+/// The state graph that the BFS and DFS checkers record for [`Property::temporal`] properties,
+/// which are checked against the product of the graph and an automaton once exploration stops.
+/// Unlike [`LivenessChecker`], fairness constraints are not taken into account.
+pub(crate) struct TemporalGraph<M: Model> {
+    automata: Vec<(&'static str, Automaton)>,
+    predicates: Vec<fn(&M, &M::State) -> bool>,
+    inits: Vec<Fingerprint>,
+    /// The successors of each explored state, along with the predicates that hold in it.
+    nodes: DashMap<Fingerprint, (Vec<Fingerprint>, IdSet), BuildHasherDefault<NoHashHasher<u64>>>,
+}
+
+impl<M: Model> TemporalGraph<M> {
+    /// Returns `None` if none of the properties are temporal, as the graph need not be recorded.
+    pub(crate) fn new(properties: &[Property<M>], inits: Vec<Fingerprint>) -> Option<Self> {
+        let mut predicates = Vec::new();
+        let automata: Vec<_> = properties
+            .iter()
+            .filter(|property| property.expectation == Expectation::Temporal)
+            .map(|property| {
+                let formula = property
+                    .formula
+                    .as_ref()
+                    .expect("temporal property lacks a formula");
+                (
+                    property.name,
+                    Automaton::new(formula.to_nnf(true, &mut predicates)),
+                )
+            })
+            .collect();
+        if automata.is_empty() {
+            return None;
+        }
+        Some(TemporalGraph {
+            automata,
+            predicates,
+            inits,
+            nodes: DashMap::default(),
+        })
+    }
+
+    /// Records the successors of an explored state.
+    pub(crate) fn record(
+        &self,
+        model: &M,
+        state: &M::State,
+        fp: Fingerprint,
+        successors: Vec<Fingerprint>,
+    ) {
+        let mut predicate_bits = IdSet::new();
+        for (i, predicate) in self.predicates.iter().enumerate() {
+            if predicate(model, state) {
+                predicate_bits.insert(i);
+            }
+        }
+        self.nodes.insert(fp, (successors, predicate_bits));
+    }
+
+    /// Spawns a thread that waits for the `workers` to stop exploring and then searches the
+    /// recorded graph for a counterexample to each temporal property that lacks a discovery.
+    /// States that were never recorded are treated as unknown, as with [`LivenessChecker`].
+    /// `is_analyzing` is cleared once the search completes.
+    pub(crate) fn spawn_analysis(
+        self: Arc<Self>,
+        workers: Vec<JoinHandle<()>>,
+        is_analyzing: Arc<AtomicBool>,
+        is_discovered: impl Fn(&'static str) -> bool + Send + 'static,
+        mut discover: impl FnMut(&'static str, Vec<Fingerprint>) + Send + 'static,
+    ) -> JoinHandle<()>
+    where
+        M: 'static,
+    {
+        std::thread::Builder::new()
+            .name("checker-temporal".to_string())
+            .spawn(move || {
+                struct ClearOnDrop(Arc<AtomicBool>);
+                impl Drop for ClearOnDrop {
+                    fn drop(&mut self) {
+                        self.0.store(false, Ordering::Relaxed);
+                    }
+                }
+                let _clear_on_drop = ClearOnDrop(is_analyzing);
+
+                for worker in workers {
+                    if let Err(err) = worker.join() {
+                        std::panic::resume_unwind(err);
+                    }
+                }
+                log::debug!("Exploration complete. Analyzing temporal properties...");
+
+                // Fairness is not taken into account, so actions are only identified by the
+                // states that they lead to.
+                let mut graph = FingerprintMap::default();
+                for entry in self.nodes.iter() {
+                    let (fp, (successors, predicate_bits)) = entry.pair();
+                    let edges = successors
+                        .iter()
+                        .map(|&dst| Edge {
+                            action: dst,
+                            fairness: None,
+                            dst,
+                        })
+                        .collect();
+                    graph.insert(
+                        *fp,
+                        Node {
+                            parent: None,
+                            edges,
+                            is_expanded: true,
+                            predicate_bits: predicate_bits.clone(),
+                        },
+                    );
+                }
+                let unknown: Vec<Fingerprint> = graph
+                    .values()
+                    .flat_map(|node| node.edges.iter().map(|e| e.dst))
+                    .chain(self.inits.iter().copied())
+                    .filter(|fp| !graph.contains_key(fp))
+                    .collect();
+                for fp in unknown {
+                    graph.insert(fp, Node::new(None));
+                }
+
+                for (name, automaton) in &self.automata {
+                    if is_discovered(name) {
+                        continue;
+                    }
+                    let product = product(&graph, &self.inits, automaton);
+                    if let Some(fingerprints) = find_counterexample(&graph, &product, automaton) {
+                        discover(name, fingerprints);
+                    }
+                }
+            })
+            .expect("Failed to spawn a thread")
+    }
+}
+
+/// Follows parent pointers back to an initial state.
+fn trace(graph: &FingerprintMap<Node>, fp: Fingerprint) -> VecDeque<Fingerprint> {
+    let mut fingerprints = VecDeque::new();
+    let mut next = Some(fp);
+    while let Some(fp) = next {
+        fingerprints.push_front(fp);
+        next = graph[&fp].parent;
+    }
+    fingerprints
+}
+
+/// Builds the reachable part of the product of the state graph and an automaton, numbering
+/// vertices in breadth-first order. Terminal states repeat forever.
+fn product(
+    graph: &FingerprintMap<Node>,
+    inits: &[Fingerprint],
+    automaton: &Automaton,
+) -> Vec<Vertex> {
+    let admits = |fp: &Fingerprint, automaton_state: usize| {
+        let node = &graph[fp];
+        node.is_expanded
+            && automaton.states[automaton_state]
+                .literals
+                .iter()
+                .all(|(i, value)| node.predicate_bits.contains(*i) == *value)
+    };
+    let mut vertices = Vec::new();
+    let mut ids = HashMap::new();
+    let mut add_vertex = |vertices: &mut Vec<Vertex>, state, automaton_state, parent| {
+        *ids.entry((state, automaton_state)).or_insert_with(|| {
+            vertices.push(Vertex {
+                state,
+                automaton_state,
+                parent,
+                edges: Vec::new(),
+            });
+            vertices.len() - 1
+        })
+    };
+    for init in inits {
+        for (automaton_state, s) in automaton.states.iter().enumerate() {
+            if s.is_initial && admits(init, automaton_state) {
+                add_vertex(&mut vertices, *init, automaton_state, None);
+            }
+        }
+    }
+    let mut id = 0;
+    while id < vertices.len() {
+        let Vertex {
+            state,
+            automaton_state,
+            ..
+        } = vertices[id];
+        let node = &graph[&state];
+        let steps: Vec<_> = if node.is_terminal() {
+            vec![(None, None, state)]
+        } else {
+            node.edges
+                .iter()
+                .map(|e| (Some(e.action), e.fairness, e.dst))
+                .collect()
+        };
+        let mut edges = Vec::new();
+        for (action, fairness, dst) in steps {
+            for &successor in &automaton.states[automaton_state].successors {
+                if admits(&dst, successor) {
+                    edges.push(VertexEdge {
+                        action,
+                        fairness,
+                        dst: add_vertex(&mut vertices, dst, successor, Some(id)),
+                    });
+                }
+            }
+        }
+        vertices[id].edges = edges;
+        id += 1;
+    }
+    vertices
+}
+
+/// Finds a path through the product to a fair cycle that visits every acceptance set, preferring
+/// cycles that are closest to an initial state. A cycle is indicated by a final fingerprint that
+/// also appears earlier in the path, unless the path ends in a terminal state.
+fn find_counterexample(
+    graph: &FingerprintMap<Node>,
+    vertices: &[Vertex],
+    automaton: &Automaton,
+) -> Option<Vec<Fingerprint>> {
+    let all: Vec<usize> = (0..vertices.len()).collect();
+    let mut components = strongly_connected_components(vertices, &all, |_| true);
+    components.sort_by_key(|c| c.first().copied());
+    for component in components {
+        if let Some(cycle) = find_fair_cycle(graph, vertices, automaton, &component) {
+            let mut path = vec![cycle[0]];
+            while let Some(parent) = vertices[*path.last().unwrap()].parent {
+                path.push(parent);
+            }
+            path.reverse();
+            path.extend(&cycle[1..]);
+            let mut fingerprints: Vec<_> = path.into_iter().map(|v| vertices[v].state).collect();
+            if let Some(i) = fingerprints.iter().position(|fp| graph[fp].is_terminal()) {
+                fingerprints.truncate(i + 1);
+            }
+            return Some(fingerprints);
+        }
+    }
+    None
+}
+
+/// Finds a cycle within a strongly connected component that visits every acceptance set without
+/// violating a fairness constraint. The returned cycle starts and ends with the same vertex.
+fn find_fair_cycle(
+    graph: &FingerprintMap<Node>,
+    vertices: &[Vertex],
+    automaton: &Automaton,
+    component: &BTreeSet<usize>,
+) -> Option<Vec<usize>> {
+    let entry = *component.first().unwrap();
+    let is_nontrivial = component.len() > 1 || vertices[entry].edges.iter().any(|e| e.dst == entry);
+    if !is_nontrivial {
+        return None;
+    }
+    let mut accepting = Vec::new();
+    for set in 0..automaton.acceptance_set_count() {
+        let vertex = component.iter().copied().find(|v| {
+            automaton.states[vertices[*v].automaton_state]
+                .acceptance_sets
+                .contains(&set)
+        })?;
+        accepting.push(vertex);
+    }
+
+    // Classify the constrained actions based on where they are enabled and whether the component
+    // can take them.
+    let enabled = |v: usize| {
+        graph[&vertices[v].state]
+            .edges
+            .iter()
+            .filter_map(|e| Some((e.action, e.fairness?)))
+    };
+    let mut enabled_everywhere: Option<BTreeMap<Fingerprint, FairnessKind>> = None;
+    let mut enabled_somewhere = BTreeMap::new();
+    let mut taken = BTreeMap::new();
+    for &v in component {
+        for edge in &vertices[v].edges {
+            if let (Some(action), Some(_)) = (edge.action, edge.fairness) {
+                if component.contains(&edge.dst) {
+                    taken.entry(action).or_insert((v, edge.dst));
+                }
+            }
+        }
+        let enabled: BTreeMap<_, _> = enabled(v).collect();
+        enabled_everywhere = Some(match enabled_everywhere {
+            None => enabled.clone(),
+            Some(mut everywhere) => {
+                everywhere.retain(|action, _| enabled.contains_key(action));
+                everywhere
+            }
+        });
+        enabled_somewhere.extend(enabled);
+    }
+    if enabled_everywhere
+        .unwrap_or_default()
+        .keys()
+        .any(|action| !taken.contains_key(action))
+    {
+        return None; // every cycle within the component violates weak fairness
+    }
+    let neglected: BTreeSet<Fingerprint> = enabled_somewhere
+        .iter()
+        .filter(|(action, kind)| **kind == FairnessKind::Strong && !taken.contains_key(action))
+        .map(|(action, _)| *action)
+        .collect();
+    if !neglected.is_empty() {
+        // A fair cycle must avoid the vertices that enable a neglected action.
+        let remaining: BTreeSet<usize> = component
+            .iter()
+            .copied()
+            .filter(|v| !enabled(*v).any(|(action, _)| neglected.contains(&action)))
+            .collect();
+        let roots: Vec<usize> = remaining.iter().copied().collect();
+        let mut subcomponents =
+            strongly_connected_components(vertices, &roots, |v| remaining.contains(&v));
+        subcomponents.sort_by_key(|c| c.first().copied());
+        return subcomponents
+            .into_iter()
+            .find_map(|c| find_fair_cycle(graph, vertices, automaton, &c));
+    }
+
+    // The cycle takes each constrained action that the component can take, visits a vertex
+    // disabling each weakly constrained action that the component cannot take, and visits each
+    // acceptance set.
+    let mut waypoints = Vec::new();
+    for (action, kind) in &enabled_somewhere {
+        if let Some((src, dst)) = taken.get(action) {
+            waypoints.push(*src);
+            waypoints.push(*dst);
+        } else if *kind == FairnessKind::Weak {
+            waypoints.push(
+                component
+                    .iter()
+                    .copied()
+                    .find(|v| !enabled(*v).any(|(a, _)| a == *action))
+                    .unwrap(),
+            );
+        }
+    }
+    waypoints.extend(accepting);
+    let mut cycle = vec![entry];
+    for waypoint in waypoints {
+        let current = *cycle.last().unwrap();
+        if waypoint != current {
+            cycle.extend(path_within(vertices, component, current, waypoint));
+        }
+    }
+    if cycle.len() == 1 {
+        let edge = vertices[entry]
+            .edges
+            .iter()
+            .find(|e| component.contains(&e.dst))
+            .unwrap();
+        cycle.push(edge.dst);
+    }
+    let current = *cycle.last().unwrap();
+    if current != entry {
+        cycle.extend(path_within(vertices, component, current, entry));
+    }
+    Some(cycle)
+}
+
+/// Returns the vertices following `src` along a shortest path to `dst` within a component.
+fn path_within(
+    vertices: &[Vertex],
+    component: &BTreeSet<usize>,
+    src: usize,
+    dst: usize,
+) -> Vec<usize> {
+    let mut parents = HashMap::new();
+    let mut pending = VecDeque::from([src]);
+    while let Some(v) = pending.pop_front() {
+        if v == dst {
+            let mut path = Vec::new();
+            let mut v = dst;
+            while v != src {
+                path.push(v);
+                v = parents[&v];
+            }
+            path.reverse();
+            return path;
+        }
+        for edge in &vertices[v].edges {
+            if component.contains(&edge.dst) && edge.dst != src && !parents.contains_key(&edge.dst)
+            {
+                parents.insert(edge.dst, v);
+                pending.push_back(edge.dst);
+            }
+        }
+    }
+    unreachable!("component is strongly connected")
+}
+
+/// Tarjan's algorithm, iteratively computing the strongly connected components of the subgraph
+/// induced by `contains`.
+fn strongly_connected_components(
+    vertices: &[Vertex],
+    roots: &[usize],
+    contains: impl Fn(usize) -> bool,
+) -> Vec<BTreeSet<usize>> {
+    #[derive(Default)]
+    struct Search {
+        indices: HashMap<usize, usize>,
+        lowlinks: HashMap<usize, usize>,
+        stack: Vec<usize>,
+        on_stack: BTreeSet<usize>,
+        calls: Vec<(usize, usize)>,
+    }
+    impl Search {
+        fn visit(&mut self, v: usize) {
+            let index = self.indices.len();
+            self.indices.insert(v, index);
+            self.lowlinks.insert(v, index);
+            self.stack.push(v);
+            self.on_stack.insert(v);
+            self.calls.push((v, 0));
+        }
+    }
+
+    let mut search = Search::default();
+    let mut components = Vec::new();
+    for &root in roots {
+        if search.indices.contains_key(&root) {
+            continue;
+        }
+        search.visit(root);
+        while let Some(&(v, edge_index)) = search.calls.last() {
+            let edges = &vertices[v].edges;
+            if edge_index < edges.len() {
+                search.calls.last_mut().unwrap().1 += 1;
+                let dst = edges[edge_index].dst;
+                if !contains(dst) {
+                    continue;
+                }
+                if !search.indices.contains_key(&dst) {
+                    search.visit(dst);
+                } else if search.on_stack.contains(&dst) {
+                    let lowlink = search.lowlinks[&v].min(search.indices[&dst]);
+                    search.lowlinks.insert(v, lowlink);
+                }
+                continue;
+            }
+            search.calls.pop();
+            if let Some(&(caller, _)) = search.calls.last() {
+                let lowlink = search.lowlinks[&caller].min(search.lowlinks[&v]);
+                search.lowlinks.insert(caller, lowlink);
+            }
+            if search.lowlinks[&v] == search.indices[&v] {
+                let mut component = BTreeSet::new();
+                while let Some(member) = search.stack.pop() {
+                    search.on_stack.remove(&member);
+                    component.insert(member);
+                    if member == v {
+                        break;
+                    }
+                }
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+impl<M> Checker<M> for LivenessChecker<M>
+where
+    M: Model,
+    M::State: Hash,
+{
+    fn model(&self) -> &M {
+        &self.model
+    }
+
+    fn state_count(&self) -> usize {
+        self.state_count.load(Ordering::Relaxed)
+    }
+
+    fn unique_state_count(&self) -> usize {
+        self.unique_state_count.load(Ordering::Relaxed)
+    }
+
+    fn max_depth(&self) -> usize {
+        self.max_depth.load(Ordering::Relaxed)
+    }
+
+    fn discoveries(&self) -> HashMap<&'static str, Path<M::State, M::Action>> {
+        self.discoveries
+            .iter()
+            .map(|mapref| {
+                (
+                    <&'static str>::clone(mapref.key()),
+                    Path::from_fingerprints(self.model(), VecDeque::from(mapref.value().clone())),
+                )
+            })
+            .collect()
+    }
+
+    fn handles(&mut self) -> Vec<JoinHandle<()>> {
+        std::mem::take(&mut self.handles)
+    }
+
+    fn is_done(&self) -> bool {
+        self.is_done.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::dgraph::DGraph;
+    use crate::*;
+
+    fn eventually_odd() -> Property<DGraph> {
+        Property::eventually("odd", |_, s| s % 2 == 1)
+    }
+
+    #[test]
+    fn can_validate() {
+        DGraph::with_property(eventually_odd())
+            .with_path(vec![1])
+            .with_path(vec![2, 3])
+            .with_path(vec![2, 6, 7])
+            .with_path(vec![4, 9, 10, 4]) // cycle satisfies the property
+            .checker()
+            .spawn_liveness()
+            .join()
+            .assert_properties();
+    }
+
+    #[test]
+    fn can_discover_counterexample_ending_in_terminal_state() {
+        let checker = DGraph::with_property(eventually_odd())
+            .with_path(vec![0, 2, 4])
+            .with_path(vec![1, 4, 6]) // revisiting 4
+            .checker()
+            .spawn_liveness()
+            .join();
+        assert_eq!(
+            checker.discovery("odd").unwrap().into_states(),
+            vec![0, 2, 4, 6]
+        );
+        checker.assert_discovery("odd", vec![2, 4, 6]);
+    }
+
+    #[test]
+    fn can_discover_counterexample_ending_in_cycle() {
+        let checker = DGraph::with_property(eventually_odd())
+            .with_path(vec![0, 2, 4, 2])
+            .checker()
+            .spawn_liveness()
+            .join();
+        assert_eq!(
+            checker.discovery("odd").unwrap().into_states(),
+            vec![0, 2, 4, 2]
+        );
+        checker.assert_discovery("odd", vec![2, 4, 2]);
+
+        let checker = DGraph::with_property(eventually_odd())
+            .with_path(vec![0, 0, 1]) // self-loop
+            .checker()
+            .spawn_liveness()
+            .join();
+        assert_eq!(checker.discovery("odd").unwrap().into_states(), vec![0, 0]);
+    }
+
+    #[test]
+    fn checks_safety_properties() {
+        let checker = DGraph::with_property(Property::always("small", |_, s| *s < 4))
+            .with_path(vec![0, 2, 4, 2])
+            .checker()
+            .spawn_liveness()
+            .join();
+        assert_eq!(
+            checker.discovery("small").unwrap().into_states(),
+            vec![0, 2, 4]
+        );
+        assert_eq!(checker.unique_state_count(), 3);
+    }
+
+    #[test]
+    fn checks_temporal_properties() {
+        fn is_odd() -> Formula<DGraph> {
+            Formula::predicate(|_, s| s % 2 == 1)
+        }
+        fn is_zero() -> Formula<DGraph> {
+            Formula::predicate(|_, s| *s == 0)
+        }
+        let check = |formula, path| {
+            DGraph::with_property(Property::temporal("formula", formula))
+                .with_path(path)
+                .checker()
+                .spawn_liveness()
+                .join()
+        };
+        let discovery = |formula, path| {
+            let checker = check(formula, path);
+            let path = checker.discovery("formula").map(|p| p.into_states());
+            if let Some(path) = &path {
+                checker.assert_discovery("formula", path[1..].to_vec());
+            }
+            path
+        };
+
+        // Next
+        assert_eq!(discovery(Formula::next(is_odd()), vec![0, 1]), None);
+        assert_eq!(
+            discovery(Formula::next(is_odd()), vec![0, 2]),
+            Some(vec![0, 2])
+        );
+        assert_eq!(discovery(Formula::next(is_zero()), vec![0]), None); // stutters
+
+        // Until
+        let even_until_odd = || Formula::until(Formula::not(is_odd()), is_odd());
+        assert_eq!(discovery(even_until_odd(), vec![0, 2, 3, 4]), None);
+        assert_eq!(
+            discovery(even_until_odd(), vec![0, 2, 4]),
+            Some(vec![0, 2, 4])
+        );
+        assert_eq!(
+            discovery(even_until_odd(), vec![0, 2, 0]),
+            Some(vec![0, 2, 0])
+        );
+
+        // Leads to
+        let zero_leads_to_odd = || Formula::leads_to(is_zero(), is_odd());
+        assert_eq!(discovery(zero_leads_to_odd(), vec![2, 0, 3, 0]), None);
+        assert_eq!(
+            discovery(zero_leads_to_odd(), vec![1, 0, 2, 2]),
+            Some(vec![1, 0, 2, 2])
+        );
+        assert_eq!(
+            discovery(zero_leads_to_odd(), vec![1, 3, 0, 2, 4, 2]),
+            Some(vec![1, 3, 0, 2, 4, 2])
+        );
+
+        // Infinitely often
+        let always_eventually_odd = || Formula::always(Formula::eventually(is_odd()));
+        assert_eq!(discovery(always_eventually_odd(), vec![1, 2, 1]), None);
+        assert_eq!(
+            discovery(always_eventually_odd(), vec![1, 2, 4, 2]),
+            Some(vec![1, 2, 4, 2, 4]) // the automaton needs another lap to reach acceptance
+        );
+    }
+
+    #[test]
+    fn bfs_and_dfs_check_temporal_properties() {
+        fn zero_leads_to_odd() -> Formula<DGraph> {
+            Formula::leads_to(
+                Formula::predicate(|_, s| *s == 0),
+                Formula::predicate(|_, s| s % 2 == 1),
+            )
+        }
+        let discoveries = |path: Vec<u8>| {
+            let model = DGraph::with_property(Property::temporal("formula", zero_leads_to_odd()))
+                .with_path(path);
+            [
+                model.clone().checker().spawn_bfs().join(),
+                model.clone().checker().threads(2).spawn_bfs().join(),
+            ]
+            .into_iter()
+            .map(|c| c.discovery("formula").map(|p| p.into_states()))
+            .chain(
+                [
+                    model.clone().checker().spawn_dfs().join(),
+                    model.checker().symmetry_fn(|s| *s).spawn_dfs().join(),
+                ]
+                .into_iter()
+                .map(|c| c.discovery("formula").map(|p| p.into_states())),
+            )
+            .collect::<Vec<_>>()
+        };
+
+        assert_eq!(discoveries(vec![2, 0, 3, 0]), vec![None; 4]);
+        assert_eq!(
+            discoveries(vec![1, 0, 2, 2]),
+            vec![Some(vec![1, 0, 2, 2]); 4]
+        );
+        assert_eq!(
+            discoveries(vec![1, 3, 0, 2, 4, 2]),
+            vec![Some(vec![1, 3, 0, 2, 4, 2]); 4]
+        );
+        assert_eq!(discoveries(vec![1, 3, 0]), vec![Some(vec![1, 3, 0]); 4]);
+    }
+
+    #[test]
+    #[should_panic(expected = "only checked by `spawn_bfs`, `spawn_dfs`, and `spawn_liveness`")]
+    fn other_checkers_reject_temporal_properties() {
+        let formula = Formula::eventually(Formula::predicate(|_, s| *s == 1));
+        DGraph::with_property(Property::temporal("formula", formula))
+            .with_path(vec![0, 1])
+            .checker()
+            .spawn_simulation(0, UniformChooser)
+            .join();
+    }
+
+    #[test]
+    fn on_demand_checker_skips_temporal_properties() {
+        let formula = Formula::eventually(Formula::predicate(|_, s| *s == 1));
+        let checker = DGraph::with_property(Property::temporal("formula", formula))
+            .with_path(vec![0, 2])
+            .checker()
+            .spawn_on_demand();
+        checker.run_to_completion();
+        while !checker.is_done() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert_eq!(checker.discovery("formula"), None);
+    }
+
+    /// A process that can spin in place, or can toggle between a state where it may finish and a
+    /// state where it may not.
+    #[derive(Clone)]
+    struct Spinner(Vec<Fairness<Spinner>>);
+    #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+    enum SpinnerAction {
+        Spin,
+        Toggle,
+        Finish,
+    }
+    impl Model for Spinner {
+        type State = (bool, bool); // (may finish, finished)
+        type Action = SpinnerAction;
+        fn init_states(&self) -> Vec<Self::State> {
+            vec![(false, false)]
+        }
+        fn actions(&self, state: &Self::State, actions: &mut Vec<Self::Action>) {
+            if !state.1 {
+                actions.push(SpinnerAction::Spin);
+                actions.push(SpinnerAction::Toggle);
+                if state.0 {
+                    actions.push(SpinnerAction::Finish);
+                }
+            }
+        }
+        fn next_state(&self, state: &Self::State, action: Self::Action) -> Option<Self::State> {
+            match action {
+                SpinnerAction::Spin => Some(*state),
+                SpinnerAction::Toggle => Some((!state.0, state.1)),
+                SpinnerAction::Finish => Some((state.0, true)),
+            }
+        }
+        fn properties(&self) -> Vec<Property<Self>> {
+            vec![Property::eventually("finishes", |_, s: &Self::State| s.1)]
+        }
+        fn fairness(&self) -> Vec<Fairness<Self>> {
+            self.0.clone()
+        }
+    }
+
+    #[test]
+    fn honors_fairness() {
+        use SpinnerAction::*;
+
+        // Spinning forever is a counterexample without fairness.
+        let checker = Spinner(vec![]).checker().spawn_liveness().join();
+        assert_eq!(
+            checker.discovery("finishes").unwrap().into_actions(),
+            vec![Spin]
+        );
+
+        // Fairness for spinning and toggling still allows a behavior that toggles forever, as
+        // finishing is only enabled intermittently.
+        let checker = Spinner(vec![
+            Fairness::weak(|_, a| *a != Finish),
+            Fairness::weak(|_, a| *a == Finish),
+        ])
+        .checker()
+        .spawn_liveness()
+        .join();
+        let path = checker.discovery("finishes").unwrap();
+        assert!(path.clone().into_actions().contains(&Toggle));
+        checker.assert_discovery("finishes", path.into_actions());
+
+        // Strong fairness rules that out.
+        Spinner(vec![
+            Fairness::weak(|_, a| *a != Finish),
+            Fairness::strong(|_, a| *a == Finish),
+        ])
+        .checker()
+        .spawn_liveness()
+        .join()
+        .assert_properties();
+    }
+}
@@ -1,6 +1,6 @@
 //! Private module for selective re-export.
 
-use crate::checker::{Checker, EventuallyBits, Expectation, Path};
+use crate::checker::{next_state_checking_transitions, Checker, EventuallyBits, Expectation, Path};
 use crate::job_market::JobBroker;
 use crate::{
     fingerprint, CheckerBuilder, CheckerVisitor, ControlFlow, Fingerprint, Model, Property,
@@ -30,10 +30,13 @@ pub(crate) struct OnDemandChecker<M: Model> {
     max_depth: Arc<AtomicUsize>,
     generated:
         Arc<DashMap<Fingerprint, Option<Fingerprint>, BuildHasherDefault<NoHashHasher<u64>>>>,
-    discoveries: Arc<DashMap<&'static str, Fingerprint>>,
+    discoveries: Arc<DashMap<&'static str, Discovery>>,
     control_flow: std::sync::mpsc::SyncSender<ControlFlow>,
 }
 type Job<State> = (State, Fingerprint, EventuallyBits, NonZeroUsize);
+/// The fingerprint of a discovered state, along with the fingerprint of a subsequent state if the
+/// discovery is a step (for a `transition` property).
+type Discovery = (Fingerprint, Option<Fingerprint>);
 
 impl<M> OnDemandChecker<M>
 where
@@ -248,13 +251,12 @@ where
             BuildHasherDefault<NoHashHasher<u64>>,
         >,
         pending: &mut VecDeque<Job<M::State>>,
-        discoveries: &DashMap<&'static str, Fingerprint>,
+        discoveries: &DashMap<&'static str, Discovery>,
         visitor: &Option<Box<dyn CheckerVisitor<M> + Send + Sync>>,
         max_count: usize,
         global_max_depth: &AtomicUsize,
     ) {
         let properties = model.properties();
-
         let mut current_max_depth = global_max_depth.load(Ordering::Relaxed);
         let mut actions = Vec::new();
         let mut local_pending = pending
@@ -295,7 +297,7 @@ where
                     } => {
                         if !always(model, &state) {
                             // Races other threads, but that's fine.
-                            discoveries.insert(property.name, state_fp);
+                            discoveries.insert(property.name, (state_fp, None));
                         } else {
                             is_awaiting_discoveries = true;
                         }
@@ -307,7 +309,7 @@ where
                     } => {
                         if sometimes(model, &state) {
                             // Races other threads, but that's fine.
-                            discoveries.insert(property.name, state_fp);
+                            discoveries.insert(property.name, (state_fp, None));
                         } else {
                             is_awaiting_discoveries = true;
                         }
@@ -327,6 +329,19 @@ where
                             ebits.remove(i);
                         }
                     }
+                    Property {
+                        expectation: Expectation::Temporal,
+                        ..
+                    } => {
+                        // Skipped, as exploration may never stop.
+                    }
+                    Property {
+                        expectation: Expectation::Transition,
+                        ..
+                    } => {
+                        // Checked below for each step.
+                        is_awaiting_discoveries = true;
+                    }
                 }
             }
             if !is_awaiting_discoveries {
@@ -336,8 +351,18 @@ where
             // Otherwise enqueue newly generated states (with related metadata).
             let mut is_terminal = true;
             model.actions(&state, &mut actions);
-            let next_states = actions.drain(..).flat_map(|a| model.next_state(&state, a));
-            for next_state in next_states {
+            for action in actions.drain(..) {
+                let is_discovered = |name| discoveries.contains_key(name);
+                let (next_state, violations) = match next_state_checking_transitions(
+                    model,
+                    &properties,
+                    &state,
+                    action,
+                    is_discovered,
+                ) {
+                    None => continue,
+                    Some(step) => step,
+                };
                 let next_fp = fingerprint(&next_state);
                 log::debug!(
                     "checker generated state transition: {} -> {}",
@@ -350,6 +375,11 @@ where
                 }
                 state_count.fetch_add(1, Ordering::Relaxed);
 
+                for name in violations {
+                    // Races other threads, but that's fine.
+                    discoveries.insert(name, (state_fp, Some(next_fp)));
+                }
+
                 // Skip if already generated.
                 //
                 // FIXME: we should really include ebits in the fingerprint here --
@@ -386,7 +416,7 @@ where
                 for (i, property) in properties.iter().enumerate() {
                     if ebits.contains(i) {
                         // Races other threads, but that's fine.
-                        discoveries.insert(property.name, state_fp);
+                        discoveries.insert(property.name, (state_fp, None));
                     }
                 }
             }
@@ -432,7 +462,14 @@ where
             .map(|mapref| {
                 (
                     <&'static str>::clone(mapref.key()),
-                    reconstruct_path(self.model(), &self.generated, *mapref.value()),
+                    match *mapref.value() {
+                        (fp, None) => reconstruct_path(self.model(), &self.generated, fp),
+                        (fp, Some(next_fp)) => {
+                            let mut fingerprints = reconstruct_fingerprints(&self.generated, fp);
+                            fingerprints.push_back(next_fp);
+                            Path::from_fingerprints(self.model(), fingerprints)
+                        }
+                    },
                 )
             })
             .collect()
@@ -456,6 +493,13 @@ where
     M: Model,
     M::State: Hash,
 {
+    Path::from_fingerprints(model, reconstruct_fingerprints(generated, fp))
+}
+
+fn reconstruct_fingerprints(
+    generated: &DashMap<Fingerprint, Option<Fingerprint>, BuildHasherDefault<NoHashHasher<u64>>>,
+    fp: Fingerprint,
+) -> VecDeque<Fingerprint> {
     // First build a stack of digests representing the path (with the init digest at top of
     // stack). Then unwind the stack of digests into a vector of states. The TLC model checker
     // uses a similar technique, which is documented in the paper "Model Checking TLA+
@@ -475,7 +519,7 @@ where
             }
         }
     }
-    Path::from_fingerprints(model, fingerprints)
+    fingerprints
 }
 
 #[cfg(test)]
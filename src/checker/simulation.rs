@@ -1,6 +1,6 @@
 //! Private module for selective re-export.
 
-use crate::checker::{Checker, Expectation, Path};
+use crate::checker::{next_state_checking_transitions, Checker, Expectation, Path};
 use crate::{fingerprint, CheckerBuilder, CheckerVisitor, Fingerprint, Model, Property};
 use dashmap::DashMap;
 use rand::rngs::StdRng;
@@ -348,6 +348,19 @@ where
                             ebits.remove(i);
                         }
                     }
+                    Property {
+                        expectation: Expectation::Temporal,
+                        ..
+                    } => {
+                        // Rejected when spawned, as this checker lacks the state graph.
+                    }
+                    Property {
+                        expectation: Expectation::Transition,
+                        ..
+                    } => {
+                        // Checked below for the chosen step.
+                        is_awaiting_discoveries = true;
+                    }
                 }
             }
             if !is_awaiting_discoveries {
@@ -373,12 +386,25 @@ where
                 let action = actions.swap_remove(index);
 
                 // take the chosen action
-                match model.next_state(&state, action) {
+                let is_discovered = |name| discoveries.contains_key(name);
+                match next_state_checking_transitions(
+                    model,
+                    &properties,
+                    &state,
+                    action,
+                    is_discovered,
+                ) {
                     None => {
                         // this action was ignored, try and choose another
                         log::trace!("No next state");
                     }
-                    Some(next_state) => {
+                    Some((next_state, violations)) => {
+                        for name in violations {
+                            let mut fingerprints = fingerprint_path.clone();
+                            fingerprints.push(fingerprint(&next_state));
+                            // Races other threads, but that's fine.
+                            discoveries.insert(name, fingerprints);
+                        }
                         // now clear the actions for the next round
                         actions.clear();
                         state = next_state;
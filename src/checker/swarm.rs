@@ -0,0 +1,402 @@
+//! Private module for selective re-export.
+
+use crate::checker::{
+    next_state_checking_transitions, Checker, Coverage, EventuallyBits, Expectation, Path,
+    VisitedStore,
+};
+use crate::{fingerprint, CheckerBuilder, Fingerprint, Model, Property};
+use dashmap::DashMap;
+use nohash_hasher::NoHashHasher;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{BuildHasherDefault, Hash};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+/// Runs many small depth-first searches ("members" of a swarm) rather than one exhaustive search,
+/// as described in "Swarm Verification Techniques" by Holzmann, Joshi, and Groce. Each member
+/// tracks the states it visits separately and is diversified by its own seed, which determines
+/// the order in which it explores actions and its depth bound, so the members tend to explore
+/// different parts of a state space that is too large to check exhaustively.
+pub(crate) struct SwarmChecker<M: Model> {
+    model: Arc<M>,
+    handles: Vec<JoinHandle<()>>,
+    state_count: Arc<AtomicUsize>,
+    max_depth: Arc<AtomicUsize>,
+    /// The states visited by any member, for reporting coverage.
+    generated: Arc<dyn VisitedStore>,
+    discoveries: Arc<DashMap<&'static str, Vec<Fingerprint>>>,
+}
+
+/// The parameters that distinguish a member of the swarm.
+struct Member {
+    index: usize,
+    rng: StdRng,
+    max_depth: Option<usize>,
+    max_state_count: Option<usize>,
+}
+
+impl<M> SwarmChecker<M>
+where
+    M: Model + Send + Sync + 'static,
+    M::State: Hash + Send + 'static,
+{
+    pub(crate) fn spawn(options: CheckerBuilder<M>, member_count: usize) -> Self {
+        let model = Arc::new(options.model);
+        let symmetry = options.symmetry;
+        let finish_when = Arc::new(options.finish_when);
+        let target_max_depth = options.target_max_depth;
+        // The target state count is a budget for the whole swarm.
+        let member_state_count = options
+            .target_state_count
+            .map(|n| (n.get() / member_count.max(1)).max(1));
+        let close_at = options.timeout.map(|t| Instant::now() + t);
+
+        let state_count = Arc::new(AtomicUsize::new(0));
+        let max_depth = Arc::new(AtomicUsize::new(0));
+        let generated: Arc<dyn VisitedStore> = options.visited_store.into();
+        let discoveries = Arc::new(DashMap::default());
+        let next_member = Arc::new(AtomicUsize::new(0));
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let mut handles = Vec::new();
+        for t in 0..options.thread_count {
+            let model = Arc::clone(&model);
+            let finish_when = Arc::clone(&finish_when);
+            let state_count = Arc::clone(&state_count);
+            let max_depth = Arc::clone(&max_depth);
+            let generated = Arc::clone(&generated);
+            let discoveries = Arc::clone(&discoveries);
+            let next_member = Arc::clone(&next_member);
+            let shutdown = Arc::clone(&shutdown);
+            handles.push(
+                std::thread::Builder::new()
+                    .name(format!("checker-{}", t))
+                    .spawn(move || {
+                        let properties = model.properties();
+                        loop {
+                            let index = next_member.fetch_add(1, Ordering::Relaxed);
+                            if index >= member_count || shutdown.load(Ordering::Relaxed) {
+                                log::debug!("{}: No more members. Shutting down...", t);
+                                return;
+                            }
+                            let mut rng = StdRng::seed_from_u64(index as u64);
+                            let member = Member {
+                                index,
+                                // Shallower members reach fewer states but finish sooner, so the
+                                // depth bounds vary between half of the target and the target.
+                                max_depth: target_max_depth.map(|d| {
+                                    let d = d.get();
+                                    rng.gen_range(d.div_ceil(2)..=d)
+                                }),
+                                max_state_count: member_state_count,
+                                rng,
+                            };
+                            log::debug!(
+                                "{}: Starting member {} with max_depth={:?}.",
+                                t,
+                                index,
+                                member.max_depth
+                            );
+                            Self::check_member(
+                                &model,
+                                member,
+                                symmetry,
+                                &state_count,
+                                &max_depth,
+                                &*generated,
+                                &discoveries,
+                                &shutdown,
+                                close_at,
+                            );
+
+                            if finish_when.matches(
+                                &discoveries.iter().map(|r| *r.key()).collect(),
+                                &properties,
+                            ) {
+                                log::debug!("{}: Discovery complete. Shutting down...", t);
+                                shutdown.store(true, Ordering::Relaxed);
+                                return;
+                            }
+                        }
+                    })
+                    .expect("Failed to spawn a thread"),
+            );
+        }
+        SwarmChecker {
+            model,
+            handles,
+            state_count,
+            max_depth,
+            generated,
+            discoveries,
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[allow(clippy::type_complexity)]
+    fn check_member(
+        model: &M,
+        mut member: Member,
+        symmetry: Option<fn(&M::State) -> M::State>,
+        state_count: &AtomicUsize,
+        global_max_depth: &AtomicUsize,
+        generated: &dyn VisitedStore,
+        discoveries: &DashMap<&'static str, Vec<Fingerprint>>,
+        shutdown: &AtomicBool,
+        close_at: Option<Instant>,
+    ) {
+        let properties = model.properties();
+        let ebits = {
+            let mut ebits = EventuallyBits::new();
+            for (i, p) in properties.iter().enumerate() {
+                if p.expectation == Expectation::Eventually {
+                    ebits.insert(i);
+                }
+            }
+            ebits
+        };
+        let representative_fingerprint = |s: &M::State| match symmetry {
+            Some(representative) => fingerprint(&representative(s)),
+            None => fingerprint(s),
+        };
+
+        let mut visited: HashSet<Fingerprint, BuildHasherDefault<NoHashHasher<u64>>> =
+            HashSet::default();
+        let mut pending = Vec::new();
+        let mut init_states = model.init_states();
+        init_states.shuffle(&mut member.rng);
+        for s in init_states {
+            let representative_fp = representative_fingerprint(&s);
+            if model.within_boundary(&s) && visited.insert(representative_fp) {
+                state_count.fetch_add(1, Ordering::Relaxed);
+                generated.insert(representative_fp, None);
+                let fp = fingerprint(&s);
+                pending.push((s, vec![fp], ebits.clone()));
+            }
+        }
+
+        let mut member_state_count = pending.len();
+        let mut actions = Vec::new();
+        while let Some((state, fingerprints, mut ebits)) = pending.pop() {
+            if shutdown.load(Ordering::Relaxed)
+                || matches!(close_at, Some(t) if t <= Instant::now())
+            {
+                shutdown.store(true, Ordering::Relaxed);
+                return;
+            }
+            if matches!(member.max_state_count, Some(n) if n <= member_state_count) {
+                log::trace!("Member {} reached its state budget.", member.index);
+                return;
+            }
+
+            let depth = fingerprints.len();
+            global_max_depth.fetch_max(depth, Ordering::Relaxed);
+            if matches!(member.max_depth, Some(d) if d <= depth) {
+                continue;
+            }
+
+            let mut is_awaiting_discoveries = false;
+            for (i, property) in properties.iter().enumerate() {
+                if discoveries.contains_key(property.name) {
+                    continue;
+                }
+                match property {
+                    Property {
+                        expectation: Expectation::Always,
+                        condition: always,
+                        ..
+                    } => {
+                        if !always(model, &state) {
+                            // Races other threads, but that's fine.
+                            discoveries.insert(property.name, fingerprints.clone());
+                        } else {
+                            is_awaiting_discoveries = true;
+                        }
+                    }
+                    Property {
+                        expectation: Expectation::Sometimes,
+                        condition: sometimes,
+                        ..
+                    } => {
+                        if sometimes(model, &state) {
+                            // Races other threads, but that's fine.
+                            discoveries.insert(property.name, fingerprints.clone());
+                        } else {
+                            is_awaiting_discoveries = true;
+                        }
+                    }
+                    Property {
+                        expectation: Expectation::Eventually,
+                        condition: eventually,
+                        ..
+                    } => {
+                        is_awaiting_discoveries = true;
+                        if eventually(model, &state) {
+                            ebits.remove(i);
+                        }
+                    }
+                    Property {
+                        expectation: Expectation::Temporal,
+                        ..
+                    } => {
+                        // Rejected when spawned, as this checker lacks the state graph.
+                    }
+                    Property {
+                        expectation: Expectation::Transition,
+                        ..
+                    } => {
+                        // Checked below for each step.
+                        is_awaiting_discoveries = true;
+                    }
+                }
+            }
+            if !is_awaiting_discoveries {
+                return;
+            }
+
+            // Otherwise push newly generated states in this member's order.
+            let mut is_terminal = true;
+            model.actions(&state, &mut actions);
+            actions.shuffle(&mut member.rng);
+            for action in actions.drain(..) {
+                let is_discovered = |name| discoveries.contains_key(name);
+                let (next_state, violations) = match next_state_checking_transitions(
+                    model,
+                    &properties,
+                    &state,
+                    action,
+                    is_discovered,
+                ) {
+                    None => continue,
+                    Some(step) => step,
+                };
+
+                // Skip if outside boundary.
+                if !model.within_boundary(&next_state) {
+                    continue;
+                }
+                is_terminal = false;
+                member_state_count += 1;
+                state_count.fetch_add(1, Ordering::Relaxed);
+                let next_fingerprint = fingerprint(&next_state);
+
+                for name in violations {
+                    let mut fingerprints = fingerprints.clone();
+                    fingerprints.push(next_fingerprint);
+                    // Races other threads, but that's fine.
+                    discoveries.insert(name, fingerprints);
+                }
+
+                // Skip if already visited by this member.
+                let representative_fp = representative_fingerprint(&next_state);
+                if !visited.insert(representative_fp) {
+                    continue;
+                }
+                generated.insert(representative_fp, fingerprints.last().copied());
+                let mut next_fingerprints = fingerprints.clone();
+                next_fingerprints.push(next_fingerprint);
+                pending.push((next_state, next_fingerprints, ebits.clone()));
+            }
+            if is_terminal {
+                for (i, property) in properties.iter().enumerate() {
+                    if ebits.contains(i) {
+                        // Races other threads, but that's fine.
+                        discoveries.insert(property.name, fingerprints.clone());
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<M> Checker<M> for SwarmChecker<M>
+where
+    M: Model,
+    M::State: Hash,
+{
+    fn model(&self) -> &M {
+        &self.model
+    }
+
+    fn state_count(&self) -> usize {
+        self.state_count.load(Ordering::Relaxed)
+    }
+
+    fn unique_state_count(&self) -> usize {
+        self.generated.len()
+    }
+
+    fn max_depth(&self) -> usize {
+        self.max_depth.load(Ordering::Relaxed)
+    }
+
+    fn coverage(&self) -> Coverage {
+        self.generated.coverage()
+    }
+
+    fn discoveries(&self) -> HashMap<&'static str, Path<M::State, M::Action>> {
+        self.discoveries
+            .iter()
+            .map(|mapref| {
+                (
+                    <&'static str>::clone(mapref.key()),
+                    Path::from_fingerprints(self.model(), VecDeque::from(mapref.value().clone())),
+                )
+            })
+            .collect()
+    }
+
+    fn handles(&mut self) -> Vec<JoinHandle<()>> {
+        std::mem::take(&mut self.handles)
+    }
+
+    fn is_done(&self) -> bool {
+        self.handles.iter().all(|h| h.is_finished())
+            || self.discoveries.len() == self.model.properties().len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test_util::linear_equation_solver::*;
+    use crate::*;
+
+    #[test]
+    fn can_complete_by_eliminating_properties() {
+        let checker = LinearEquation { a: 2, b: 10, c: 14 }
+            .checker()
+            .target_max_depth(30)
+            .spawn_swarm(8)
+            .join();
+        checker.assert_properties();
+        let (x, y) = *checker.discovery("solvable").unwrap().last_state();
+        assert_eq!((2 * x as u32 + 10 * y as u32) % 256, 14);
+    }
+
+    #[test]
+    fn members_explore_different_states() {
+        // Each member generates at most 500 states, so the members must have diverged.
+        let checker = LinearEquation { a: 2, b: 4, c: 7 }
+            .checker()
+            .target_state_count(4 * 500)
+            .spawn_swarm(4)
+            .join();
+        checker.assert_no_discovery("solvable");
+        assert!(checker.unique_state_count() > 500);
+        assert!(checker.state_count() <= 4 * 501);
+    }
+
+    #[test]
+    #[should_panic(expected = "requires a `target_max_depth` or `target_state_count`")]
+    fn requires_a_bound() {
+        LinearEquation { a: 2, b: 10, c: 14 }
+            .checker()
+            .spawn_swarm(8)
+            .join();
+    }
+}
@@ -0,0 +1,551 @@
+//! Private module for selective re-export.
+
+use crate::Fingerprint;
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use nohash_hasher::NoHashHasher;
+use parking_lot::{Mutex, RwLock};
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::hash::BuildHasherDefault;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// Tracks the states that a [`Checker`] has visited, as identified by their [`Fingerprint`]s.
+/// Specified via [`CheckerBuilder::visited_store`]. Defaults to an [`InMemoryStore`].
+///
+/// [`Checker`]: crate::Checker
+/// [`CheckerBuilder::visited_store`]: crate::CheckerBuilder::visited_store
+pub trait VisitedStore: Send + Sync {
+    /// Records a fingerprint along with the fingerprint of the state from which it was generated
+    /// (`None` for an initial state). Returns `false` if the fingerprint was already recorded.
+    fn insert(&self, fingerprint: Fingerprint, source: Option<Fingerprint>) -> bool;
+
+    /// Looks up the source of a recorded fingerprint (which is `None` for an initial state).
+    /// Returns `None` if the fingerprint was not recorded or if the store does not retain sources.
+    fn source(&self, fingerprint: Fingerprint) -> Option<Option<Fingerprint>>;
+
+    /// Indicates how many fingerprints have been recorded.
+    fn len(&self) -> usize;
+
+    /// Indicates whether no fingerprints have been recorded.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Indicates whether the store retains sources, which [`CheckerBuilder::spawn_bfs`] needs
+    /// for reconstructing paths.
+    ///
+    /// [`CheckerBuilder::spawn_bfs`]: crate::CheckerBuilder::spawn_bfs
+    fn retains_sources(&self) -> bool {
+        true
+    }
+
+    /// Calls a function with every recorded fingerprint and its source. Returns `false` if the
+    /// store is unable to enumerate its fingerprints, in which case checkpoints are unavailable.
+    fn for_each(&self, f: &mut dyn FnMut(Fingerprint, Option<Fingerprint>)) -> bool;
+
+    /// Removes all recorded fingerprints.
+    fn clear(&self);
+
+    /// Indicates the guarantee that the store provides regarding visited states.
+    fn coverage(&self) -> Coverage {
+        Coverage::Exhaustive
+    }
+}
+
+/// The guarantee that a [`Checker`] provides regarding the states that it has checked. See
+/// [`VisitedStore::coverage`].
+///
+/// [`Checker`]: crate::Checker
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Coverage {
+    /// Every generated state was checked unless its fingerprint collided with that of another
+    /// state, which is vanishingly unlikely.
+    Exhaustive,
+    /// Generated states may have been skipped because they were mistaken for states that were
+    /// already visited.
+    Probabilistic {
+        /// The estimated probability that the next newly generated state would be skipped.
+        false_positive_rate: f64,
+    },
+}
+
+impl Display for Coverage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Coverage::Exhaustive => write!(f, "exhaustive"),
+            Coverage::Probabilistic {
+                false_positive_rate,
+            } => write!(
+                f,
+                "probabilistic (false positive rate {:.1e})",
+                false_positive_rate
+            ),
+        }
+    }
+}
+
+/// A [`VisitedStore`] that keeps every fingerprint in memory. This is the default.
+#[derive(Default)]
+pub struct InMemoryStore(
+    DashMap<Fingerprint, Option<Fingerprint>, BuildHasherDefault<NoHashHasher<u64>>>,
+);
+
+impl InMemoryStore {
+    /// Instantiates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl VisitedStore for InMemoryStore {
+    fn insert(&self, fingerprint: Fingerprint, source: Option<Fingerprint>) -> bool {
+        if let Entry::Vacant(entry) = self.0.entry(fingerprint) {
+            entry.insert(source);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn source(&self, fingerprint: Fingerprint) -> Option<Option<Fingerprint>> {
+        self.0.get(&fingerprint).map(|r| *r.value())
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn for_each(&self, f: &mut dyn FnMut(Fingerprint, Option<Fingerprint>)) -> bool {
+        for r in self.0.iter() {
+            f(*r.key(), *r.value());
+        }
+        true
+    }
+
+    fn clear(&self) {
+        self.0.clear();
+    }
+}
+
+/// A [`VisitedStore`] that bounds memory usage by spilling fingerprints to files on disk.
+///
+/// Fingerprints are kept in memory until reaching a limit, at which point they are sorted and
+/// written to a new file in a specified directory. Only a sparse index and a Bloom filter of
+/// roughly 10 bits per fingerprint are retained for each file, so most lookups of fingerprints
+/// that were never recorded avoid reading from disk. Files are deleted when the store is dropped.
+///
+/// # Example
+///
+/// ```no_run
+/// # use stateright::*; let model = ();
+/// let store = DiskStore::new("/tmp/visited", 10_000_000).unwrap();
+/// model.checker().visited_store(store).spawn_bfs().join();
+/// ```
+pub struct DiskStore {
+    dir: PathBuf,
+    /// Distinguishes the files of stores that share a directory.
+    id: usize,
+    memory_limit: usize,
+    memory: DashMap<Fingerprint, Option<Fingerprint>, BuildHasherDefault<NoHashHasher<u64>>>,
+    runs: RwLock<Vec<Run>>,
+    len: AtomicUsize,
+}
+
+impl DiskStore {
+    /// Instantiates an empty store that writes files to a directory (which is created if
+    /// necessary) once more than `memory_limit` fingerprints are held in memory.
+    pub fn new(dir: impl Into<PathBuf>, memory_limit: usize) -> std::io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        Ok(DiskStore {
+            dir,
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            memory_limit: memory_limit.max(1),
+            memory: Default::default(),
+            runs: Default::default(),
+            len: AtomicUsize::new(0),
+        })
+    }
+
+    /// Writes the fingerprints held in memory to a new file.
+    fn spill(&self) {
+        let mut runs = self.runs.write();
+        if self.memory.len() < self.memory_limit {
+            return; // Another thread spilled first.
+        }
+        let mut records: Vec<_> = self
+            .memory
+            .iter()
+            .map(|r| (r.key().get(), r.value().map_or(0, |fp| fp.get())))
+            .collect();
+        records.sort_unstable();
+        let path = self.dir.join(format!(
+            "visited-{}-{}-{}.bin",
+            std::process::id(),
+            self.id,
+            runs.len()
+        ));
+        let run = Run::write(path, &records).unwrap_or_else(|err| {
+            panic!(
+                "Unable to write fingerprints to {}: {}",
+                self.dir.display(),
+                err
+            )
+        });
+        log::debug!(
+            "Wrote {} fingerprints to {}.",
+            records.len(),
+            run.path.display()
+        );
+        runs.push(run);
+        self.memory.clear();
+    }
+}
+
+impl VisitedStore for DiskStore {
+    fn insert(&self, fingerprint: Fingerprint, source: Option<Fingerprint>) -> bool {
+        let is_new = {
+            let runs = self.runs.read();
+            if runs.iter().any(|run| run.get(fingerprint).is_some()) {
+                return false;
+            }
+            if let Entry::Vacant(entry) = self.memory.entry(fingerprint) {
+                entry.insert(source);
+                true
+            } else {
+                false
+            }
+        };
+        if is_new {
+            self.len.fetch_add(1, Ordering::Relaxed);
+            if self.memory.len() >= self.memory_limit {
+                self.spill();
+            }
+        }
+        is_new
+    }
+
+    fn source(&self, fingerprint: Fingerprint) -> Option<Option<Fingerprint>> {
+        let runs = self.runs.read();
+        if let Some(source) = self.memory.get(&fingerprint) {
+            return Some(*source);
+        }
+        runs.iter().rev().find_map(|run| run.get(fingerprint))
+    }
+
+    fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    fn for_each(&self, f: &mut dyn FnMut(Fingerprint, Option<Fingerprint>)) -> bool {
+        let runs = self.runs.read();
+        for r in self.memory.iter() {
+            f(*r.key(), *r.value());
+        }
+        for run in runs.iter() {
+            if let Err(err) = run.for_each(f) {
+                log::error!("Unable to read {}: {}", run.path.display(), err);
+                return false;
+            }
+        }
+        true
+    }
+
+    fn clear(&self) {
+        let mut runs = self.runs.write();
+        self.memory.clear();
+        runs.clear();
+        self.len.store(0, Ordering::Relaxed);
+    }
+}
+
+/// A sorted file of fingerprints and their sources.
+struct Run {
+    path: PathBuf,
+    file: Mutex<File>,
+    len: usize,
+    /// The first fingerprint of each block of records.
+    index: Vec<u64>,
+    filter: Bits,
+}
+
+impl Run {
+    /// Each record is a fingerprint followed by its source, where `0` indicates no source.
+    const RECORD_SIZE: usize = 16;
+    const BLOCK_LEN: usize = 256;
+
+    fn write(path: PathBuf, records: &[(u64, u64)]) -> std::io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(&path)?);
+        let mut index = Vec::with_capacity(records.len() / Self::BLOCK_LEN + 1);
+        let filter = Bits::new(10 * records.len(), 7);
+        for (i, (fp, source)) in records.iter().enumerate() {
+            if i % Self::BLOCK_LEN == 0 {
+                index.push(*fp);
+            }
+            filter.insert(*fp);
+            writer.write_all(&fp.to_le_bytes())?;
+            writer.write_all(&source.to_le_bytes())?;
+        }
+        writer.flush()?;
+        Ok(Run {
+            file: Mutex::new(File::open(&path)?),
+            path,
+            len: records.len(),
+            index,
+            filter,
+        })
+    }
+
+    fn get(&self, fingerprint: Fingerprint) -> Option<Option<Fingerprint>> {
+        let fp = fingerprint.get();
+        if !self.filter.contains(fp) {
+            return None;
+        }
+        let block = self
+            .index
+            .partition_point(|first| *first <= fp)
+            .checked_sub(1)?;
+        let start = block * Self::BLOCK_LEN;
+        let count = Self::BLOCK_LEN.min(self.len - start);
+        let mut bytes = vec![0; count * Self::RECORD_SIZE];
+        let read = {
+            let mut file = self.file.lock();
+            file.seek(SeekFrom::Start((start * Self::RECORD_SIZE) as u64))
+                .and_then(|_| file.read_exact(&mut bytes))
+        };
+        if let Err(err) = read {
+            panic!("Unable to read {}: {}", self.path.display(), err);
+        }
+        let records: Vec<_> = bytes.chunks_exact(Self::RECORD_SIZE).map(decode).collect();
+        let i = records.binary_search_by_key(&fp, |(fp, _)| *fp).ok()?;
+        Some(Fingerprint::new(records[i].1))
+    }
+
+    fn for_each(&self, f: &mut dyn FnMut(Fingerprint, Option<Fingerprint>)) -> std::io::Result<()> {
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        let mut bytes = [0; Self::RECORD_SIZE];
+        for _ in 0..self.len {
+            reader.read_exact(&mut bytes)?;
+            let (fp, source) = decode(&bytes);
+            if let Some(fp) = Fingerprint::new(fp) {
+                f(fp, Fingerprint::new(source));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn decode(record: &[u8]) -> (u64, u64) {
+    let (fp, source) = record.split_at(8);
+    (
+        u64::from_le_bytes(fp.try_into().unwrap()),
+        u64::from_le_bytes(source.try_into().unwrap()),
+    )
+}
+
+/// A [`VisitedStore`] that records each fingerprint by setting a few bits in a fixed-size bit
+/// array, as with the "bitstate hashing" (Bloom filter) mode of the SPIN model checker.
+///
+/// Memory usage is fixed regardless of the size of the state space, but a state is occasionally
+/// mistaken for a visited one (and then not checked) because its bits happen to be set already,
+/// so the resulting [`Coverage`] is probabilistic. Sources are not retained, so this store is only
+/// compatible with [`CheckerBuilder::spawn_dfs`], which tracks paths separately.
+///
+/// # Example
+///
+/// ```no_run
+/// # use stateright::*; let model = ();
+/// // 1 GiB of bits, 3 bits per state.
+/// let store = BitstateStore::new(1 << 30, 3);
+/// model.checker().visited_store(store).spawn_dfs().join();
+/// ```
+///
+/// [`CheckerBuilder::spawn_dfs`]: crate::CheckerBuilder::spawn_dfs
+pub struct BitstateStore {
+    bits: Bits,
+    len: AtomicUsize,
+}
+
+impl BitstateStore {
+    /// Instantiates an empty store with the specified number of bytes, setting `hash_count` bits
+    /// for each fingerprint.
+    pub fn new(byte_count: usize, hash_count: u32) -> Self {
+        BitstateStore {
+            bits: Bits::new(8 * byte_count, hash_count.max(1)),
+            len: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl VisitedStore for BitstateStore {
+    fn insert(&self, fingerprint: Fingerprint, _source: Option<Fingerprint>) -> bool {
+        let is_new = self.bits.insert(fingerprint.get());
+        if is_new {
+            self.len.fetch_add(1, Ordering::Relaxed);
+        }
+        is_new
+    }
+
+    fn source(&self, _fingerprint: Fingerprint) -> Option<Option<Fingerprint>> {
+        None
+    }
+
+    fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    fn retains_sources(&self) -> bool {
+        false
+    }
+
+    fn for_each(&self, _f: &mut dyn FnMut(Fingerprint, Option<Fingerprint>)) -> bool {
+        false
+    }
+
+    fn clear(&self) {
+        self.bits.clear();
+        self.len.store(0, Ordering::Relaxed);
+    }
+
+    fn coverage(&self) -> Coverage {
+        // The standard estimate for a Bloom filter with `k` hashes, `m` bits, and `n` entries.
+        let k = self.bits.hash_count as f64;
+        let m = self.bits.bit_count() as f64;
+        let n = self.len() as f64;
+        Coverage::Probabilistic {
+            false_positive_rate: (1.0 - (-k * n / m).exp()).powf(k),
+        }
+    }
+}
+
+/// A fixed-size array of bits, several of which are set for each fingerprint.
+struct Bits {
+    words: Vec<AtomicU64>,
+    hash_count: u32,
+}
+
+impl Bits {
+    fn new(bit_count: usize, hash_count: u32) -> Self {
+        Bits {
+            words: (0..bit_count.div_ceil(64).max(1))
+                .map(|_| AtomicU64::new(0))
+                .collect(),
+            hash_count,
+        }
+    }
+
+    fn bit_count(&self) -> u64 {
+        64 * self.words.len() as u64
+    }
+
+    /// Derives the bit positions for a fingerprint via double hashing, with the second hash
+    /// obtained by remixing the fingerprint.
+    fn positions(&self, fp: u64) -> impl Iterator<Item = (usize, u64)> {
+        let bit_count = self.bit_count();
+        let step = fp.rotate_left(32).wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1;
+        (0..self.hash_count as u64).map(move |i| {
+            let position = fp.wrapping_add(i.wrapping_mul(step)) % bit_count;
+            ((position / 64) as usize, 1 << (position % 64))
+        })
+    }
+
+    /// Sets the bits for a fingerprint, returning whether any of them was previously unset.
+    fn insert(&self, fp: u64) -> bool {
+        let mut is_new = false;
+        for (word, mask) in self.positions(fp) {
+            if self.words[word].fetch_or(mask, Ordering::Relaxed) & mask == 0 {
+                is_new = true;
+            }
+        }
+        is_new
+    }
+
+    fn contains(&self, fp: u64) -> bool {
+        self.positions(fp)
+            .all(|(word, mask)| self.words[word].load(Ordering::Relaxed) & mask != 0)
+    }
+
+    fn clear(&self) {
+        for word in &self.words {
+            word.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn fp(n: u64) -> Fingerprint {
+        crate::fingerprint(&n)
+    }
+
+    fn check_store(store: &dyn VisitedStore) {
+        assert!(store.insert(fp(0), None));
+        for n in 1..1_000 {
+            assert!(store.insert(fp(n), Some(fp(n - 1))));
+        }
+        for n in 0..1_000 {
+            assert!(!store.insert(fp(n), None));
+        }
+        assert_eq!(store.len(), 1_000);
+        assert_eq!(store.source(fp(0)), Some(None));
+        assert_eq!(store.source(fp(500)), Some(Some(fp(499))));
+        assert_eq!(store.source(fp(1_000)), None);
+
+        let mut count = 0;
+        assert!(store.for_each(&mut |fp, _| {
+            assert!(store.source(fp).is_some());
+            count += 1;
+        }));
+        assert_eq!(count, 1_000);
+
+        store.clear();
+        assert!(store.is_empty());
+        assert!(store.insert(fp(0), None));
+    }
+
+    #[test]
+    fn in_memory_store_records_fingerprints() {
+        check_store(&InMemoryStore::new());
+    }
+
+    #[test]
+    fn disk_store_records_fingerprints() {
+        let dir = std::env::temp_dir().join(format!("stateright-visited-{}", std::process::id()));
+        let store = DiskStore::new(&dir, 300).unwrap();
+        check_store(&store);
+        drop(store);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        std::fs::remove_dir(dir).unwrap();
+    }
+
+    #[test]
+    fn bitstate_store_records_fingerprints_probabilistically() {
+        let store = BitstateStore::new(1_024, 3);
+        for n in 0..100 {
+            store.insert(fp(n), None);
+        }
+        for n in 0..100 {
+            assert!(!store.insert(fp(n), None));
+        }
+        assert!(store.len() <= 100);
+        assert_eq!(store.source(fp(0)), None);
+        match store.coverage() {
+            Coverage::Probabilistic {
+                false_positive_rate,
+            } => assert!(0.0 < false_positive_rate && false_positive_rate < 0.01),
+            Coverage::Exhaustive => panic!("expected probabilistic coverage"),
+        }
+    }
+}
@@ -0,0 +1,531 @@
+//! Private module for selective re-export.
+
+use crate::Model;
+use std::collections::BTreeSet;
+
+/// A [linear temporal logic](https://en.wikipedia.org/wiki/Linear_temporal_logic) formula over
+/// the states of a behavior path, which is built from the same state predicates that other
+/// [`Property`](crate::Property) types use. See [`Property::temporal`](crate::Property::temporal).
+///
+/// A path ending in a terminal state is treated as though that final state repeats forever, so
+/// for instance [`Formula::next`] holds in the terminal state if the formula holds in that state.
+///
+/// ```
+/// # use stateright::*;
+/// # struct Counter;
+/// # impl Model for Counter {
+/// #     type State = u8;
+/// #     type Action = ();
+/// #     fn init_states(&self) -> Vec<Self::State> { vec![0] }
+/// #     fn actions(&self, _: &Self::State, actions: &mut Vec<Self::Action>) { actions.push(()) }
+/// #     fn next_state(&self, s: &Self::State, _: ()) -> Option<Self::State> { Some((s + 1) % 3) }
+/// # }
+/// // Every time the counter is zero, it is eventually two.
+/// let leads_to: Formula<Counter> = Formula::leads_to(
+///     Formula::predicate(|_, s| *s == 0),
+///     Formula::predicate(|_, s| *s == 2),
+/// );
+/// // The counter is one until it is two.
+/// let until: Formula<Counter> = Formula::until(
+///     Formula::predicate(|_, s| *s == 1),
+///     Formula::predicate(|_, s| *s == 2),
+/// );
+/// ```
+pub enum Formula<M: Model> {
+    /// Holds if the predicate is true for the current state.
+    Predicate(fn(&M, &M::State) -> bool),
+    /// Holds if the formula does not hold.
+    Not(Box<Formula<M>>),
+    /// Holds if both formulas hold.
+    And(Box<Formula<M>>, Box<Formula<M>>),
+    /// Holds if either formula holds.
+    Or(Box<Formula<M>>, Box<Formula<M>>),
+    /// Holds if the formula holds starting from the next state.
+    Next(Box<Formula<M>>),
+    /// Holds if the first formula holds at least until the second formula holds, and the second
+    /// formula eventually holds.
+    Until(Box<Formula<M>>, Box<Formula<M>>),
+    /// Holds if the second formula holds up to and including the point where the first formula
+    /// holds, which need not happen.
+    Release(Box<Formula<M>>, Box<Formula<M>>),
+}
+
+impl<M: Model> Formula<M> {
+    /// A formula that holds if the predicate is true for the current state.
+    pub fn predicate(predicate: fn(&M, &M::State) -> bool) -> Self {
+        Formula::Predicate(predicate)
+    }
+
+    /// A formula that holds if the specified formula does not.
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(formula: Self) -> Self {
+        Formula::Not(Box::new(formula))
+    }
+
+    /// A formula that holds if both formulas hold.
+    pub fn and(lhs: Self, rhs: Self) -> Self {
+        Formula::And(Box::new(lhs), Box::new(rhs))
+    }
+
+    /// A formula that holds if either formula holds.
+    pub fn or(lhs: Self, rhs: Self) -> Self {
+        Formula::Or(Box::new(lhs), Box::new(rhs))
+    }
+
+    /// A formula that holds if the second formula holds whenever the first does.
+    pub fn implies(lhs: Self, rhs: Self) -> Self {
+        Formula::or(Formula::not(lhs), rhs)
+    }
+
+    /// A formula that holds if the specified formula holds starting from the next state.
+    pub fn next(formula: Self) -> Self {
+        Formula::Next(Box::new(formula))
+    }
+
+    /// A formula that holds if `lhs` holds at least until `rhs` holds, and `rhs` eventually
+    /// holds.
+    pub fn until(lhs: Self, rhs: Self) -> Self {
+        Formula::Until(Box::new(lhs), Box::new(rhs))
+    }
+
+    /// A formula that holds if `rhs` holds up to and including the point where `lhs` holds, which
+    /// need not happen. The dual of [`Formula::until`].
+    pub fn release(lhs: Self, rhs: Self) -> Self {
+        Formula::Release(Box::new(lhs), Box::new(rhs))
+    }
+
+    /// A formula that holds if the specified formula holds for every state from now on.
+    pub fn always(formula: Self) -> Self {
+        Formula::release(Formula::predicate(|_, _| false), formula)
+    }
+
+    /// A formula that holds if the specified formula holds for some state from now on.
+    pub fn eventually(formula: Self) -> Self {
+        Formula::until(Formula::predicate(|_, _| true), formula)
+    }
+
+    /// A formula that holds if every state satisfying `lhs` is followed by (or is itself) a state
+    /// satisfying `rhs`.
+    pub fn leads_to(lhs: Self, rhs: Self) -> Self {
+        Formula::always(Formula::implies(lhs, Formula::eventually(rhs)))
+    }
+
+    /// Converts this formula (or its negation) to negation normal form, recording the predicates
+    /// in `predicates` so that atoms can refer to them by index.
+    pub(crate) fn to_nnf(
+        &self,
+        negate: bool,
+        predicates: &mut Vec<fn(&M, &M::State) -> bool>,
+    ) -> Nnf {
+        let binary = |lhs: &Self, rhs: &Self, predicates: &mut Vec<_>| {
+            (
+                Box::new(lhs.to_nnf(negate, predicates)),
+                Box::new(rhs.to_nnf(negate, predicates)),
+            )
+        };
+        match self {
+            Formula::Predicate(predicate) => {
+                predicates.push(*predicate);
+                Nnf::Atom(predicates.len() - 1, !negate)
+            }
+            Formula::Not(formula) => formula.to_nnf(!negate, predicates),
+            Formula::And(lhs, rhs) => {
+                let (lhs, rhs) = binary(lhs, rhs, predicates);
+                if negate {
+                    Nnf::Or(lhs, rhs)
+                } else {
+                    Nnf::And(lhs, rhs)
+                }
+            }
+            Formula::Or(lhs, rhs) => {
+                let (lhs, rhs) = binary(lhs, rhs, predicates);
+                if negate {
+                    Nnf::And(lhs, rhs)
+                } else {
+                    Nnf::Or(lhs, rhs)
+                }
+            }
+            Formula::Next(formula) => Nnf::Next(Box::new(formula.to_nnf(negate, predicates))),
+            Formula::Until(lhs, rhs) => {
+                let (lhs, rhs) = binary(lhs, rhs, predicates);
+                if negate {
+                    Nnf::Release(lhs, rhs)
+                } else {
+                    Nnf::Until(lhs, rhs)
+                }
+            }
+            Formula::Release(lhs, rhs) => {
+                let (lhs, rhs) = binary(lhs, rhs, predicates);
+                if negate {
+                    Nnf::Until(lhs, rhs)
+                } else {
+                    Nnf::Release(lhs, rhs)
+                }
+            }
+        }
+    }
+
+    /// Indicates whether this formula holds for the infinite sequence of states that visits
+    /// `states` in order and then repeatedly returns to `states[loop_start..]`.
+    pub(crate) fn holds_on_lasso(&self, model: &M, states: &[M::State], loop_start: usize) -> bool {
+        self.evaluate(model, states, loop_start)[0]
+    }
+
+    fn evaluate(&self, model: &M, states: &[M::State], loop_start: usize) -> Vec<bool> {
+        let successor = |i: usize| {
+            if i + 1 < states.len() {
+                i + 1
+            } else {
+                loop_start
+            }
+        };
+        // Until and release are least and greatest fixpoints respectively, which are found by
+        // iterating from the right-hand side's values.
+        let fixpoint = |lhs: Vec<bool>, rhs: Vec<bool>, is_until: bool| {
+            let mut values = rhs.clone();
+            loop {
+                let mut changed = false;
+                for i in (0..states.len()).rev() {
+                    let value = if is_until {
+                        rhs[i] || (lhs[i] && values[successor(i)])
+                    } else {
+                        rhs[i] && (lhs[i] || values[successor(i)])
+                    };
+                    changed |= value != values[i];
+                    values[i] = value;
+                }
+                if !changed {
+                    return values;
+                }
+            }
+        };
+        match self {
+            Formula::Predicate(predicate) => states.iter().map(|s| predicate(model, s)).collect(),
+            Formula::Not(formula) => formula
+                .evaluate(model, states, loop_start)
+                .into_iter()
+                .map(|v| !v)
+                .collect(),
+            Formula::And(lhs, rhs) => lhs
+                .evaluate(model, states, loop_start)
+                .into_iter()
+                .zip(rhs.evaluate(model, states, loop_start))
+                .map(|(lhs, rhs)| lhs && rhs)
+                .collect(),
+            Formula::Or(lhs, rhs) => lhs
+                .evaluate(model, states, loop_start)
+                .into_iter()
+                .zip(rhs.evaluate(model, states, loop_start))
+                .map(|(lhs, rhs)| lhs || rhs)
+                .collect(),
+            Formula::Next(formula) => {
+                let values = formula.evaluate(model, states, loop_start);
+                (0..states.len()).map(|i| values[successor(i)]).collect()
+            }
+            Formula::Until(lhs, rhs) => fixpoint(
+                lhs.evaluate(model, states, loop_start),
+                rhs.evaluate(model, states, loop_start),
+                true,
+            ),
+            Formula::Release(lhs, rhs) => fixpoint(
+                lhs.evaluate(model, states, loop_start),
+                rhs.evaluate(model, states, loop_start),
+                false,
+            ),
+        }
+    }
+}
+
+// Manual implementation to avoid `Clone` constraint that `#derive(Clone)` would introduce on
+// `Formula<M>` type parameters.
+impl<M: Model> Clone for Formula<M> {
+    fn clone(&self) -> Self {
+        match self {
+            Formula::Predicate(predicate) => Formula::Predicate(*predicate),
+            Formula::Not(formula) => Formula::Not(formula.clone()),
+            Formula::And(lhs, rhs) => Formula::And(lhs.clone(), rhs.clone()),
+            Formula::Or(lhs, rhs) => Formula::Or(lhs.clone(), rhs.clone()),
+            Formula::Next(formula) => Formula::Next(formula.clone()),
+            Formula::Until(lhs, rhs) => Formula::Until(lhs.clone(), rhs.clone()),
+            Formula::Release(lhs, rhs) => Formula::Release(lhs.clone(), rhs.clone()),
+        }
+    }
+}
+
+/// A formula in negation normal form, where atoms refer to predicates by index.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub(crate) enum Nnf {
+    False,
+    /// A predicate index and whether the predicate is expected to be true.
+    Atom(usize, bool),
+    And(Box<Nnf>, Box<Nnf>),
+    Or(Box<Nnf>, Box<Nnf>),
+    Next(Box<Nnf>),
+    Until(Box<Nnf>, Box<Nnf>),
+    Release(Box<Nnf>, Box<Nnf>),
+}
+
+/// A generalized [Büchi automaton](https://en.wikipedia.org/wiki/B%C3%BCchi_automaton) that
+/// accepts the behaviors satisfying a formula. Each state constrains the model state that is
+/// current upon entering it.
+#[derive(Debug)]
+pub(crate) struct Automaton {
+    pub(crate) states: Vec<AutomatonState>,
+}
+
+#[derive(Debug)]
+pub(crate) struct AutomatonState {
+    pub(crate) is_initial: bool,
+    /// Predicate indices and the value that each predicate must have.
+    pub(crate) literals: Vec<(usize, bool)>,
+    pub(crate) successors: Vec<usize>,
+    /// Indices of the acceptance sets containing this state. An accepting run visits every
+    /// acceptance set infinitely often.
+    pub(crate) acceptance_sets: BTreeSet<usize>,
+}
+
+/// A partially expanded automaton state, per the tableau construction of Gerth, Peled, Vardi,
+/// and Wolper in "Simple On-the-fly Automatic Verification of Linear Temporal Logic."
+#[derive(Clone)]
+struct TableauNode {
+    incoming: BTreeSet<Option<usize>>, // `None` indicates an initial node
+    new: BTreeSet<Nnf>,
+    old: BTreeSet<Nnf>,
+    next: BTreeSet<Nnf>,
+}
+
+impl Automaton {
+    pub(crate) fn new(formula: Nnf) -> Self {
+        let mut nodes = Vec::new();
+        expand(
+            TableauNode {
+                incoming: BTreeSet::from([None]),
+                new: BTreeSet::from([formula]),
+                old: BTreeSet::new(),
+                next: BTreeSet::new(),
+            },
+            &mut nodes,
+        );
+
+        let untils: BTreeSet<&Nnf> = nodes
+            .iter()
+            .flat_map(|n| n.old.iter())
+            .filter(|f| matches!(f, Nnf::Until(..)))
+            .collect();
+        let states = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| AutomatonState {
+                is_initial: node.incoming.contains(&None),
+                literals: node
+                    .old
+                    .iter()
+                    .filter_map(|f| match f {
+                        Nnf::Atom(index, value) => Some((*index, *value)),
+                        _ => None,
+                    })
+                    .collect(),
+                successors: nodes
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, n)| n.incoming.contains(&Some(i)))
+                    .map(|(j, _)| j)
+                    .collect(),
+                acceptance_sets: untils
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, until)| match until {
+                        Nnf::Until(_, rhs) => !node.old.contains(until) || node.old.contains(rhs),
+                        _ => unreachable!(),
+                    })
+                    .map(|(j, _)| j)
+                    .collect(),
+            })
+            .collect();
+        Automaton { states }
+    }
+
+    /// The number of acceptance sets.
+    pub(crate) fn acceptance_set_count(&self) -> usize {
+        self.states
+            .iter()
+            .flat_map(|s| s.acceptance_sets.iter())
+            .max()
+            .map_or(0, |max| max + 1)
+    }
+}
+
+fn expand(mut node: TableauNode, nodes: &mut Vec<TableauNode>) {
+    let formula = match node.new.pop_first() {
+        Some(formula) => formula,
+        None => {
+            if let Some(existing) = nodes
+                .iter_mut()
+                .find(|n| n.old == node.old && n.next == node.next)
+            {
+                existing.incoming.extend(node.incoming);
+            } else {
+                let successor = TableauNode {
+                    incoming: BTreeSet::from([Some(nodes.len())]),
+                    new: node.next.clone(),
+                    old: BTreeSet::new(),
+                    next: BTreeSet::new(),
+                };
+                nodes.push(node);
+                expand(successor, nodes);
+            }
+            return;
+        }
+    };
+    let split = |node: &TableauNode, new: &[&Nnf], next: Option<&Nnf>| {
+        let mut node = node.clone();
+        for f in new {
+            if !node.old.contains(*f) {
+                node.new.insert((*f).clone());
+            }
+        }
+        if let Some(next) = next {
+            node.next.insert(next.clone());
+        }
+        node
+    };
+    match &formula {
+        Nnf::False => {} // contradiction
+        Nnf::Atom(index, value) => {
+            if !node.old.contains(&Nnf::Atom(*index, !value)) {
+                node.old.insert(formula);
+                expand(node, nodes);
+            }
+        }
+        Nnf::And(lhs, rhs) => {
+            let mut node = split(&node, &[lhs, rhs], None);
+            node.old.insert(formula);
+            expand(node, nodes);
+        }
+        Nnf::Or(lhs, rhs) => {
+            let mut node1 = split(&node, &[lhs], None);
+            let mut node2 = split(&node, &[rhs], None);
+            node1.old.insert(formula.clone());
+            node2.old.insert(formula);
+            expand(node1, nodes);
+            expand(node2, nodes);
+        }
+        Nnf::Next(next) => {
+            let mut node = split(&node, &[], Some(next));
+            node.old.insert(formula);
+            expand(node, nodes);
+        }
+        Nnf::Until(lhs, rhs) => {
+            let mut node1 = split(&node, &[lhs], Some(&formula));
+            let mut node2 = split(&node, &[rhs], None);
+            node1.old.insert(formula.clone());
+            node2.old.insert(formula);
+            expand(node1, nodes);
+            expand(node2, nodes);
+        }
+        Nnf::Release(lhs, rhs) => {
+            let mut node1 = split(&node, &[rhs], Some(&formula));
+            let mut node2 = split(&node, &[lhs, rhs], None);
+            node1.old.insert(formula.clone());
+            node2.old.insert(formula);
+            expand(node1, nodes);
+            expand(node2, nodes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::dgraph::DGraph;
+
+    fn is_even() -> Formula<DGraph> {
+        Formula::predicate(|_, s| s % 2 == 0)
+    }
+
+    fn is_zero() -> Formula<DGraph> {
+        Formula::predicate(|_, s| *s == 0)
+    }
+
+    #[test]
+    fn can_evaluate_on_lasso() {
+        let model = DGraph::with_property(crate::Property::always("unused", |_, _| true));
+        let holds = |formula: Formula<DGraph>, states: &[u8], loop_start| {
+            formula.holds_on_lasso(&model, states, loop_start)
+        };
+
+        // 0, 2, (4, 5)...
+        let states = [0, 2, 4, 5];
+        assert!(holds(is_zero(), &states, 2));
+        assert!(!holds(Formula::next(is_zero()), &states, 2));
+        assert!(holds(
+            Formula::until(is_even(), Formula::not(is_even())),
+            &states,
+            2
+        ));
+        assert!(!holds(Formula::always(is_even()), &states, 2));
+        assert!(holds(
+            Formula::always(Formula::eventually(Formula::not(is_even()))),
+            &states,
+            2
+        ));
+        assert!(!holds(
+            Formula::eventually(Formula::always(is_even())),
+            &states,
+            2
+        ));
+        assert!(!holds(
+            Formula::leads_to(Formula::not(is_even()), is_zero()),
+            &states,
+            2
+        ));
+
+        // 1, 0, 0...
+        let states = [1, 0];
+        assert!(holds(
+            Formula::eventually(Formula::always(is_zero())),
+            &states,
+            1
+        ));
+        assert!(holds(
+            Formula::leads_to(Formula::not(is_even()), is_zero()),
+            &states,
+            1
+        ));
+        assert!(!holds(
+            Formula::release(is_zero(), Formula::not(is_even())),
+            &states,
+            1
+        ));
+    }
+
+    #[test]
+    fn can_build_automaton() {
+        // Always even: one state that loops and requires evenness.
+        let mut predicates = Vec::new();
+        let automaton = Automaton::new(Formula::always(is_even()).to_nnf(false, &mut predicates));
+        assert_eq!(predicates.len(), 2); // includes the `false` from `always`
+        let loops: Vec<_> = automaton
+            .states
+            .iter()
+            .filter(|s| s.literals.contains(&(1, true)))
+            .collect();
+        assert!(!loops.is_empty());
+        assert!(loops.iter().all(|s| s.acceptance_sets.is_empty()));
+        assert_eq!(automaton.acceptance_set_count(), 0);
+
+        // Eventually even: a state awaiting evenness that cannot be accepting forever.
+        let mut predicates = Vec::new();
+        let automaton =
+            Automaton::new(Formula::eventually(is_even()).to_nnf(false, &mut predicates));
+        assert_eq!(automaton.acceptance_set_count(), 1);
+        assert!(automaton
+            .states
+            .iter()
+            .any(|s| s.is_initial && s.acceptance_sets.is_empty()));
+        assert!(automaton
+            .states
+            .iter()
+            .any(|s| s.is_initial && s.literals.contains(&(1, true))));
+    }
+}
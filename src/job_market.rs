@@ -49,6 +49,51 @@ struct JobMarket<Job> {
     open_count: usize,
     /// Jobs available.
     job_batches: Vec<VecDeque<Job>>,
+    /// Periodically saves the jobs, if enabled.
+    checkpointer: Option<Checkpointer<Job>>,
+}
+
+type SaveFn<Job> = Box<dyn FnMut(&[VecDeque<Job>]) + Send>;
+
+struct Checkpointer<Job> {
+    /// How often to save.
+    interval: Duration,
+    /// When the next save is due.
+    next_at: SystemTime,
+    /// Whether to close the market after the next save (e.g. upon reaching a timeout).
+    close_after: bool,
+    /// How many times the jobs have been saved.
+    save_count: usize,
+    /// Saves the jobs along with any other state that the workers share.
+    save: SaveFn<Job>,
+}
+
+impl<Job> JobMarket<Job> {
+    fn is_checkpoint_due(&self) -> bool {
+        match &self.checkpointer {
+            Some(checkpointer) => checkpointer.next_at <= SystemTime::now(),
+            None => false,
+        }
+    }
+
+    fn checkpoint_save_count(&self) -> usize {
+        match &self.checkpointer {
+            Some(checkpointer) => checkpointer.save_count,
+            None => 0,
+        }
+    }
+
+    fn checkpoint(&mut self) {
+        if let Some(checkpointer) = &mut self.checkpointer {
+            (checkpointer.save)(&self.job_batches);
+            checkpointer.save_count += 1;
+            checkpointer.next_at = SystemTime::now() + checkpointer.interval;
+            if checkpointer.close_after {
+                self.open = false;
+                self.job_batches.clear();
+            }
+        }
+    }
 }
 
 impl<Job> JobBroker<Job>
@@ -64,6 +109,7 @@ where
                 thread_count,
                 open_count: thread_count,
                 job_batches: Vec::new(),
+                checkpointer: None,
             })),
         };
         if let Some(closing_time) = close_at {
@@ -71,14 +117,30 @@ where
             std::thread::Builder::new()
                 .name("timeout".to_owned())
                 .spawn(move || loop {
-                    let mut market = s1.market.lock();
-                    let now = SystemTime::now();
-                    if closing_time < now {
-                        log::debug!("Reached timeout, triggering shutdown");
-                        market.open = false;
-                    }
-                    if !market.open {
-                        break;
+                    {
+                        let mut market = s1.market.lock();
+                        let now = SystemTime::now();
+                        if closing_time < now {
+                            match &mut market.checkpointer {
+                                Some(checkpointer) => {
+                                    // Workers close the market once they have saved their jobs.
+                                    if !checkpointer.close_after {
+                                        log::debug!(
+                                            "Reached timeout, triggering checkpoint and shutdown"
+                                        );
+                                        checkpointer.next_at = now;
+                                        checkpointer.close_after = true;
+                                    }
+                                }
+                                None => {
+                                    log::debug!("Reached timeout, triggering shutdown");
+                                    market.open = false;
+                                }
+                            }
+                        }
+                        if !market.open {
+                            break;
+                        }
                     }
                     sleep(Duration::from_secs(1));
                 })
@@ -86,6 +148,24 @@ where
         }
         s
     }
+
+    /// Periodically passes all jobs to a function that saves them. Workers should return their
+    /// jobs to the market (via [`JobBroker::push`]) when [`JobBroker::is_checkpoint_due`], at
+    /// which point the last worker to do so performs the save while the others wait.
+    pub fn checkpoint_every(
+        &mut self,
+        interval: Duration,
+        save: impl FnMut(&[VecDeque<Job>]) + Send + 'static,
+    ) {
+        let mut market = self.market.lock();
+        market.checkpointer = Some(Checkpointer {
+            interval,
+            next_at: SystemTime::now() + interval,
+            close_after: false,
+            save_count: 0,
+            save: Box::new(save),
+        });
+    }
 }
 
 impl<Job> JobBroker<Job> {
@@ -94,10 +174,31 @@ impl<Job> JobBroker<Job> {
     /// Returns an empty result if there are no more jobs coming.
     pub fn pop(&mut self) -> VecDeque<Job> {
         let mut market = self.market.lock();
-        if !market.open {
-            return VecDeque::new();
-        }
         loop {
+            if !market.open {
+                return VecDeque::new();
+            }
+            if market.is_checkpoint_due() {
+                // Wait for the other workers to return their jobs.
+                market.open_count = market.open_count.saturating_sub(1);
+                if market.open_count == 0 {
+                    log::debug!(
+                        "{}: All jobs returned. Saving checkpoint.",
+                        std::thread::current().name().unwrap_or_default()
+                    );
+                    market.checkpoint();
+                    self.has_new_jobs.notify_all();
+                } else {
+                    let save_count = market.checkpoint_save_count();
+                    while market.open && market.checkpoint_save_count() == save_count {
+                        self.has_new_jobs.wait(&mut market);
+                    }
+                }
+                market.open_count += 1;
+                if !market.open {
+                    return VecDeque::new();
+                }
+            }
             if let Some(jobs) = market.job_batches.pop() {
                 log::trace!(
                     "{}: Got jobs. Working.",
@@ -175,6 +276,11 @@ impl<Job> JobBroker<Job> {
         }
     }
 
+    /// See whether the workers should return their jobs to the market so that they can be saved.
+    pub fn is_checkpoint_due(&self) -> bool {
+        self.market.lock().is_checkpoint_due()
+    }
+
     /// See whether the market is closed.
     pub fn is_closed(&self) -> bool {
         let market = self.market.lock();
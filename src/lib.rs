@@ -134,6 +134,7 @@
 #[warn(anonymous_parameters)]
 #[warn(missing_docs)]
 mod checker;
+mod formula;
 mod has_discoveries;
 mod job_market;
 pub mod report;
@@ -145,6 +146,7 @@ mod test_util;
 
 pub mod actor;
 pub use checker::*;
+pub use formula::Formula;
 pub use has_discoveries::HasDiscoveries;
 pub mod semantics;
 pub mod util;
@@ -246,6 +248,20 @@ pub trait Model: Sized {
         true
     }
 
+    /// Generates the fairness constraints that rule out unrealistic behaviors when checking
+    /// `eventually` properties via [`CheckerBuilder::spawn_liveness`].
+    fn fairness(&self) -> Vec<Fairness<Self>> {
+        Vec::new()
+    }
+
+    /// Indicates whether two actions that are both enabled in a state are independent there,
+    /// meaning that taking one does not disable the other, and taking them in either order results
+    /// in the same state. Used by [`CheckerBuilder::partial_order_reduction`] to avoid exploring
+    /// redundant interleavings. Defaults to `false`, which is always safe.
+    fn independent(&self, _state: &Self::State, _a: &Self::Action, _b: &Self::Action) -> bool {
+        false
+    }
+
     /// Instantiates a [`CheckerBuilder`] for this model.
     fn checker(self) -> CheckerBuilder<Self>
     where
@@ -265,6 +281,15 @@ pub struct Property<M: Model> {
     pub expectation: Expectation,
     pub name: &'static str,
     pub condition: fn(&M, &M::State) -> bool,
+    /// The formula of a [`Property::temporal`] property, which has no `condition`.
+    pub(crate) formula: Option<Formula<M>>,
+    /// The condition of a [`Property::transition`] property, which has no `condition`, along
+    /// with a way to clone the action, as `next_state` consumes it.
+    #[allow(clippy::type_complexity)]
+    pub(crate) transition: Option<(
+        fn(&M, &M::State, &M::Action, &M::State) -> bool,
+        fn(&M::Action) -> M::Action,
+    )>,
 }
 impl<M: Model> Property<M> {
     /// An invariant that defines a [safety
@@ -275,6 +300,8 @@ impl<M: Model> Property<M> {
             expectation: Expectation::Always,
             name,
             condition,
+            formula: None,
+            transition: None,
         }
     }
 
@@ -283,16 +310,20 @@ impl<M: Model> Property<M> {
     /// discover a counterexample path leading from the initial state through to a
     /// terminal state.
     ///
-    /// Note that in this implementation `eventually` properties only work correctly on acyclic
-    /// paths (those that end in either states with no successors or checking boundaries). A path
-    /// ending in a cycle is not viewed as _terminating_ in that cycle, as the checker does not
-    /// differentiate cycles from DAG joins, and so an `eventually` property that has not been met
-    /// by the cycle-closing edge will ignored -- a false negative.
+    /// Note that the BFS, DFS, on-demand, and simulation checkers only handle `eventually`
+    /// properties correctly on acyclic paths (those that end in either states with no successors
+    /// or checking boundaries). A path ending in a cycle is not viewed as _terminating_ in that
+    /// cycle, as those checkers do not differentiate cycles from DAG joins, and so an `eventually`
+    /// property that has not been met by the cycle-closing edge will ignored -- a false negative.
+    /// Use [`CheckerBuilder::spawn_liveness`] to also find counterexamples that end in a cycle,
+    /// optionally subject to [`Model::fairness`] constraints.
     pub fn eventually(name: &'static str, condition: fn(&M, &M::State) -> bool) -> Property<M> {
         Property {
             expectation: Expectation::Eventually,
             name,
             condition,
+            formula: None,
+            transition: None,
         }
     }
 
@@ -303,6 +334,46 @@ impl<M: Model> Property<M> {
             expectation: Expectation::Sometimes,
             name,
             condition,
+            formula: None,
+            transition: None,
+        }
+    }
+
+    /// A [linear temporal logic](https://en.wikipedia.org/wiki/Linear_temporal_logic) formula
+    /// that every behavior path should satisfy, such as "every request _leads to_ a response."
+    /// [`CheckerBuilder::spawn_bfs`], [`CheckerBuilder::spawn_dfs`], and
+    /// [`CheckerBuilder::spawn_liveness`] check these properties by searching the product of the
+    /// state space and an automaton for the negated formula. The on-demand checker skips them,
+    /// and the other checkers panic when spawned for a model with such a property. The model
+    /// checker will try to discover a counterexample path, which either ends in a terminal state
+    /// or ends in a cycle (as indicated by the last state appearing earlier in the path).
+    pub fn temporal(name: &'static str, formula: Formula<M>) -> Property<M> {
+        Property {
+            expectation: Expectation::Temporal,
+            name,
+            condition: |_, _| true,
+            formula: Some(formula),
+            transition: None,
+        }
+    }
+}
+impl<M: Model> Property<M>
+where
+    M::Action: Clone,
+{
+    /// An invariant over the steps of the model, such as "a term never decreases," which is given
+    /// the previous state, the action, and the next state. The model checker will try to discover
+    /// a counterexample, which is a path whose last step violates the invariant.
+    pub fn transition(
+        name: &'static str,
+        transition: fn(&M, &M::State, &M::Action, &M::State) -> bool,
+    ) -> Property<M> {
+        Property {
+            expectation: Expectation::Transition,
+            name,
+            condition: |_, _| true,
+            formula: None,
+            transition: Some((transition, M::Action::clone)),
         }
     }
 }
@@ -312,11 +383,14 @@ impl<M: Model> Clone for Property<M> {
             expectation: self.expectation.clone(),
             name: self.name,
             condition: self.condition,
+            formula: self.formula.clone(),
+            transition: self.transition,
         }
     }
 }
 
-/// Indicates whether a property is always, eventually, or sometimes true.
+/// Indicates whether a property is always, eventually, or sometimes true, or whether it is a
+/// temporal formula or transition invariant.
 #[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, serde::Deserialize, serde::Serialize)]
 pub enum Expectation {
     /// The property is true for all reachable states.
@@ -325,6 +399,10 @@ pub enum Expectation {
     Eventually,
     /// The property is true for at least one reachable state.
     Sometimes,
+    /// The property's [`Formula`] holds for all behavior paths.
+    Temporal,
+    /// The property's transition condition is true for all steps between reachable states.
+    Transition,
 }
 
 impl Expectation {
@@ -333,10 +411,58 @@ impl Expectation {
             Expectation::Always => true,
             Expectation::Eventually => true,
             Expectation::Sometimes => false,
+            Expectation::Temporal => true,
+            Expectation::Transition => true,
         }
     }
 }
 
+/// A fairness constraint, which rules out behaviors that neglect an action forever. Each action
+/// for which `actions` returns `true` is constrained individually, so for instance a constraint
+/// matching every message delivery of an [`actor::ActorModel`] indicates that every deliverable
+/// message is eventually delivered (rather than that _some_ message is eventually delivered).
+///
+/// Only [`CheckerBuilder::spawn_liveness`] takes fairness into account.
+pub struct Fairness<M: Model> {
+    pub kind: FairnessKind,
+    pub actions: fn(&M, &M::Action) -> bool,
+}
+impl<M: Model> Fairness<M> {
+    /// A constraint indicating that an action cannot remain enabled forever without being taken.
+    pub fn weak(actions: fn(&M, &M::Action) -> bool) -> Fairness<M> {
+        Fairness {
+            kind: FairnessKind::Weak,
+            actions,
+        }
+    }
+
+    /// A constraint indicating that an action cannot be enabled infinitely often (even if it is
+    /// also disabled infinitely often) without being taken.
+    pub fn strong(actions: fn(&M, &M::Action) -> bool) -> Fairness<M> {
+        Fairness {
+            kind: FairnessKind::Strong,
+            actions,
+        }
+    }
+}
+impl<M: Model> Clone for Fairness<M> {
+    fn clone(&self) -> Self {
+        Fairness {
+            kind: self.kind,
+            actions: self.actions,
+        }
+    }
+}
+
+/// Indicates whether a [`Fairness`] constraint is weak or strong.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum FairnessKind {
+    /// An action that is continuously enabled is eventually taken.
+    Weak,
+    /// An action that is repeatedly enabled is eventually taken.
+    Strong,
+}
+
 /// A state identifier. See [`fingerprint`].
 type Fingerprint = std::num::NonZeroU64;
 
@@ -4,7 +4,7 @@ use std::hash::Hash;
 use std::io::Write;
 use std::time::Duration;
 
-use crate::{DiscoveryClassification, Model, Path};
+use crate::{Coverage, DiscoveryClassification, Model, Path};
 
 /// The data sent during a report event.
 pub struct ReportData {
@@ -12,6 +12,10 @@ pub struct ReportData {
     pub total_states: usize,
     /// The number of unique states found.
     pub unique_states: usize,
+    /// The number of states skipped by partial order reduction.
+    pub pruned_states: usize,
+    /// Whether every generated state was checked.
+    pub coverage: Coverage,
     /// Maximum depth explored.
     pub max_depth: usize,
     /// The current duration checking has been running for.
@@ -63,20 +67,31 @@ where
     W: Write,
 {
     fn report_checking(&mut self, data: ReportData) {
+        let pruned = if data.pruned_states > 0 {
+            format!(", pruned={}", data.pruned_states)
+        } else {
+            String::new()
+        };
+        let coverage = match data.coverage {
+            Coverage::Exhaustive => String::new(),
+            coverage => format!(", coverage={}", coverage),
+        };
         if data.done {
             let _ = writeln!(
                 self.writer,
-                "Done. states={}, unique={}, depth={}, sec={}",
+                "Done. states={}, unique={}{}, depth={}, sec={}{}",
                 data.total_states,
                 data.unique_states,
+                pruned,
                 data.max_depth,
                 data.duration.as_secs(),
+                coverage,
             );
         } else {
             let _ = writeln!(
                 self.writer,
-                "Checking. states={}, unique={}, depth={}",
-                data.total_states, data.unique_states, data.max_depth
+                "Checking. states={}, unique={}{}, depth={}{}",
+                data.total_states, data.unique_states, pruned, data.max_depth, coverage
             );
         }
     }
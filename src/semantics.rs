@@ -6,7 +6,8 @@
 //!
 //! [`SequentialSpec`] is a trait for defining correctness via a "reference implementation" (e.g.
 //! "*this system should behave like a queue*").  Stateright includes reusable implementations such
-//! as [`register`] for register-like semantics and [`vec`] for stack-like semantics.  Implementing
+//! as [`register`] for register-like semantics, [`key_value`] for map-like semantics, and [`vec`]
+//! for stack-like semantics.  Implementing
 //! the trait yourself is also straightforward -- just define two `enum`s for invocations and
 //! returns. Then associate these (as [`SequentialSpec::Op`] and [`SequentialSpec::Ret`]
 //! respectively) with a state type that implements [`SequentialSpec::invoke`].
@@ -15,7 +16,8 @@
 //!
 //! A concurrent system can be verified against an implementation of the [`SequentialSpec`] trait
 //! by using a [`ConsistencyTester`] for an expected [consistency model],  such as
-//! [`LinearizabilityTester`]. In that case, operations are sequential (think blocking I/O) with
+//! [`LinearizabilityTester`] or, for geo-replicated systems, [`CausalConsistencyTester`]. In
+//! that case, operations are sequential (think blocking I/O) with
 //! respect to an abstract thread-like caller, which is identified by a distinct "thread ID"
 //! (sometimes called a "process ID" in the literature, but these are assumed to be
 //! single-threaded, so thread ID arguably provides better intuition for most developers).
@@ -28,6 +30,22 @@
 //! its own [`ConsistencyTester`] with local thread IDs for multiple concurrent
 //! invocations.
 //!
+//! A history recorded outside of a model, such as by a test harness, can instead be checked
+//! after the fact by passing its [`HistoryEvent`]s to [`ConsistencyTester::on_history`]. See
+//! [`history`] for a JSON format that also supports failed and indeterminate operations.
+//!
+//! # Verifying Convergence
+//!
+//! Replicated data types such as CRDTs are instead verified by checking that replicas converge.
+//! See [`convergence`], which includes reusable specs such as a grow-only counter and an
+//! observed-remove set.
+//!
+//! # Verifying Transactional Isolation
+//!
+//! Systems that group reads and writes over multiple keys into transactions are instead verified
+//! by an [`IsolationTester`], which checks an [`IsolationLevel`] such as serializability and
+//! explains any anomaly that it finds.
+//!
 //! # Additional Reading
 //!
 //! For more background on specifying the semantics of concurrent systems, see
@@ -35,6 +53,8 @@
 //!
 //! - ["Consistency in Non-Transactional Distributed Storage
 //!   Systems"](http://vukolic.com/consistency-survey.pdf) by Viotti and Vukolić
+//! - ["Weak Consistency: A Generalized Theory and Optimistic Implementations for Distributed
+//!   Transactions"](https://pmg.csail.mit.edu/papers/adya-phd.pdf) by Adya
 //! - ["Principles of Eventual
 //!   Consistency"](https://www.microsoft.com/en-us/research/publication/principles-of-eventual-consistency/)
 //!   by Burckhardt
@@ -46,15 +66,24 @@
 //! [consistency model]: https://en.wikipedia.org/wiki/Consistency_model
 //! [`vec`]: self::vec
 
+mod causal_consistency;
 mod consistency_tester;
+mod isolation;
 mod linearizability;
 mod sequential_consistency;
+mod session_guarantees;
 
-pub use consistency_tester::ConsistencyTester;
+pub use causal_consistency::CausalConsistencyTester;
+pub use consistency_tester::{ConsistencyTester, HistoryEvent};
+pub mod convergence;
+pub mod history;
+pub use isolation::{Anomaly, IsolationLevel, IsolationTester, IsolationViolation, TxnOp};
+pub mod key_value;
 pub mod register;
 pub mod write_once_register;
 pub use linearizability::LinearizabilityTester;
 pub use sequential_consistency::SequentialConsistencyTester;
+pub use session_guarantees::{SessionGuarantee, SessionGuaranteeTester};
 pub mod vec;
 
 /// An implementation of this trait can serve as a sequential "reference object"
@@ -67,6 +96,8 @@ pub mod vec;
 ///
 /// - [`LinearizabilityTester`]
 /// - [`SequentialConsistencyTester`]
+/// - [`CausalConsistencyTester`]
+/// - [`SessionGuaranteeTester`]
 ///
 /// [consistency model]: https://en.wikipedia.org/wiki/Consistency_model
 /// [operational semantics]: https://en.wikipedia.org/wiki/Operational_semantics
@@ -96,3 +127,20 @@ pub trait SequentialSpec: Sized {
             .all(|(op, ret)| self.is_valid_step(&op, &ret))
     }
 }
+
+/// A [`SequentialSpec`] whose operations each act upon one of many independent partitions, such
+/// as the keys of a [`key_value::KeyValue`] store.
+///
+/// Linearizability is compositional, so a history is linearizable if and only if the history of
+/// every partition is linearizable. [`LinearizabilityTester::serialized_history_by_partition`]
+/// leverages this property, which is often exponentially faster than checking the full history.
+pub trait PartitionedSpec: SequentialSpec {
+    /// The type that identifies a partition, such as a key.
+    type Key: Clone + Ord;
+
+    /// The partition upon which an operation acts.
+    fn partition(op: &Self::Op) -> Self::Key;
+
+    /// This reference object restricted to a single partition.
+    fn project(&self, key: &Self::Key) -> Self;
+}
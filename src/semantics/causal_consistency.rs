@@ -0,0 +1,540 @@
+//! Private module for selective re-export. See [`CausalConsistencyTester`].
+
+use crate::semantics::{ConsistencyTester, SequentialSpec, SessionGuarantee};
+use std::collections::{btree_map, BTreeMap, BTreeSet};
+use std::fmt::Debug;
+
+// Both this tester and `SessionGuaranteeTester` search for a "visibility" relation in the style
+// of Burckhardt's "Principles of Eventual Consistency." Operations are assigned a total
+// "arbitration" order that extends the order of operations within each thread, and each
+// operation observes a subset of the operations arbitrated before it. An operation's return
+// value must match the result of replaying the operations it observes (ignoring their return
+// values) followed by the operation itself. Consistency models then differ only in how the
+// observed subsets are constrained. Checking causal consistency is NP-complete in general, so
+// rejecting a long history can still take time exponential in its length.
+
+/// This tester captures a potentially concurrent history of operations and validates that it
+/// adheres to a [`SequentialSpec`] based on the [causal consistency] model. This model requires
+/// that every operation observe the operations that causally precede it, where an operation
+/// causally precedes another if it was earlier in the same thread or if it was observed by an
+/// operation that causally precedes the other.
+///
+/// Favor [`LinearizabilityTester`] or [`SequentialConsistencyTester`] if the system is expected
+/// to provide those stronger guarantees.
+///
+/// # Causal Consistency
+///
+/// Unlike with [sequential consistency], threads need not agree on a single order of
+/// operations, so a thread can observe its own writes before observing concurrent writes from
+/// other threads. For example, the following history is causally consistent but not
+/// sequentially consistent, as each thread reads a length that only includes its own push:
+///
+/// ```text
+/// Thread 1: [push(1)] [len() -> 1]
+/// Thread 2: [push(2)] [len() -> 1]
+/// ```
+///
+/// Causality is nonetheless preserved. If Thread 2 pushes a value after observing a value
+/// pushed by Thread 1, then no thread can observe the former without observing the latter.
+///
+/// Concurrent operations are replayed in an order common to all threads, so this tester verifies
+/// the variant of causal consistency that requires replicas to converge, sometimes called
+/// "causal+" or "causal convergence." This matches systems that resolve conflicting writes
+/// deterministically, such as with last-writer-wins registers. Operations still in flight may or
+/// may not have been observed.
+///
+/// See also: [`SessionGuaranteeTester`], which verifies weaker guarantees.
+///
+/// [causal consistency]: https://en.wikipedia.org/wiki/Causal_consistency
+/// [sequential consistency]: https://en.wikipedia.org/wiki/Sequential_consistency
+/// [`LinearizabilityTester`]: crate::semantics::LinearizabilityTester
+/// [`SequentialConsistencyTester`]: crate::semantics::SequentialConsistencyTester
+/// [`SessionGuaranteeTester`]: crate::semantics::SessionGuaranteeTester
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct CausalConsistencyTester<ThreadId, RefObj: SequentialSpec>(
+    pub(crate) VisibilityTester<ThreadId, RefObj, RefObj::Op, RefObj::Ret>,
+);
+
+#[allow(clippy::len_without_is_empty)] // no use case for an emptiness check
+impl<T: Ord, RefObj: SequentialSpec> CausalConsistencyTester<T, RefObj> {
+    /// Constructs a [`CausalConsistencyTester`].
+    pub fn new(init_ref_obj: RefObj) -> Self {
+        // Observing earlier operations in the same thread and observing whatever they observed
+        // implies every session guarantee.
+        let guarantees = [SessionGuarantee::ReadYourWrites].into_iter().collect();
+        Self(VisibilityTester::new(init_ref_obj, guarantees, true))
+    }
+
+    /// Indicates the aggregate number of operations completed or in flight
+    /// across all threads.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl<T, RefObj> ConsistencyTester<T, RefObj> for CausalConsistencyTester<T, RefObj>
+where
+    T: Copy + Debug + Ord,
+    RefObj: Clone + SequentialSpec,
+    RefObj::Op: Clone + Debug,
+    RefObj::Ret: Clone + Debug + PartialEq,
+{
+    /// Indicates that a thread invoked an operation. Returns `Ok(...)` if the
+    /// history is valid, even if it is not causally consistent.
+    ///
+    /// See [`CausalConsistencyTester::serialized_history`].
+    fn on_invoke(&mut self, thread_id: T, op: RefObj::Op) -> Result<&mut Self, String> {
+        self.0.on_invoke(thread_id, op)?;
+        Ok(self)
+    }
+
+    /// Indicates that a thread's earlier operation invocation returned. Returns
+    /// `Ok(...)` if the history is valid, even if it is not causally consistent.
+    ///
+    /// See [`CausalConsistencyTester::serialized_history`].
+    fn on_return(&mut self, thread_id: T, ret: RefObj::Ret) -> Result<&mut Self, String> {
+        self.0.on_return(thread_id, ret)?;
+        Ok(self)
+    }
+
+    /// Indicates whether the recorded history is causally consistent.
+    fn is_consistent(&self) -> bool {
+        self.serialized_history().is_some()
+    }
+}
+
+impl<T, RefObj> CausalConsistencyTester<T, RefObj>
+where
+    T: Copy + Debug + Ord,
+    RefObj: Clone + SequentialSpec,
+    RefObj::Op: Clone + Debug,
+    RefObj::Ret: Clone + Debug + PartialEq,
+{
+    /// Attempts to order the recorded partially ordered operation history such that every
+    /// operation's return value results from replaying the operations it observes in that
+    /// order. Operations still in flight may or may not be included.
+    pub fn serialized_history(&self) -> Option<Vec<(RefObj::Op, RefObj::Ret)>> {
+        self.0.serialized_history()
+    }
+}
+
+impl<T: Ord, RefObj> Default for CausalConsistencyTester<T, RefObj>
+where
+    RefObj: Default + SequentialSpec,
+{
+    fn default() -> Self {
+        Self::new(RefObj::default())
+    }
+}
+
+impl<T, RefObj> serde::Serialize for CausalConsistencyTester<T, RefObj>
+where
+    RefObj: serde::Serialize + SequentialSpec,
+    RefObj::Op: serde::Serialize,
+    RefObj::Ret: serde::Serialize,
+    T: Ord + serde::Serialize,
+{
+    fn serialize<Ser: serde::Serializer>(&self, ser: Ser) -> Result<Ser::Ok, Ser::Error> {
+        self.0.serialize("CausalConsistencyTester", ser)
+    }
+}
+
+/// Records a history and searches for a visibility relation satisfying the specified
+/// constraints. Parameterized by the operation and return types so that testers wrapping it can
+/// derive traits.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub(crate) struct VisibilityTester<ThreadId, RefObj, Op, Ret> {
+    init_ref_obj: RefObj,
+    history_by_thread: BTreeMap<ThreadId, Vec<(Op, Ret)>>,
+    in_flight_by_thread: BTreeMap<ThreadId, Op>,
+    is_valid_history: bool,
+    guarantees: BTreeSet<SessionGuarantee>,
+    /// Whether operations observe whatever the operations they observe observed.
+    transitive: bool,
+}
+
+/// An operation assigned a position in the arbitration order.
+struct Arbitrated<'a, ThreadId, Op, Ret> {
+    thread_id: ThreadId,
+    op: &'a Op,
+    ret: Ret,
+    /// Positions of the observed operations in the arbitration order.
+    observed: BTreeSet<usize>,
+}
+
+impl<T: Ord, RefObj: SequentialSpec> VisibilityTester<T, RefObj, RefObj::Op, RefObj::Ret> {
+    pub(crate) fn new(
+        init_ref_obj: RefObj,
+        guarantees: BTreeSet<SessionGuarantee>,
+        transitive: bool,
+    ) -> Self {
+        Self {
+            init_ref_obj,
+            history_by_thread: Default::default(),
+            in_flight_by_thread: Default::default(),
+            is_valid_history: true,
+            guarantees,
+            transitive,
+        }
+    }
+
+    pub(crate) fn guarantees_mut(&mut self) -> &mut BTreeSet<SessionGuarantee> {
+        &mut self.guarantees
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        let mut len = self.in_flight_by_thread.len();
+        for history in self.history_by_thread.values() {
+            len += history.len();
+        }
+        len
+    }
+
+    pub(crate) fn serialize<Ser: serde::Serializer>(
+        &self,
+        name: &'static str,
+        ser: Ser,
+    ) -> Result<Ser::Ok, Ser::Error>
+    where
+        RefObj: serde::Serialize,
+        RefObj::Op: serde::Serialize,
+        RefObj::Ret: serde::Serialize,
+        T: serde::Serialize,
+    {
+        use serde::ser::SerializeStruct;
+        let mut out = ser.serialize_struct(name, 4)?;
+        out.serialize_field("init_ref_obj", &self.init_ref_obj)?;
+        out.serialize_field("history_by_thread", &self.history_by_thread)?;
+        out.serialize_field("in_flight_by_thread", &self.in_flight_by_thread)?;
+        out.serialize_field("is_valid_history", &self.is_valid_history)?;
+        out.end()
+    }
+}
+
+impl<T, RefObj> VisibilityTester<T, RefObj, RefObj::Op, RefObj::Ret>
+where
+    T: Copy + Debug + Ord,
+    RefObj: Clone + SequentialSpec,
+    RefObj::Op: Clone + Debug,
+    RefObj::Ret: Clone + Debug + PartialEq,
+{
+    pub(crate) fn on_invoke(&mut self, thread_id: T, op: RefObj::Op) -> Result<(), String> {
+        if !self.is_valid_history {
+            return Err("Earlier history was invalid.".to_string());
+        }
+        let in_flight_elem = self.in_flight_by_thread.entry(thread_id);
+        if let btree_map::Entry::Occupied(occupied_op_entry) = in_flight_elem {
+            self.is_valid_history = false;
+            return Err(format!(
+                    "Thread already has an operation in flight. thread_id={:?}, op={:?}, history_by_thread={:?}",
+                    thread_id, occupied_op_entry.get(), self.history_by_thread));
+        };
+        in_flight_elem.or_insert(op);
+        self.history_by_thread.entry(thread_id).or_default();
+        Ok(())
+    }
+
+    pub(crate) fn on_return(&mut self, thread_id: T, ret: RefObj::Ret) -> Result<(), String> {
+        if !self.is_valid_history {
+            return Err("Earlier history was invalid.".to_string());
+        }
+        let op = match self.in_flight_by_thread.remove(&thread_id) {
+            None => {
+                self.is_valid_history = false;
+                return Err(format!(
+                    "There is no in-flight invocation for this thread ID. \
+                     thread_id={:?}, unexpected_return={:?}, history={:?}",
+                    thread_id,
+                    ret,
+                    self.history_by_thread.entry(thread_id).or_default()
+                ));
+            }
+            Some(op) => op,
+        };
+        self.history_by_thread
+            .entry(thread_id)
+            .or_default()
+            .push((op, ret));
+        Ok(())
+    }
+
+    pub(crate) fn serialized_history(&self) -> Option<Vec<(RefObj::Op, RefObj::Ret)>> {
+        if !self.is_valid_history {
+            return None;
+        }
+        let mut arbitrated = Vec::new();
+        let mut next_by_thread: BTreeMap<T, usize> =
+            self.history_by_thread.keys().map(|&t| (t, 0)).collect();
+        if !self.arbitrate(&mut arbitrated, &mut next_by_thread) {
+            return None;
+        }
+        Some(
+            arbitrated
+                .into_iter()
+                .map(|a| (a.op.clone(), a.ret))
+                .collect(),
+        )
+    }
+
+    /// Extends the arbitration order one operation at a time, backtracking as needed. The next
+    /// index for a thread exceeds its completed history once its in-flight operation is
+    /// arbitrated.
+    fn arbitrate<'a>(
+        &'a self,
+        arbitrated: &mut Vec<Arbitrated<'a, T, RefObj::Op, RefObj::Ret>>,
+        next_by_thread: &mut BTreeMap<T, usize>,
+    ) -> bool {
+        // In-flight operations need not be arbitrated, as they may not have taken effect.
+        let done = self
+            .history_by_thread
+            .iter()
+            .all(|(t, history)| next_by_thread[t] >= history.len());
+        if done {
+            return true;
+        }
+
+        for (&thread_id, history) in &self.history_by_thread {
+            let next = next_by_thread[&thread_id];
+            let (op, ret) = match history.get(next) {
+                Some((op, ret)) => (op, Some(ret)),
+                None if next == history.len() => match self.in_flight_by_thread.get(&thread_id) {
+                    Some(op) => (op, None),
+                    None => continue,
+                },
+                None => continue,
+            };
+            let required = self.required(arbitrated, thread_id);
+            let mut observed = BTreeSet::new();
+            let next = (thread_id, op, ret);
+            if self.observe(
+                arbitrated,
+                next_by_thread,
+                next,
+                &required,
+                &mut observed,
+                &self.init_ref_obj,
+                0,
+            ) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// The arbitrated operations that the next operation of a thread must observe.
+    fn required(
+        &self,
+        arbitrated: &[Arbitrated<T, RefObj::Op, RefObj::Ret>],
+        thread_id: T,
+    ) -> BTreeSet<usize> {
+        let mut required = BTreeSet::new();
+        for (i, earlier) in arbitrated.iter().enumerate() {
+            if earlier.thread_id != thread_id {
+                continue;
+            }
+            if self.guarantees.contains(&SessionGuarantee::ReadYourWrites) {
+                required.insert(i);
+            }
+            if self.guarantees.contains(&SessionGuarantee::MonotonicReads) {
+                required.extend(earlier.observed.iter().copied());
+            }
+        }
+        required
+    }
+
+    /// Decides whether the next operation observes each arbitrated operation in turn, starting at
+    /// `position`, then arbitrates it and continues the search. Observed operations are decided
+    /// in arbitration order, so a subset is abandoned as soon as it violates a constraint, and
+    /// the reference object for a shared prefix of the subsets is only replayed once. Observing
+    /// an operation is tried before skipping it, as histories usually observe what they can.
+    #[allow(clippy::too_many_arguments)]
+    #[allow(clippy::type_complexity)]
+    fn observe<'a>(
+        &'a self,
+        arbitrated: &mut Vec<Arbitrated<'a, T, RefObj::Op, RefObj::Ret>>,
+        next_by_thread: &mut BTreeMap<T, usize>,
+        next: (T, &'a RefObj::Op, Option<&RefObj::Ret>),
+        required: &BTreeSet<usize>,
+        observed: &mut BTreeSet<usize>,
+        ref_obj: &RefObj,
+        position: usize,
+    ) -> bool {
+        let (thread_id, op, ret) = next;
+        if position == arbitrated.len() {
+            let mut ref_obj = ref_obj.clone();
+            let ret = match ret {
+                Some(ret) if ref_obj.is_valid_step(op, ret) => ret.clone(),
+                Some(_) => return false,
+                None => ref_obj.invoke(op),
+            };
+            arbitrated.push(Arbitrated {
+                thread_id,
+                op,
+                ret,
+                observed: observed.clone(),
+            });
+            *next_by_thread.get_mut(&thread_id).unwrap() += 1;
+            if self.arbitrate(arbitrated, next_by_thread) {
+                return true;
+            }
+            *next_by_thread.get_mut(&thread_id).unwrap() -= 1;
+            arbitrated.pop();
+            return false;
+        }
+
+        // Every constraint on observing this operation concerns earlier positions, which have
+        // already been decided.
+        let candidate = &arbitrated[position];
+        let mut earlier_in_thread = arbitrated[..position]
+            .iter()
+            .enumerate()
+            .filter(|(_, earlier)| earlier.thread_id == candidate.thread_id);
+        let is_closed = (!self.transitive || candidate.observed.is_subset(observed))
+            && (!self.guarantees.contains(&SessionGuarantee::MonotonicWrites)
+                || earlier_in_thread
+                    .clone()
+                    .all(|(j, _)| observed.contains(&j)))
+            && (!self
+                .guarantees
+                .contains(&SessionGuarantee::WritesFollowReads)
+                || earlier_in_thread.all(|(_, earlier)| earlier.observed.is_subset(observed)));
+        if is_closed {
+            let mut next_ref_obj = ref_obj.clone();
+            next_ref_obj.invoke(candidate.op);
+            observed.insert(position);
+            let found = self.observe(
+                arbitrated,
+                next_by_thread,
+                next,
+                required,
+                observed,
+                &next_ref_obj,
+                position + 1,
+            );
+            observed.remove(&position);
+            if found {
+                return true;
+            }
+        }
+        !required.contains(&position)
+            && self.observe(
+                arbitrated,
+                next_by_thread,
+                next,
+                required,
+                observed,
+                ref_obj,
+                position + 1,
+            )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::semantics::register::*;
+    use crate::semantics::vec::*;
+    use crate::semantics::SequentialConsistencyTester;
+
+    #[test]
+    fn rejects_invalid_history() -> Result<(), String> {
+        assert_eq!(
+            CausalConsistencyTester::new(Register('A'))
+                .on_invoke(99, RegisterOp::Write('B'))?
+                .on_invoke(99, RegisterOp::Write('C')),
+            Err("Thread already has an operation in flight. thread_id=99, op=Write('B'), history_by_thread={99: []}".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn accepts_concurrent_writes_observed_in_different_orders() -> Result<(), String> {
+        let mut tester = CausalConsistencyTester::new(Vec::new());
+        tester
+            .on_invret(0, VecOp::Push(1), VecRet::PushOk)?
+            .on_invret(0, VecOp::Len, VecRet::LenOk(1))?
+            .on_invret(1, VecOp::Push(2), VecRet::PushOk)?
+            .on_invret(1, VecOp::Len, VecRet::LenOk(1))?;
+        assert!(tester.is_consistent());
+
+        let mut tester = SequentialConsistencyTester::new(Vec::new());
+        tester
+            .on_invret(0, VecOp::Push(1), VecRet::PushOk)?
+            .on_invret(0, VecOp::Len, VecRet::LenOk(1))?
+            .on_invret(1, VecOp::Push(2), VecRet::PushOk)?
+            .on_invret(1, VecOp::Len, VecRet::LenOk(1))?;
+        assert!(!tester.is_consistent());
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_missing_causal_dependency() -> Result<(), String> {
+        // Thread 1 pushes after observing Thread 0's push, so Thread 2 cannot pop the former
+        // without also observing the latter.
+        assert_eq!(
+            CausalConsistencyTester::new(Vec::new())
+                .on_invret(0, VecOp::Push(1), VecRet::PushOk)?
+                .on_invret(1, VecOp::Len, VecRet::LenOk(1))?
+                .on_invret(1, VecOp::Push(2), VecRet::PushOk)?
+                .on_invret(2, VecOp::Pop, VecRet::PopOk(Some(2)))?
+                .on_invret(2, VecOp::Len, VecRet::LenOk(0))?
+                .serialized_history(),
+            None
+        );
+        assert_eq!(
+            CausalConsistencyTester::new(Vec::new())
+                .on_invret(0, VecOp::Push(1), VecRet::PushOk)?
+                .on_invret(1, VecOp::Len, VecRet::LenOk(1))?
+                .on_invret(1, VecOp::Push(2), VecRet::PushOk)?
+                .on_invret(2, VecOp::Pop, VecRet::PopOk(Some(2)))?
+                .on_invret(2, VecOp::Len, VecRet::LenOk(1))?
+                .serialized_history(),
+            Some(vec![
+                (VecOp::Push(1), VecRet::PushOk),
+                (VecOp::Len, VecRet::LenOk(1)),
+                (VecOp::Push(2), VecRet::PushOk),
+                (VecOp::Pop, VecRet::PopOk(Some(2))),
+                (VecOp::Len, VecRet::LenOk(1)),
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_stale_reads_within_a_thread() -> Result<(), String> {
+        assert!(!CausalConsistencyTester::new(Register('A'))
+            .on_invret(0, RegisterOp::Write('B'), RegisterRet::WriteOk)?
+            .on_invret(0, RegisterOp::Read, RegisterRet::ReadOk('A'))?
+            .is_consistent());
+        assert!(!CausalConsistencyTester::new(Register('A'))
+            .on_invret(0, RegisterOp::Write('B'), RegisterRet::WriteOk)?
+            .on_invret(1, RegisterOp::Read, RegisterRet::ReadOk('B'))?
+            .on_invret(1, RegisterOp::Read, RegisterRet::ReadOk('A'))?
+            .is_consistent());
+        Ok(())
+    }
+
+    #[test]
+    fn checks_long_histories() -> Result<(), String> {
+        // Exceeds the 64 operations that fit in a bitmask of observed operations.
+        let mut tester = CausalConsistencyTester::new(Register(0));
+        for value in 1..=24 {
+            tester
+                .on_invret(0, RegisterOp::Write(value), RegisterRet::WriteOk)?
+                .on_invret(1, RegisterOp::Read, RegisterRet::ReadOk(value))?
+                .on_invret(2, RegisterOp::Read, RegisterRet::ReadOk(value))?;
+        }
+        assert_eq!(tester.len(), 72);
+        assert!(tester.is_consistent());
+        Ok(())
+    }
+
+    #[test]
+    fn in_flight_operations_may_be_observed() -> Result<(), String> {
+        assert!(CausalConsistencyTester::new(Register('A'))
+            .on_invoke(0, RegisterOp::Write('B'))?
+            .on_invret(1, RegisterOp::Read, RegisterRet::ReadOk('B'))?
+            .on_invret(2, RegisterOp::Read, RegisterRet::ReadOk('A'))?
+            .is_consistent());
+        Ok(())
+    }
+}
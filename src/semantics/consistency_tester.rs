@@ -6,12 +6,14 @@ use crate::semantics::SequentialSpec;
 /// a "reference sequential specification" [`SequentialSpec`]. The interface for doing so involves
 /// recording operation invocations and returns.
 ///
-/// Currently Stateright includes implementations in the form of a [`LinearizabilityTester`] and
-/// [`SequentialConsistencyTester`].
+/// Currently Stateright includes implementations in the form of a [`LinearizabilityTester`],
+/// [`SequentialConsistencyTester`], [`CausalConsistencyTester`], and [`SessionGuaranteeTester`].
 ///
 /// [consistency]: https://en.wikipedia.org/wiki/Consistency_model
 /// [`LinearizabilityTester`]: crate::semantics::LinearizabilityTester
 /// [`SequentialConsistencyTester`]: crate::semantics::SequentialConsistencyTester
+/// [`CausalConsistencyTester`]: crate::semantics::CausalConsistencyTester
+/// [`SessionGuaranteeTester`]: crate::semantics::SessionGuaranteeTester
 pub trait ConsistencyTester<T, RefObj>
 where
     RefObj: SequentialSpec,
@@ -40,4 +42,29 @@ where
     ) -> Result<&mut Self, String> {
         self.on_invoke(thread_id, op)?.on_return(thread_id, ret)
     }
+
+    /// A helper that records a complete history, such as one recorded outside of a model and
+    /// then deserialized. Returns `Ok(...)` if the history is valid, even if it is not
+    /// consistent.
+    fn on_history(
+        &mut self,
+        events: impl IntoIterator<Item = HistoryEvent<T, RefObj::Op, RefObj::Ret>>,
+    ) -> Result<&mut Self, String> {
+        for event in events {
+            match event {
+                HistoryEvent::Invoke(thread_id, op) => self.on_invoke(thread_id, op)?,
+                HistoryEvent::Return(thread_id, ret) => self.on_return(thread_id, ret)?,
+            };
+        }
+        Ok(self)
+    }
+}
+
+/// An event in a history that a [`ConsistencyTester`] records.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum HistoryEvent<ThreadId, Op, Ret> {
+    /// A thread invoked an operation.
+    Invoke(ThreadId, Op),
+    /// A thread's earlier operation invocation returned.
+    Return(ThreadId, Ret),
 }
@@ -0,0 +1,626 @@
+//! Defines and verifies the [strong eventual consistency] of replicated data types such as
+//! [CRDTs].
+//!
+//! A [`Crdt`] is replicated by embedding it in a [`Replica`], which also tracks the updates that
+//! the replica has observed, whether applied locally or merged from another replica. Replicas
+//! must satisfy two properties:
+//!
+//! 1. **Strong convergence:** replicas that have observed the same updates have equal values,
+//!    which [`is_strongly_convergent`] verifies.
+//! 2. **Eventual convergence:** once every update has reached every replica, all replicas have
+//!    equal values, which [`has_converged`] verifies.
+//!
+//! [`strong_convergence`] and [`convergence_when_quiescent`] check these properties for an
+//! [`ActorModel`] whose actor states implement [`Convergent`].
+//!
+//! Stateright includes [`GCounter`], [`PnCounter`], [`OrSet`], and [`LwwRegister`].
+//!
+//! # Example
+//!
+//! ```
+//! use stateright::{Checker, Expectation, Model};
+//! use stateright::actor::{Actor, ActorModel, Id, Network, Out};
+//! use stateright::semantics::convergence::*;
+//! use std::borrow::Cow;
+//!
+//! #[derive(Clone)]
+//! struct CounterActor { peers: Vec<Id> }
+//!
+//! impl Actor for CounterActor {
+//!     type Msg = Replica<GCounter>;
+//!     type State = Replica<GCounter>;
+//!     type Timer = ();
+//!     type Random = ();
+//!
+//!     fn on_start(&self, id: Id, o: &mut Out<Self>) -> Self::State {
+//!         let mut replica = Replica::default();
+//!         replica.update(id, &GCounterOp::Increment(1));
+//!         o.broadcast(&self.peers, &replica);
+//!         replica
+//!     }
+//!
+//!     fn on_msg(&self, _id: Id, state: &mut Cow<Self::State>, _src: Id, msg: Self::Msg,
+//!               _o: &mut Out<Self>) {
+//!         state.to_mut().merge(&msg);
+//!     }
+//! }
+//!
+//! let checker = ActorModel::new((), ())
+//!     .actor(CounterActor { peers: vec![Id::from(1), Id::from(2)] })
+//!     .actor(CounterActor { peers: vec![Id::from(0), Id::from(2)] })
+//!     .actor(CounterActor { peers: vec![Id::from(0), Id::from(1)] })
+//!     .init_network(Network::new_unordered_nonduplicating([]))
+//!     .property(Expectation::Always, "strong convergence", strong_convergence)
+//!     .property(Expectation::Always, "convergence", convergence_when_quiescent)
+//!     .checker().spawn_bfs().join();
+//! checker.assert_properties();
+//! ```
+//!
+//! [strong eventual consistency]: https://en.wikipedia.org/wiki/Eventual_consistency#Strong_eventual_consistency
+//! [CRDTs]: https://en.wikipedia.org/wiki/Conflict-free_replicated_data_type
+//! [`ActorModel`]: crate::actor::ActorModel
+
+use crate::actor::{Actor, ActorModel, ActorModelState, Id};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Debug;
+use std::hash::Hash;
+
+/// A replicated data type whose replicas can be updated independently and then merged. An
+/// implementation is a CRDT if merging is commutative, associative, and idempotent, and if
+/// applying an operation only ever advances the state relative to merging.
+pub trait Crdt: Clone {
+    /// The type of operators. Often an enum.
+    type Op;
+
+    /// The type of the observable value.
+    type Value: PartialEq;
+
+    /// Applies an operation originating at the replica with the specified [`Id`].
+    fn apply(&mut self, replica: Id, op: &Self::Op);
+
+    /// Merges the state of another replica into this one.
+    fn merge(&mut self, other: &Self);
+
+    /// The observable value of this replica.
+    fn value(&self) -> Self::Value;
+}
+
+/// The state of a replica whose convergence can be verified.
+pub trait Convergent {
+    /// The type that identifies an update.
+    type Update: Ord;
+
+    /// The type of the observable value.
+    type Value: PartialEq;
+
+    /// The updates observed by this replica.
+    fn updates(&self) -> &BTreeSet<Self::Update>;
+
+    /// The observable value of this replica.
+    fn value(&self) -> Self::Value;
+}
+
+/// Identifies an update by the replica at which it originated and a sequence number.
+pub type UpdateId = (Id, u64);
+
+/// A replica of a [`Crdt`] that tracks the updates it has observed.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Replica<T> {
+    crdt: T,
+    updates: BTreeSet<UpdateId>,
+}
+
+impl<T: Crdt> Replica<T> {
+    /// Constructs a replica with the specified initial state.
+    pub fn new(crdt: T) -> Self {
+        Replica {
+            crdt,
+            updates: BTreeSet::new(),
+        }
+    }
+
+    /// Applies an operation originating at this replica, which has the specified [`Id`].
+    pub fn update(&mut self, id: Id, op: &T::Op) {
+        let seq = self
+            .updates
+            .range((id, 0)..=(id, u64::MAX))
+            .next_back()
+            .map_or(1, |(_, seq)| seq + 1);
+        self.crdt.apply(id, op);
+        self.updates.insert((id, seq));
+    }
+
+    /// Merges the state of another replica into this one.
+    pub fn merge(&mut self, other: &Self) {
+        self.crdt.merge(&other.crdt);
+        self.updates.extend(other.updates.iter().copied());
+    }
+
+    /// The replicated state.
+    pub fn crdt(&self) -> &T {
+        &self.crdt
+    }
+}
+
+impl<T: Crdt> Convergent for Replica<T> {
+    type Update = UpdateId;
+    type Value = T::Value;
+
+    fn updates(&self) -> &BTreeSet<UpdateId> {
+        &self.updates
+    }
+
+    fn value(&self) -> T::Value {
+        self.crdt.value()
+    }
+}
+
+/// Indicates whether replicas that have observed the same updates have equal values.
+pub fn is_strongly_convergent<'a, R: Convergent + 'a>(
+    replicas: impl IntoIterator<Item = &'a R>,
+) -> bool {
+    let replicas: Vec<&R> = replicas.into_iter().collect();
+    replicas.iter().enumerate().all(|(i, r1)| {
+        replicas[i + 1..]
+            .iter()
+            .all(|r2| r1.updates() != r2.updates() || r1.value() == r2.value())
+    })
+}
+
+/// Indicates whether all replicas have equal values.
+pub fn has_converged<'a, R: Convergent + 'a>(replicas: impl IntoIterator<Item = &'a R>) -> bool {
+    let mut values = replicas.into_iter().map(Convergent::value);
+    match values.next() {
+        None => true,
+        Some(first) => values.all(|v| v == first),
+    }
+}
+
+/// An [`ActorModel`] property condition that holds if actors that have observed the same
+/// updates have equal values. Intended for [`Expectation::Always`](crate::Expectation::Always).
+pub fn strong_convergence<A, C, H>(_: &ActorModel<A, C, H>, state: &ActorModelState<A, H>) -> bool
+where
+    A: Actor,
+    A::State: Convergent,
+    H: Clone + Debug + Hash,
+{
+    is_strongly_convergent(state.actor_states.iter().map(|s| &**s))
+}
+
+/// An [`ActorModel`] property condition that holds if the running actors have equal values
+/// whenever the network is empty. Intended for
+/// [`Expectation::Always`](crate::Expectation::Always).
+///
+/// Assumes that every update is sent to every other actor. A duplicating network is never empty,
+/// so this condition is only meaningful for a nonduplicating network.
+pub fn convergence_when_quiescent<A, C, H>(
+    _: &ActorModel<A, C, H>,
+    state: &ActorModelState<A, H>,
+) -> bool
+where
+    A: Actor,
+    A::State: Convergent,
+    H: Clone + Debug + Hash,
+{
+    if state.network.len() > 0 {
+        return true;
+    }
+    let running = state
+        .actor_states
+        .iter()
+        .zip(&state.crashed)
+        .filter(|(_, crashed)| !**crashed)
+        .map(|(s, _)| &**s);
+    has_converged(running)
+}
+
+/// A grow-only counter.
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    Eq,
+    Hash,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub struct GCounter(BTreeMap<Id, u64>);
+
+/// An operation that can be invoked upon a [`GCounter`].
+#[derive(Clone, Debug, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum GCounterOp {
+    Increment(u64),
+}
+
+impl Crdt for GCounter {
+    type Op = GCounterOp;
+    type Value = u64;
+
+    fn apply(&mut self, replica: Id, op: &Self::Op) {
+        match op {
+            GCounterOp::Increment(n) => *self.0.entry(replica).or_default() += n,
+        }
+    }
+
+    fn merge(&mut self, other: &Self) {
+        for (replica, count) in &other.0 {
+            let entry = self.0.entry(*replica).or_default();
+            *entry = (*entry).max(*count);
+        }
+    }
+
+    fn value(&self) -> u64 {
+        self.0.values().sum()
+    }
+}
+
+/// A counter that can be incremented and decremented.
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    Eq,
+    Hash,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub struct PnCounter {
+    increments: GCounter,
+    decrements: GCounter,
+}
+
+/// An operation that can be invoked upon a [`PnCounter`].
+#[derive(Clone, Debug, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum PnCounterOp {
+    Increment(u64),
+    Decrement(u64),
+}
+
+impl Crdt for PnCounter {
+    type Op = PnCounterOp;
+    type Value = i64;
+
+    fn apply(&mut self, replica: Id, op: &Self::Op) {
+        match op {
+            PnCounterOp::Increment(n) => self.increments.apply(replica, &GCounterOp::Increment(*n)),
+            PnCounterOp::Decrement(n) => self.decrements.apply(replica, &GCounterOp::Increment(*n)),
+        }
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.increments.merge(&other.increments);
+        self.decrements.merge(&other.decrements);
+    }
+
+    fn value(&self) -> i64 {
+        self.increments.value() as i64 - self.decrements.value() as i64
+    }
+}
+
+/// An observed-remove set, in which an element is present if it was added more recently than
+/// it was removed at every replica that added it. Concurrent additions therefore win over removals.
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    Eq,
+    Hash,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub struct OrSet<T: Ord> {
+    elements: BTreeSet<(T, UpdateId)>,
+    tombstones: BTreeSet<UpdateId>,
+}
+
+/// An operation that can be invoked upon an [`OrSet`].
+#[derive(Clone, Debug, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum OrSetOp<T> {
+    Insert(T),
+    Remove(T),
+}
+
+impl<T: Clone + Ord> Crdt for OrSet<T> {
+    type Op = OrSetOp<T>;
+    type Value = BTreeSet<T>;
+
+    fn apply(&mut self, replica: Id, op: &Self::Op) {
+        match op {
+            OrSetOp::Insert(element) => {
+                // Tags are unique as each replica numbers its own insertions.
+                let seq = self
+                    .elements
+                    .iter()
+                    .map(|(_, tag)| tag)
+                    .chain(&self.tombstones)
+                    .filter(|(id, _)| *id == replica)
+                    .map(|(_, seq)| seq + 1)
+                    .max()
+                    .unwrap_or(1);
+                self.elements.insert((element.clone(), (replica, seq)));
+            }
+            OrSetOp::Remove(element) => {
+                let observed: Vec<_> = self
+                    .elements
+                    .iter()
+                    .filter(|(e, _)| e == element)
+                    .cloned()
+                    .collect();
+                for (e, tag) in observed {
+                    self.elements.remove(&(e, tag));
+                    self.tombstones.insert(tag);
+                }
+            }
+        }
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.tombstones.extend(other.tombstones.iter().copied());
+        self.elements.extend(other.elements.iter().cloned());
+        let tombstones = &self.tombstones;
+        self.elements.retain(|(_, tag)| !tombstones.contains(tag));
+    }
+
+    fn value(&self) -> BTreeSet<T> {
+        self.elements.iter().map(|(e, _)| e.clone()).collect()
+    }
+}
+
+/// A last-writer-wins register, in which the write with the greatest timestamp wins, with ties
+/// broken by the [`Id`] of the writing replica.
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    Eq,
+    Hash,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub struct LwwRegister<T> {
+    value: Option<T>,
+    timestamp: (u64, Id),
+}
+
+/// An operation that can be invoked upon an [`LwwRegister`].
+#[derive(Clone, Debug, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum LwwRegisterOp<T> {
+    /// Writes a value with a timestamp, such as from the writing replica's clock.
+    Write(T, u64),
+}
+
+impl<T: Clone + PartialEq> Crdt for LwwRegister<T> {
+    type Op = LwwRegisterOp<T>;
+    type Value = Option<T>;
+
+    fn apply(&mut self, replica: Id, op: &Self::Op) {
+        match op {
+            LwwRegisterOp::Write(value, timestamp) => {
+                if (*timestamp, replica) > self.timestamp {
+                    self.value = Some(value.clone());
+                    self.timestamp = (*timestamp, replica);
+                }
+            }
+        }
+    }
+
+    fn merge(&mut self, other: &Self) {
+        if other.timestamp > self.timestamp {
+            self.value = other.value.clone();
+            self.timestamp = other.timestamp;
+        }
+    }
+
+    fn value(&self) -> Option<T> {
+        self.value.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::actor::{Network, Out};
+    use crate::{Checker, Expectation, Model};
+    use std::borrow::Cow;
+
+    /// Applies each replica's operations locally and then merges the replicas in every order.
+    fn assert_converges<T>(ops: Vec<Vec<T::Op>>)
+    where
+        T: Crdt + Debug + Default,
+        T::Value: Debug,
+    {
+        let replicas: Vec<Replica<T>> = ops
+            .iter()
+            .enumerate()
+            .map(|(i, ops)| {
+                let mut replica = Replica::default();
+                for op in ops {
+                    replica.update(Id::from(i), op);
+                }
+                replica
+            })
+            .collect();
+        let mut merged = Vec::new();
+        for first in &replicas {
+            for second in &replicas {
+                let mut replica = first.clone();
+                replica.merge(second);
+                replica.merge(first); // idempotent
+                for third in &replicas {
+                    replica.merge(third);
+                }
+                merged.push(replica);
+            }
+        }
+        assert!(is_strongly_convergent(&merged));
+        assert!(has_converged(&merged), "{:?}", merged);
+    }
+
+    #[test]
+    fn counters_converge() {
+        assert_converges::<GCounter>(vec![
+            vec![GCounterOp::Increment(1), GCounterOp::Increment(2)],
+            vec![GCounterOp::Increment(4)],
+        ]);
+        let mut replica = Replica::<GCounter>::default();
+        replica.update(Id::from(0), &GCounterOp::Increment(1));
+        replica.update(Id::from(1), &GCounterOp::Increment(2));
+        assert_eq!(replica.value(), 3);
+
+        assert_converges::<PnCounter>(vec![
+            vec![PnCounterOp::Increment(3), PnCounterOp::Decrement(1)],
+            vec![PnCounterOp::Decrement(5)],
+        ]);
+        let mut replica = Replica::<PnCounter>::default();
+        replica.update(Id::from(0), &PnCounterOp::Increment(1));
+        replica.update(Id::from(0), &PnCounterOp::Decrement(3));
+        assert_eq!(replica.value(), -2);
+        assert_eq!(
+            replica.updates().iter().copied().collect::<Vec<_>>(),
+            vec![(Id::from(0), 1), (Id::from(0), 2)]
+        );
+    }
+
+    #[test]
+    fn or_set_converges_with_concurrent_insert_winning() {
+        use OrSetOp::*;
+        assert_converges::<OrSet<char>>(vec![
+            vec![Insert('a'), Insert('b'), Remove('a')],
+            vec![Insert('a'), Remove('b')],
+        ]);
+
+        let mut r0 = Replica::<OrSet<char>>::default();
+        r0.update(Id::from(0), &Insert('a'));
+        let mut r1 = r0.clone();
+        r0.update(Id::from(0), &Remove('a'));
+        r1.update(Id::from(1), &Insert('a'));
+        r0.merge(&r1);
+        assert_eq!(r0.value(), BTreeSet::from(['a']));
+        r0.update(Id::from(0), &Remove('a'));
+        r1.merge(&r0);
+        assert_eq!(r1.value(), BTreeSet::new());
+    }
+
+    #[test]
+    fn lww_register_converges_to_latest_write() {
+        use LwwRegisterOp::*;
+        assert_converges::<LwwRegister<char>>(vec![
+            vec![Write('a', 1), Write('b', 3)],
+            vec![Write('c', 3)],
+            vec![Write('d', 2)],
+        ]);
+
+        let mut r0 = Replica::<LwwRegister<char>>::default();
+        let mut r1 = r0.clone();
+        r0.update(Id::from(0), &Write('a', 2));
+        r1.update(Id::from(1), &Write('b', 1));
+        r1.merge(&r0);
+        assert_eq!(r1.value(), Some('a'));
+    }
+
+    /// Merges by adopting the other replica's state, which is not commutative.
+    #[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+    struct Overwrite(u64);
+
+    impl Crdt for Overwrite {
+        type Op = u64;
+        type Value = u64;
+        fn apply(&mut self, _replica: Id, op: &u64) {
+            self.0 = *op;
+        }
+        fn merge(&mut self, other: &Self) {
+            self.0 = other.0;
+        }
+        fn value(&self) -> u64 {
+            self.0
+        }
+    }
+
+    #[derive(Clone)]
+    struct ReplicaActor<T: Crdt> {
+        op: T::Op,
+        peers: Vec<Id>,
+    }
+
+    impl<T> Actor for ReplicaActor<T>
+    where
+        T: Crdt + Debug + Default + Eq + Hash,
+        T::Op: Clone,
+    {
+        type Msg = Replica<T>;
+        type State = Replica<T>;
+        type Timer = ();
+        type Random = ();
+
+        fn on_start(&self, id: Id, o: &mut Out<Self>) -> Self::State {
+            let mut replica = Replica::default();
+            replica.update(id, &self.op);
+            o.broadcast(&self.peers, &replica);
+            replica
+        }
+
+        fn on_msg(
+            &self,
+            _id: Id,
+            state: &mut Cow<Self::State>,
+            _src: Id,
+            msg: Self::Msg,
+            _o: &mut Out<Self>,
+        ) {
+            state.to_mut().merge(&msg);
+        }
+    }
+
+    fn model<T>(ops: [T::Op; 3]) -> ActorModel<ReplicaActor<T>>
+    where
+        T: Crdt + Debug + Default + Eq + Hash,
+        T::Op: Clone,
+    {
+        let ids: Vec<Id> = (0..3).map(Id::from).collect();
+        ActorModel::new((), ())
+            .actors(ops.into_iter().enumerate().map(|(i, op)| ReplicaActor {
+                op,
+                peers: ids.iter().copied().filter(|id| *id != ids[i]).collect(),
+            }))
+            .init_network(Network::new_unordered_nonduplicating([]))
+            .property(
+                Expectation::Always,
+                "strong convergence",
+                strong_convergence,
+            )
+            .property(
+                Expectation::Always,
+                "convergence",
+                convergence_when_quiescent,
+            )
+    }
+
+    #[test]
+    fn can_check_actor_convergence() {
+        model::<GCounter>([
+            GCounterOp::Increment(1),
+            GCounterOp::Increment(2),
+            GCounterOp::Increment(3),
+        ])
+        .checker()
+        .spawn_bfs()
+        .join()
+        .assert_properties();
+
+        let checker = model::<Overwrite>([1, 2, 3]).checker().spawn_bfs().join();
+        assert!(checker.discovery("strong convergence").is_some());
+        assert!(checker.discovery("convergence").is_some());
+    }
+}
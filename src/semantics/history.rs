@@ -0,0 +1,474 @@
+//! Checks histories recorded outside of a model, such as by a test harness exercising a
+//! production system, using a [`ConsistencyTester`].
+//!
+//! # History Format
+//!
+//! A [`History`] is a sequence of [`Event`]s following the conventions of [Jepsen]. It can be
+//! deserialized from JSON with [`History::from_json`], either as an array of events or as one
+//! event per line ([JSON Lines]). Each event is an object with a `type` and a `process`, which
+//! identifies the client (or thread) that performed an operation:
+//!
+//! - `invoke`: The process invoked the operation in the `op` field.
+//! - `ok`: The process's operation completed and returned the value in the `ret` field.
+//! - `fail`: The process's operation definitely did not take effect, so it is omitted.
+//! - `info`: The outcome of the process's operation is indeterminate, such as after a timeout.
+//!   The operation may or may not take effect at any point after it was invoked. Later
+//!   operations of the same process are treated as if from a new process, as the indeterminate
+//!   operation never returns.
+//!
+//! The `op` and `ret` fields use the [serde] representation of the [`SequentialSpec::Op`] and
+//! [`SequentialSpec::Ret`] types, and other fields (such as timestamps) are ignored. For example,
+//! the following history of a [`Register`] is linearizable only because the read that timed out
+//! may have taken effect:
+//!
+//! ```json
+//! {"type": "invoke", "process": 0, "op": {"Write": "A"}}
+//! {"type": "invoke", "process": 1, "op": "Read"}
+//! {"type": "ok", "process": 0, "ret": "WriteOk"}
+//! {"type": "info", "process": 1}
+//! {"type": "invoke", "process": 1, "op": "Read"}
+//! {"type": "ok", "process": 1, "ret": {"ReadOk": "A"}}
+//! ```
+//!
+//! # Example
+//!
+//! ```
+//! use stateright::semantics::LinearizabilityTester;
+//! use stateright::semantics::history::History;
+//! use stateright::semantics::register::{Register, RegisterOp, RegisterRet};
+//!
+//! let history: History<u64, RegisterOp<char>, RegisterRet<char>> = History::from_json(r#"
+//!     {"type": "invoke", "process": 0, "op": {"Write": "B"}}
+//!     {"type": "ok", "process": 0, "ret": "WriteOk"}
+//!     {"type": "invoke", "process": 1, "op": {"Write": "C"}}
+//!     {"type": "fail", "process": 1}
+//!     {"type": "invoke", "process": 2, "op": "Read"}
+//!     {"type": "ok", "process": 2, "ret": {"ReadOk": "A"}}
+//! "#).unwrap();
+//! let tester = LinearizabilityTester::new(Register('A'));
+//! assert_eq!(history.is_consistent(tester.clone()), Ok(false));
+//!
+//! // The failed write is irrelevant to the anomaly.
+//! let minimal = history.minimal_inconsistent_subhistory(tester).unwrap().unwrap();
+//! assert_eq!(minimal.0.len(), 4);
+//! ```
+//!
+//! [Jepsen]: https://github.com/jepsen-io/jepsen
+//! [JSON Lines]: https://jsonlines.org/
+//! [serde]: https://serde.rs/
+//! [`Register`]: crate::semantics::register::Register
+
+use crate::semantics::{ConsistencyTester, HistoryEvent, SequentialSpec};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Debug;
+
+/// An event in a [`History`]. See the [module documentation](self) for the serialized format.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Event<P, Op, Ret> {
+    /// A process invoked an operation.
+    Invoke { process: P, op: Op },
+    /// A process's operation completed with a return value.
+    Ok { process: P, ret: Ret },
+    /// A process's operation did not take effect.
+    Fail { process: P },
+    /// A process's operation may or may not take effect.
+    Info { process: P },
+}
+
+impl<P, Op, Ret> Event<P, Op, Ret> {
+    /// The process that performed the operation.
+    pub fn process(&self) -> &P {
+        match self {
+            Event::Invoke { process, .. }
+            | Event::Ok { process, .. }
+            | Event::Fail { process }
+            | Event::Info { process } => process,
+        }
+    }
+}
+
+/// A history of operations performed by processes. See the [module documentation](self).
+///
+/// A [`ConsistencyTester`] for a history identifies threads by a process and the number of
+/// indeterminate operations that process performed earlier, so for example a process `7` is
+/// thread `(7, 0)` until its first indeterminate operation and then thread `(7, 1)`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct History<P, Op, Ret>(pub Vec<Event<P, Op, Ret>>);
+
+impl<P, Op, Ret> History<P, Op, Ret>
+where
+    P: Copy + Debug + Ord,
+    Op: Clone,
+    Ret: Clone,
+{
+    /// Deserializes a history from either a JSON array of events or JSON Lines.
+    pub fn from_json(json: &str) -> Result<Self, String>
+    where
+        Self: serde::de::DeserializeOwned,
+        Event<P, Op, Ret>: serde::de::DeserializeOwned,
+    {
+        if json.trim_start().starts_with('[') {
+            return serde_json::from_str(json).map_err(|err| err.to_string());
+        }
+        let mut events = Vec::new();
+        for (i, line) in json.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let event = serde_json::from_str(line)
+                .map_err(|err| format!("Invalid event on line {}: {}", i + 1, err))?;
+            events.push(event);
+        }
+        Ok(History(events))
+    }
+
+    /// Converts this history into the events that a [`ConsistencyTester`] records, omitting
+    /// failed operations and leaving indeterminate operations in flight.
+    #[allow(clippy::type_complexity)]
+    pub fn tester_events(&self) -> Result<Vec<HistoryEvent<(P, usize), Op, Ret>>, String> {
+        let completions = self.completions()?;
+        let mut incarnations: BTreeMap<P, usize> = BTreeMap::new();
+        let mut events = Vec::new();
+        for (i, event) in self.0.iter().enumerate() {
+            let process = *event.process();
+            let incarnation = incarnations.entry(process).or_default();
+            match event {
+                Event::Invoke { op, .. } => {
+                    let failed = matches!(
+                        completions.get(&i).map(|&c| &self.0[c]),
+                        Some(Event::Fail { .. })
+                    );
+                    if !failed {
+                        events.push(HistoryEvent::Invoke((process, *incarnation), op.clone()));
+                    }
+                }
+                Event::Ok { ret, .. } => {
+                    events.push(HistoryEvent::Return((process, *incarnation), ret.clone()));
+                }
+                Event::Fail { .. } => {}
+                Event::Info { .. } => *incarnation += 1,
+            }
+        }
+        Ok(events)
+    }
+
+    /// Indicates whether this history is consistent according to a [`ConsistencyTester`] that
+    /// has not recorded any operations. Returns `Err(...)` if the history is invalid, such as if
+    /// a process invokes an operation while another is in flight.
+    pub fn is_consistent<RefObj, C>(&self, mut tester: C) -> Result<bool, String>
+    where
+        RefObj: SequentialSpec<Op = Op, Ret = Ret>,
+        C: ConsistencyTester<(P, usize), RefObj>,
+    {
+        tester.on_history(self.tester_events()?)?;
+        Ok(tester.is_consistent())
+    }
+
+    /// Returns a subhistory that is inconsistent according to a [`ConsistencyTester`] that has
+    /// not recorded any operations, or `None` if this history is consistent. The subhistory is
+    /// minimal in that removing any one of its operations would make it consistent, and failed
+    /// operations are omitted.
+    pub fn minimal_inconsistent_subhistory<RefObj, C>(
+        &self,
+        tester: C,
+    ) -> Result<Option<Self>, String>
+    where
+        RefObj: SequentialSpec<Op = Op, Ret = Ret>,
+        C: Clone + ConsistencyTester<(P, usize), RefObj>,
+    {
+        if self.is_consistent(tester.clone())? {
+            return Ok(None);
+        }
+
+        // Each operation is identified by the index of its invocation, and the events of
+        // removed operations are omitted.
+        let completions = self.completions()?;
+        let operation_of: BTreeMap<usize, usize> = completions
+            .iter()
+            .map(|(&invoke, &completion)| (completion, invoke))
+            .collect();
+        let operation = |i: usize| *operation_of.get(&i).unwrap_or(&i);
+        let subhistory = |removed: &BTreeSet<usize>, len: usize| {
+            History(
+                self.0[..len]
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| !removed.contains(&operation(*i)))
+                    .map(|(_, e)| e.clone())
+                    .collect::<Vec<_>>(),
+            )
+        };
+        let mut removed: BTreeSet<usize> = completions
+            .iter()
+            .filter(|(_, &c)| matches!(self.0[c], Event::Fail { .. }))
+            .map(|(&invoke, _)| invoke)
+            .collect();
+
+        // Consistency is prefix closed, so the shortest inconsistent prefix can be found via
+        // binary search.
+        let (mut consistent_len, mut len) = (0, self.0.len());
+        while consistent_len + 1 < len {
+            let mid = (consistent_len + len) / 2;
+            if subhistory(&removed, mid).is_consistent(tester.clone())? {
+                consistent_len = mid;
+            } else {
+                len = mid;
+            }
+        }
+
+        // Removing later operations first tends to preserve the earlier operations that they
+        // observed, which yields a more intuitive explanation. Removing an operation can make
+        // one that was needed earlier redundant, so passes repeat until none can be removed.
+        let mut is_minimal = false;
+        while !is_minimal {
+            is_minimal = true;
+            for (i, event) in self.0[..len].iter().enumerate().rev() {
+                if !matches!(event, Event::Invoke { .. }) || removed.contains(&i) {
+                    continue;
+                }
+                removed.insert(i);
+                if subhistory(&removed, len).is_consistent(tester.clone())? {
+                    removed.remove(&i);
+                } else {
+                    is_minimal = false;
+                }
+            }
+        }
+        let mut minimal = subhistory(&removed, len);
+        // Completions of failed operations are not needed to explain the anomaly.
+        minimal.0.retain(|e| !matches!(e, Event::Fail { .. }));
+        Ok(Some(minimal))
+    }
+
+    /// Maps the index of each invocation to the index of its completion, if any.
+    fn completions(&self) -> Result<BTreeMap<usize, usize>, String> {
+        let mut in_flight: BTreeMap<P, usize> = BTreeMap::new();
+        let mut completions = BTreeMap::new();
+        for (i, event) in self.0.iter().enumerate() {
+            let process = *event.process();
+            match event {
+                Event::Invoke { .. } => {
+                    if let Some(earlier) = in_flight.insert(process, i) {
+                        return Err(format!(
+                            "Process already has an operation in flight. process={:?}, \
+                             earlier_index={}, index={}",
+                            process, earlier, i
+                        ));
+                    }
+                }
+                Event::Ok { .. } | Event::Fail { .. } | Event::Info { .. } => {
+                    match in_flight.remove(&process) {
+                        Some(invoke) => completions.insert(invoke, i),
+                        None => {
+                            return Err(format!(
+                                "There is no in-flight invocation for this process. \
+                                 process={:?}, index={}",
+                                process, i
+                            ));
+                        }
+                    };
+                }
+            }
+        }
+        Ok(completions)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::semantics::register::*;
+    use crate::semantics::vec::*;
+    use crate::semantics::{LinearizabilityTester, SequentialConsistencyTester};
+
+    type RegisterHistory = History<u64, RegisterOp<char>, RegisterRet<char>>;
+
+    #[test]
+    fn deserializes_json_arrays_and_lines() {
+        let expected = History(vec![
+            Event::Invoke {
+                process: 0,
+                op: RegisterOp::Write('B'),
+            },
+            Event::Ok {
+                process: 0,
+                ret: RegisterRet::WriteOk,
+            },
+            Event::Invoke {
+                process: 1,
+                op: RegisterOp::Read,
+            },
+            Event::Info { process: 1 },
+        ]);
+        assert_eq!(
+            RegisterHistory::from_json(
+                r#"[{"type": "invoke", "process": 0, "op": {"Write": "B"}, "time": 12},
+                    {"type": "ok", "process": 0, "ret": "WriteOk"},
+                    {"type": "invoke", "process": 1, "op": "Read"},
+                    {"type": "info", "process": 1}]"#
+            ),
+            Ok(expected.clone())
+        );
+        assert_eq!(
+            RegisterHistory::from_json(
+                r#"
+                {"type": "invoke", "process": 0, "op": {"Write": "B"}, "time": 12}
+                {"type": "ok", "process": 0, "ret": "WriteOk"}
+                {"type": "invoke", "process": 1, "op": "Read"}
+                {"type": "info", "process": 1}
+                "#
+            ),
+            Ok(expected)
+        );
+        assert!(
+            RegisterHistory::from_json(r#"{"type": "done", "process": 0}"#)
+                .unwrap_err()
+                .starts_with("Invalid event on line 1")
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_history() {
+        let history = RegisterHistory::from_json(
+            r#"
+            {"type": "invoke", "process": 0, "op": "Read"}
+            {"type": "invoke", "process": 0, "op": "Read"}
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            history.is_consistent(LinearizabilityTester::new(Register('A'))),
+            Err("Process already has an operation in flight. process=0, \
+                 earlier_index=0, index=1"
+                .to_string())
+        );
+    }
+
+    #[test]
+    fn handles_failed_and_indeterminate_operations() {
+        // The failed write cannot explain the read, but the indeterminate write can, even
+        // though its process continued.
+        let history = RegisterHistory::from_json(
+            r#"
+            {"type": "invoke", "process": 0, "op": {"Write": "B"}}
+            {"type": "fail", "process": 0}
+            {"type": "invoke", "process": 1, "op": {"Write": "C"}}
+            {"type": "info", "process": 1}
+            {"type": "invoke", "process": 1, "op": "Read"}
+            {"type": "ok", "process": 1, "ret": {"ReadOk": "A"}}
+            {"type": "invoke", "process": 2, "op": "Read"}
+            {"type": "ok", "process": 2, "ret": {"ReadOk": "C"}}
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            history.tester_events(),
+            Ok(vec![
+                HistoryEvent::Invoke((1, 0), RegisterOp::Write('C')),
+                HistoryEvent::Invoke((1, 1), RegisterOp::Read),
+                HistoryEvent::Return((1, 1), RegisterRet::ReadOk('A')),
+                HistoryEvent::Invoke((2, 0), RegisterOp::Read),
+                HistoryEvent::Return((2, 0), RegisterRet::ReadOk('C')),
+            ])
+        );
+        assert_eq!(
+            history.is_consistent(LinearizabilityTester::new(Register('A'))),
+            Ok(true)
+        );
+
+        let mut history = history;
+        history.0.push(Event::Invoke {
+            process: 2,
+            op: RegisterOp::Read,
+        });
+        history.0.push(Event::Ok {
+            process: 2,
+            ret: RegisterRet::ReadOk('B'),
+        });
+        assert_eq!(
+            history.is_consistent(LinearizabilityTester::new(Register('A'))),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn reports_minimal_inconsistent_subhistory() {
+        // Only the first write and the later read are needed to explain the stale read.
+        let history = RegisterHistory::from_json(
+            r#"
+            {"type": "invoke", "process": 0, "op": {"Write": "B"}}
+            {"type": "invoke", "process": 2, "op": "Read"}
+            {"type": "ok", "process": 0, "ret": "WriteOk"}
+            {"type": "invoke", "process": 3, "op": {"Write": "C"}}
+            {"type": "ok", "process": 2, "ret": {"ReadOk": "B"}}
+            {"type": "fail", "process": 3}
+            {"type": "invoke", "process": 1, "op": "Read"}
+            {"type": "ok", "process": 1, "ret": {"ReadOk": "A"}}
+            {"type": "invoke", "process": 0, "op": "Read"}
+            {"type": "ok", "process": 0, "ret": {"ReadOk": "B"}}
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            history.minimal_inconsistent_subhistory(LinearizabilityTester::new(Register('A'))),
+            Ok(Some(History(vec![
+                Event::Invoke {
+                    process: 0,
+                    op: RegisterOp::Write('B'),
+                },
+                Event::Ok {
+                    process: 0,
+                    ret: RegisterRet::WriteOk,
+                },
+                Event::Invoke {
+                    process: 1,
+                    op: RegisterOp::Read,
+                },
+                Event::Ok {
+                    process: 1,
+                    ret: RegisterRet::ReadOk('A'),
+                },
+            ])))
+        );
+        assert_eq!(
+            history
+                .minimal_inconsistent_subhistory(SequentialConsistencyTester::new(Register('A'))),
+            Ok(None)
+        );
+
+        let history: History<u64, VecOp<u8>, VecRet<u8>> = History::from_json(
+            r#"
+            {"type": "invoke", "process": 0, "op": {"Push": 1}}
+            {"type": "ok", "process": 0, "ret": "PushOk"}
+            {"type": "invoke", "process": 1, "op": {"Push": 2}}
+            {"type": "ok", "process": 1, "ret": "PushOk"}
+            {"type": "invoke", "process": 0, "op": "Len"}
+            {"type": "ok", "process": 0, "ret": {"LenOk": 0}}
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            history.minimal_inconsistent_subhistory(SequentialConsistencyTester::new(Vec::new())),
+            Ok(Some(History(vec![
+                Event::Invoke {
+                    process: 0,
+                    op: VecOp::Push(1),
+                },
+                Event::Ok {
+                    process: 0,
+                    ret: VecRet::PushOk,
+                },
+                Event::Invoke {
+                    process: 0,
+                    op: VecOp::Len,
+                },
+                Event::Ok {
+                    process: 0,
+                    ret: VecRet::LenOk(0),
+                },
+            ])))
+        );
+    }
+}
@@ -0,0 +1,725 @@
+//! Private module for selective re-export. See [`IsolationTester`].
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt::{self, Debug, Display, Formatter};
+
+// The tester follows Adya's formalism ("Weak Consistency: A Generalized Theory and Optimistic
+// Implementations for Distributed Transactions") in the style of Elle: committed transactions
+// are the vertices of a direct serialization graph whose edges are write-write (ww),
+// write-read (wr), and read-write anti-dependencies (rw), and each isolation level forbids
+// certain kinds of cycles. The graph depends on the order in which the versions of each key
+// were installed, which a history of reads and writes only partially reveals, so the orders
+// consistent with the reads are searched and a history is accepted if any of them avoids the
+// forbidden cycles. Real-time order is not used to narrow the search, as none of the isolation
+// levels constrain it.
+
+/// An [isolation level] that an [`IsolationTester`] verifies, from weakest to strongest.
+///
+/// [isolation level]: https://en.wikipedia.org/wiki/Isolation_(database_systems)
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, serde::Serialize)]
+pub enum IsolationLevel {
+    /// Forbids [`Anomaly::G0`], [`Anomaly::G1a`], [`Anomaly::G1b`], and [`Anomaly::G1c`]
+    /// (Adya's PL-2).
+    ReadCommitted,
+    /// Additionally forbids [`Anomaly::LostUpdate`], [`Anomaly::GSingle`], and any other cycle
+    /// that lacks two consecutive anti-dependencies, but permits write skew.
+    SnapshotIsolation,
+    /// Forbids every dependency cycle (Adya's PL-3).
+    Serializable,
+}
+
+/// An operation within a transaction.
+#[derive(
+    Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, serde::Serialize, serde::Deserialize,
+)]
+pub enum TxnOp<K, V> {
+    /// Reads a key, observing either the value of a write or `None` if the key was never
+    /// written.
+    Read(K, Option<V>),
+    /// Writes a value to a key.
+    Write(K, V),
+}
+
+/// A kind of anomaly that an [`IsolationTester`] can witness, from most to least severe.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, serde::Serialize)]
+pub enum Anomaly {
+    /// A transaction read a key without observing its own earlier write, or observed its own
+    /// later write.
+    Internal,
+    /// A transaction read a value written by an aborted transaction or never written at all.
+    G1a,
+    /// A transaction read a value that its writer overwrote before committing.
+    G1b,
+    /// Transactions overwrote each other's writes in a cycle.
+    G0,
+    /// Transactions observed or overwrote each other's writes in a cycle.
+    G1c,
+    /// Two transactions read the same version of a key and then both wrote that key.
+    LostUpdate,
+    /// A dependency cycle with exactly one anti-dependency, such as a read skew.
+    GSingle,
+    /// A dependency cycle with multiple anti-dependencies, such as a write skew.
+    G2,
+}
+
+impl Display for Anomaly {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Anomaly::Internal => "internal inconsistency",
+            Anomaly::G1a => "G1a (aborted read)",
+            Anomaly::G1b => "G1b (intermediate read)",
+            Anomaly::G0 => "G0 (write cycle)",
+            Anomaly::G1c => "G1c (circular information flow)",
+            Anomaly::LostUpdate => "lost update",
+            Anomaly::GSingle => "G-single (read skew)",
+            Anomaly::G2 => "G2 (anti-dependency cycle)",
+        })
+    }
+}
+
+/// An [`Anomaly`] witnessed by an [`IsolationTester`] along with an explanation that refers to
+/// committed transactions as `T0`, `T1`, etc. in the order they were recorded.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, serde::Serialize)]
+pub struct IsolationViolation {
+    pub anomaly: Anomaly,
+    pub explanation: String,
+}
+
+impl Display for IsolationViolation {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.anomaly, self.explanation)
+    }
+}
+
+/// This tester captures a history of transactions, each made of reads and writes over keys, and
+/// validates that it satisfies an [`IsolationLevel`] by searching the dependencies between
+/// committed transactions for forbidden cycles.
+///
+/// Values written to a key are assumed to be unique, so that every read identifies the write it
+/// observed. Transactions are recorded once their outcome is known. A transaction whose outcome
+/// is unknown can be recorded as committed if its writes may have been observed.
+///
+/// The search considers the orders in which the writes to each key could have been installed,
+/// abandoning an order as soon as the writes ordered so far form a forbidden cycle. Keys that
+/// many transactions write without reading can still require exponential time, so the tester
+/// is intended for the small histories typical of model checking.
+///
+/// # Example
+///
+/// ```
+/// use stateright::semantics::*;
+/// use stateright::semantics::TxnOp::*;
+///
+/// // Write skew: each transaction reads the key that the other writes.
+/// let mut tester = IsolationTester::new(IsolationLevel::SnapshotIsolation);
+/// tester
+///     .on_commit(0, vec![Read('x', None), Write('y', 1)])
+///     .on_commit(1, vec![Read('y', None), Write('x', 2)]);
+/// assert!(tester.is_consistent());
+///
+/// tester.level(IsolationLevel::Serializable);
+/// assert_eq!(tester.anomaly().unwrap().anomaly, Anomaly::G2);
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq, serde::Serialize)]
+pub struct IsolationTester<ThreadId, K, V> {
+    level: IsolationLevel,
+    committed: Vec<(ThreadId, Vec<TxnOp<K, V>>)>,
+    aborted: Vec<(ThreadId, Vec<TxnOp<K, V>>)>,
+}
+
+/// A kind of dependency between committed transactions.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Dep {
+    Ww,
+    Wr,
+    Rw,
+}
+
+/// A read that observed a version other than the reading transaction's own.
+struct ExternalRead<'a, K> {
+    txn: usize,
+    key: &'a K,
+    /// The transaction that installed the observed version, or `None` for the initial version.
+    writer: Option<usize>,
+}
+
+/// A dependency from one committed transaction to another via a key.
+type Edge<'a, K> = (usize, Dep, usize, &'a K);
+
+/// The committed transactions that wrote a key, and pairs of them whose versions are known to
+/// have been installed in that order.
+type Writers = (Vec<usize>, BTreeSet<(usize, usize)>);
+
+#[allow(clippy::len_without_is_empty)] // no use case for an emptiness check
+impl<T, K, V> IsolationTester<T, K, V> {
+    /// Constructs an [`IsolationTester`] that verifies an [`IsolationLevel`].
+    pub fn new(level: IsolationLevel) -> Self {
+        Self {
+            level,
+            committed: Vec::new(),
+            aborted: Vec::new(),
+        }
+    }
+
+    /// Changes the [`IsolationLevel`] to verify.
+    pub fn level(&mut self, level: IsolationLevel) -> &mut Self {
+        self.level = level;
+        self
+    }
+
+    /// Indicates that a thread committed a transaction comprising the specified operations.
+    pub fn on_commit(&mut self, thread_id: T, ops: Vec<TxnOp<K, V>>) -> &mut Self {
+        self.committed.push((thread_id, ops));
+        self
+    }
+
+    /// Indicates that a thread aborted a transaction comprising the specified operations.
+    pub fn on_abort(&mut self, thread_id: T, ops: Vec<TxnOp<K, V>>) -> &mut Self {
+        self.aborted.push((thread_id, ops));
+        self
+    }
+
+    /// Indicates the aggregate number of transactions committed or aborted.
+    pub fn len(&self) -> usize {
+        self.committed.len() + self.aborted.len()
+    }
+}
+
+impl<T, K, V> IsolationTester<T, K, V>
+where
+    T: Debug,
+    K: Debug + Ord,
+    V: Debug + Ord,
+{
+    /// Indicates whether the recorded history satisfies the isolation level.
+    pub fn is_consistent(&self) -> bool {
+        self.anomaly().is_none()
+    }
+
+    /// Returns an anomaly that the isolation level forbids if the recorded history exhibits one.
+    /// If every version order exhibits an anomaly, the first one found is returned, so a less
+    /// severe anomaly may also be present.
+    pub fn anomaly(&self) -> Option<IsolationViolation> {
+        let reads = match self.external_reads() {
+            Ok(reads) => reads,
+            Err(violation) => return Some(violation),
+        };
+        if self.level >= IsolationLevel::SnapshotIsolation {
+            if let Some(violation) = self.lost_update(&reads) {
+                return Some(violation);
+            }
+        }
+
+        // A transaction that read a version of a key before overwriting it must have installed
+        // its write after that version, as any other order has it observe a version installed
+        // after its own, which is a G1c cycle at every level.
+        let mut keys: BTreeMap<&K, Writers> = BTreeMap::new();
+        for (txn, (_, ops)) in self.committed.iter().enumerate() {
+            for op in ops {
+                if let TxnOp::Write(key, _) = op {
+                    let (writers, _) = keys.entry(key).or_default();
+                    if writers.last() != Some(&txn) {
+                        writers.push(txn);
+                    }
+                }
+            }
+        }
+        for read in &reads {
+            if let (Some(writer), Some((writers, preceding))) =
+                (read.writer, keys.get_mut(read.key))
+            {
+                if writers.contains(&read.txn) {
+                    preceding.insert((writer, read.txn));
+                }
+            }
+        }
+        // Keys with fewer writers are ordered first, so that their dependencies can rule out
+        // orders of the others early.
+        let mut keys: Vec<_> = keys
+            .into_iter()
+            .map(|(key, (writers, preceding))| (key, writers, preceding))
+            .collect();
+        keys.sort_by_key(|(_, writers, _)| writers.len());
+
+        let mut first_violation = None;
+        if self.order_versions(&reads, &keys, &mut BTreeMap::new(), &mut first_violation) {
+            None
+        } else {
+            first_violation
+        }
+    }
+
+    /// Extends the version orders one write at a time, backtracking as soon as the dependencies
+    /// that they determine contain a forbidden cycle, since further writes only add dependencies.
+    /// Returns whether every key was ordered without one, and otherwise records the first
+    /// violation found.
+    #[allow(clippy::type_complexity)]
+    fn order_versions<'a>(
+        &'a self,
+        reads: &[ExternalRead<'a, K>],
+        keys: &[(&'a K, Vec<usize>, BTreeSet<(usize, usize)>)],
+        orders: &mut BTreeMap<&'a K, Vec<usize>>,
+        first_violation: &mut Option<IsolationViolation>,
+    ) -> bool {
+        let next_key = keys
+            .iter()
+            .find(|(key, writers, _)| orders.get(key).map_or(0, Vec::len) < writers.len());
+        let (key, writers, preceding) = match next_key {
+            None => return true,
+            Some(next_key) => next_key,
+        };
+        for &writer in writers {
+            let placed = orders.get(key).map_or(&[][..], Vec::as_slice);
+            let is_installable = !placed.contains(&writer)
+                && preceding
+                    .iter()
+                    .all(|&(before, after)| after != writer || placed.contains(&before));
+            if !is_installable {
+                continue;
+            }
+            orders.entry(key).or_default().push(writer);
+            match self.forbidden_cycle(reads, orders) {
+                Some(violation) => {
+                    first_violation.get_or_insert(violation);
+                }
+                None => {
+                    if self.order_versions(reads, keys, orders, first_violation) {
+                        return true;
+                    }
+                }
+            }
+            orders.get_mut(key).unwrap().pop();
+        }
+        false
+    }
+
+    /// Identifies the version observed by each read of a key that the reading transaction had
+    /// not yet written, or the anomaly that prevents doing so.
+    fn external_reads(&self) -> Result<Vec<ExternalRead<'_, K>>, IsolationViolation> {
+        // Maps each committed write to its transaction and whether it is the transaction's final
+        // write to the key.
+        let mut committed_writes: BTreeMap<(&K, &V), (usize, bool)> = BTreeMap::new();
+        for (txn, (_, ops)) in self.committed.iter().enumerate() {
+            let mut last_by_key: BTreeMap<&K, &V> = BTreeMap::new();
+            for op in ops {
+                if let TxnOp::Write(key, value) = op {
+                    if let Some(prev) = last_by_key.insert(key, value) {
+                        committed_writes.insert((key, prev), (txn, false));
+                    }
+                    committed_writes.insert((key, value), (txn, true));
+                }
+            }
+        }
+        let aborted_writes: BTreeSet<(&K, &V)> = self
+            .aborted
+            .iter()
+            .flat_map(|(_, ops)| ops)
+            .filter_map(|op| match op {
+                TxnOp::Write(key, value) => Some((key, value)),
+                TxnOp::Read(..) => None,
+            })
+            .collect();
+
+        let mut reads = Vec::new();
+        for (txn, (_, ops)) in self.committed.iter().enumerate() {
+            let mut own_writes: BTreeMap<&K, &V> = BTreeMap::new();
+            for op in ops {
+                let (key, observed) = match op {
+                    TxnOp::Write(key, value) => {
+                        own_writes.insert(key, value);
+                        continue;
+                    }
+                    TxnOp::Read(key, observed) => (key, observed.as_ref()),
+                };
+                if let Some(&own) = own_writes.get(key) {
+                    if observed != Some(own) {
+                        return Err(self.violation(
+                            Anomaly::Internal,
+                            format!(
+                                "T{} read {:?} = {:?} after writing {:?}.",
+                                txn, key, observed, own
+                            ),
+                            [txn],
+                        ));
+                    }
+                    continue;
+                }
+                let value = match observed {
+                    None => {
+                        reads.push(ExternalRead {
+                            txn,
+                            key,
+                            writer: None,
+                        });
+                        continue;
+                    }
+                    Some(value) => value,
+                };
+                match committed_writes.get(&(key, value)) {
+                    Some(&(writer, _)) if writer == txn => {
+                        return Err(self.violation(
+                            Anomaly::Internal,
+                            format!("T{} read {:?} = {:?} before writing it.", txn, key, value),
+                            [txn],
+                        ));
+                    }
+                    Some(&(writer, false)) => {
+                        return Err(self.violation(
+                            Anomaly::G1b,
+                            format!(
+                                "T{} read {:?} = {:?}, which T{} overwrote before committing.",
+                                txn, key, value, writer
+                            ),
+                            [writer, txn],
+                        ));
+                    }
+                    Some(&(writer, true)) => reads.push(ExternalRead {
+                        txn,
+                        key,
+                        writer: Some(writer),
+                    }),
+                    None => {
+                        let source = if aborted_writes.contains(&(key, value)) {
+                            "an aborted transaction"
+                        } else {
+                            "no transaction"
+                        };
+                        return Err(self.violation(
+                            Anomaly::G1a,
+                            format!(
+                                "T{} read {:?} = {:?}, which {} wrote.",
+                                txn, key, value, source
+                            ),
+                            [txn],
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(reads)
+    }
+
+    /// Finds two transactions that read the same version of a key and then both wrote it.
+    fn lost_update(&self, reads: &[ExternalRead<K>]) -> Option<IsolationViolation> {
+        let mut readers_by_version: BTreeMap<(&K, Option<usize>), BTreeSet<usize>> =
+            BTreeMap::new();
+        for read in reads {
+            let (_, ops) = &self.committed[read.txn];
+            let writes_key = ops
+                .iter()
+                .any(|op| matches!(op, TxnOp::Write(key, _) if key == read.key));
+            if writes_key {
+                readers_by_version
+                    .entry((read.key, read.writer))
+                    .or_default()
+                    .insert(read.txn);
+            }
+        }
+        for ((key, writer), readers) in readers_by_version {
+            let mut readers = readers.into_iter();
+            if let (Some(first), Some(second)) = (readers.next(), readers.next()) {
+                let version = match writer {
+                    None => "the initial version".to_string(),
+                    Some(writer) => format!("the version written by T{}", writer),
+                };
+                return Some(self.violation(
+                    Anomaly::LostUpdate,
+                    format!(
+                        "T{} and T{} both read {} of {:?} and then both wrote {:?}.",
+                        first, second, version, key, key
+                    ),
+                    [first, second],
+                ));
+            }
+        }
+        None
+    }
+
+    /// Builds the dependency graph for the specified version orders and returns the most severe
+    /// cycle that the isolation level forbids. An order may list only the earliest versions of a
+    /// key, in which case only the dependencies that they already determine are included.
+    fn forbidden_cycle(
+        &self,
+        reads: &[ExternalRead<K>],
+        orders: &BTreeMap<&K, Vec<usize>>,
+    ) -> Option<IsolationViolation> {
+        let mut edges: Vec<Vec<Edge<K>>> = vec![Vec::new(); self.committed.len()];
+        for (&key, order) in orders {
+            for pair in order.windows(2) {
+                edges[pair[0]].push((pair[0], Dep::Ww, pair[1], key));
+            }
+        }
+        for read in reads {
+            let order = orders.get(read.key).map_or(&[][..], Vec::as_slice);
+            let next = match read.writer {
+                None => order.first(),
+                Some(writer) => {
+                    edges[writer].push((writer, Dep::Wr, read.txn, read.key));
+                    order
+                        .iter()
+                        .position(|&t| t == writer)
+                        .and_then(|i| order.get(i + 1))
+                }
+            };
+            if let Some(&next) = next {
+                if next != read.txn {
+                    edges[read.txn].push((read.txn, Dep::Rw, next, read.key));
+                }
+            }
+        }
+
+        if let Some(cycle) = find_cycle(&edges, &[Dep::Ww], false) {
+            return Some(self.cycle_violation(Anomaly::G0, &cycle));
+        }
+        if let Some(cycle) = find_cycle(&edges, &[Dep::Ww, Dep::Wr], false) {
+            return Some(self.cycle_violation(Anomaly::G1c, &cycle));
+        }
+        if self.level == IsolationLevel::ReadCommitted {
+            return None;
+        }
+        let all = [Dep::Ww, Dep::Wr, Dep::Rw];
+        if let Some(cycle) = find_cycle(&edges, &all, true) {
+            let anti_dependencies = cycle.iter().filter(|e| e.1 == Dep::Rw).count();
+            let anomaly = if anti_dependencies == 1 {
+                Anomaly::GSingle
+            } else {
+                Anomaly::G2
+            };
+            return Some(self.cycle_violation(anomaly, &cycle));
+        }
+        if self.level == IsolationLevel::SnapshotIsolation {
+            return None;
+        }
+        find_cycle(&edges, &all, false).map(|cycle| self.cycle_violation(Anomaly::G2, &cycle))
+    }
+
+    fn cycle_violation(&self, anomaly: Anomaly, cycle: &[Edge<K>]) -> IsolationViolation {
+        let steps: Vec<String> = cycle
+            .iter()
+            .map(|&(src, dep, dst, key)| match dep {
+                Dep::Ww => format!("T{} overwrote T{}'s write of {:?}", dst, src, key),
+                Dep::Wr => format!("T{} read T{}'s write of {:?}", dst, src, key),
+                Dep::Rw => format!("T{} did not observe T{}'s write of {:?}", src, dst, key),
+            })
+            .collect();
+        let mut explanation = steps.join(", and ");
+        explanation.push('.');
+        self.violation(anomaly, explanation, cycle.iter().map(|e| e.0))
+    }
+
+    /// Appends the specified transactions to an explanation.
+    fn violation(
+        &self,
+        anomaly: Anomaly,
+        mut explanation: String,
+        txns: impl IntoIterator<Item = usize>,
+    ) -> IsolationViolation {
+        for txn in txns.into_iter().collect::<BTreeSet<_>>() {
+            let (thread_id, ops) = &self.committed[txn];
+            explanation.push_str(&format!(
+                " T{} is {:?} on thread {:?}.",
+                txn, ops, thread_id
+            ));
+        }
+        IsolationViolation {
+            anomaly,
+            explanation,
+        }
+    }
+}
+
+/// Returns a shortest cycle comprising the allowed kinds of dependencies. If `separate_rw` is
+/// set, then the cycle must not have two consecutive anti-dependencies, including the last and
+/// first edges.
+fn find_cycle<'a, K>(
+    edges: &[Vec<Edge<'a, K>>],
+    allowed: &[Dep],
+    separate_rw: bool,
+) -> Option<Vec<Edge<'a, K>>> {
+    // Searches states pairing a transaction with whether it was reached via an
+    // anti-dependency. A cycle must return to its starting state.
+    let start_flags: &[bool] = if separate_rw {
+        &[false, true]
+    } else {
+        &[false]
+    };
+    for start in 0..edges.len() {
+        for &start_flag in start_flags {
+            type State = (usize, bool);
+            let mut parents: BTreeMap<State, (State, Edge<'a, K>)> = BTreeMap::new();
+            let mut pending = VecDeque::from([(start, start_flag)]);
+            while let Some((txn, flag)) = pending.pop_front() {
+                for &edge in &edges[txn] {
+                    let (_, dep, dst, _) = edge;
+                    if !allowed.contains(&dep) || (separate_rw && flag && dep == Dep::Rw) {
+                        continue;
+                    }
+                    let next = (dst, separate_rw && dep == Dep::Rw);
+                    if next == (start, start_flag) {
+                        let mut cycle = vec![edge];
+                        let mut state = (txn, flag);
+                        while state != (start, start_flag) {
+                            let (prev, edge) = parents[&state];
+                            cycle.push(edge);
+                            state = prev;
+                        }
+                        cycle.reverse();
+                        return Some(cycle);
+                    }
+                    if dst != start && !parents.contains_key(&next) {
+                        parents.insert(next, ((txn, flag), edge));
+                        pending.push_back(next);
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use TxnOp::*;
+
+    fn anomaly(level: IsolationLevel, committed: Vec<Vec<TxnOp<char, u8>>>) -> Option<Anomaly> {
+        let mut tester = IsolationTester::new(level);
+        for (thread_id, ops) in committed.into_iter().enumerate() {
+            tester.on_commit(thread_id, ops);
+        }
+        tester.anomaly().map(|v| v.anomaly)
+    }
+
+    #[test]
+    fn accepts_serial_history() {
+        let history = vec![
+            vec![Read('x', None), Write('x', 1), Write('y', 1)],
+            vec![Read('x', Some(1)), Read('y', Some(1)), Write('x', 2)],
+            vec![Read('x', Some(2)), Read('y', Some(1))],
+        ];
+        assert_eq!(anomaly(IsolationLevel::Serializable, history), None);
+    }
+
+    #[test]
+    fn rejects_reads_that_do_not_observe_committed_writes() {
+        let mut tester = IsolationTester::new(IsolationLevel::ReadCommitted);
+        tester
+            .on_abort(0, vec![Write('x', 1)])
+            .on_commit(1, vec![Read('x', Some(1))]);
+        assert_eq!(
+            tester.anomaly(),
+            Some(IsolationViolation {
+                anomaly: Anomaly::G1a,
+                explanation: "T0 read 'x' = 1, which an aborted transaction wrote. \
+                              T0 is [Read('x', Some(1))] on thread 1."
+                    .to_string(),
+            })
+        );
+
+        assert_eq!(
+            anomaly(
+                IsolationLevel::ReadCommitted,
+                vec![vec![Write('x', 1), Write('x', 2)], vec![Read('x', Some(1))]],
+            ),
+            Some(Anomaly::G1b)
+        );
+        assert_eq!(
+            anomaly(
+                IsolationLevel::ReadCommitted,
+                vec![vec![Write('x', 1), Read('x', None)]],
+            ),
+            Some(Anomaly::Internal)
+        );
+    }
+
+    #[test]
+    fn rejects_dependency_cycles_at_every_level() {
+        // Each transaction observes the other's write.
+        let history = vec![
+            vec![Write('x', 1), Read('y', Some(2))],
+            vec![Write('y', 2), Read('x', Some(1))],
+        ];
+        assert_eq!(
+            anomaly(IsolationLevel::ReadCommitted, history),
+            Some(Anomaly::G1c)
+        );
+
+        // Writes to both keys were installed in conflicting orders, as a third transaction
+        // observed the final versions.
+        let history = vec![
+            vec![Write('x', 1), Write('y', 1)],
+            vec![Write('x', 2), Write('y', 2)],
+            vec![Read('x', Some(1)), Read('y', Some(2))],
+        ];
+        assert_eq!(
+            anomaly(IsolationLevel::ReadCommitted, history.clone()),
+            None
+        );
+        assert_eq!(
+            anomaly(IsolationLevel::SnapshotIsolation, history),
+            Some(Anomaly::GSingle)
+        );
+    }
+
+    #[test]
+    fn distinguishes_snapshot_isolation_from_serializability() {
+        let lost_update = vec![
+            vec![Read('x', None), Write('x', 1)],
+            vec![Read('x', None), Write('x', 2)],
+        ];
+        assert_eq!(
+            anomaly(IsolationLevel::ReadCommitted, lost_update.clone()),
+            None
+        );
+        assert_eq!(
+            anomaly(IsolationLevel::SnapshotIsolation, lost_update),
+            Some(Anomaly::LostUpdate)
+        );
+
+        let write_skew = vec![
+            vec![Read('x', None), Read('y', None), Write('x', 1)],
+            vec![Read('x', None), Read('y', None), Write('y', 2)],
+        ];
+        assert_eq!(
+            anomaly(IsolationLevel::SnapshotIsolation, write_skew.clone()),
+            None
+        );
+        let mut tester = IsolationTester::new(IsolationLevel::Serializable);
+        tester
+            .on_commit(0, write_skew[0].clone())
+            .on_commit(1, write_skew[1].clone());
+        assert_eq!(
+            tester.anomaly().unwrap().to_string(),
+            "G2 (anti-dependency cycle): T0 did not observe T1's write of 'y', and \
+             T1 did not observe T0's write of 'x'. \
+             T0 is [Read('x', None), Read('y', None), Write('x', 1)] on thread 0. \
+             T1 is [Read('x', None), Read('y', None), Write('y', 2)] on thread 1."
+        );
+    }
+
+    #[test]
+    fn narrows_version_orders_for_many_writers() {
+        // Each transaction overwrites the version of 'x' that it read, which determines the order
+        // of 'x', while the order of the blind writes to 'y' is only constrained by the cycles
+        // that each order would form.
+        let mut history: Vec<Vec<TxnOp<char, u8>>> = (0..10)
+            .map(|i| {
+                let observed = if i == 0 { None } else { Some(i - 1) };
+                vec![Read('x', observed), Write('x', i), Write('y', i)]
+            })
+            .collect();
+        history.push(vec![Read('x', Some(9)), Read('y', Some(9))]);
+        assert_eq!(anomaly(IsolationLevel::Serializable, history.clone()), None);
+
+        // Reading a stale version of 'y' is only forbidden by stronger levels.
+        *history.last_mut().unwrap() = vec![Read('x', Some(9)), Read('y', Some(0))];
+        assert_eq!(
+            anomaly(IsolationLevel::ReadCommitted, history.clone()),
+            None
+        );
+        assert_eq!(
+            anomaly(IsolationLevel::Serializable, history),
+            Some(Anomaly::GSingle)
+        );
+    }
+}
@@ -0,0 +1,114 @@
+//! Implements [`SequentialSpec`] for [`KeyValue`] operational semantics.
+
+use super::{PartitionedSpec, SequentialSpec};
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+/// A key-value store used to define reference operational semantics via [`SequentialSpec`].
+/// Each key behaves like an independent [`Register`], so the store also implements
+/// [`PartitionedSpec`].
+///
+/// [`Register`]: crate::semantics::register::Register
+#[derive(Clone, Default, Debug, Hash, PartialEq, serde::Serialize)]
+pub struct KeyValue<K, V>(pub BTreeMap<K, V>);
+
+/// An operation that can be invoked upon a [`KeyValue`] store, resulting in a
+/// [`KeyValueRet`].
+#[derive(Clone, Debug, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum KeyValueOp<K, V> {
+    Put(K, V),
+    Get(K),
+}
+
+/// A return value for a [`KeyValueOp`] invoked upon a [`KeyValue`] store.
+#[derive(Clone, Debug, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum KeyValueRet<V> {
+    PutOk,
+    GetOk(Option<V>),
+}
+
+impl<K, V> SequentialSpec for KeyValue<K, V>
+where
+    K: Clone + Debug + Ord,
+    V: Clone + Debug + PartialEq,
+{
+    type Op = KeyValueOp<K, V>;
+    type Ret = KeyValueRet<V>;
+    fn invoke(&mut self, op: &Self::Op) -> Self::Ret {
+        match op {
+            KeyValueOp::Put(k, v) => {
+                self.0.insert(k.clone(), v.clone());
+                KeyValueRet::PutOk
+            }
+            KeyValueOp::Get(k) => KeyValueRet::GetOk(self.0.get(k).cloned()),
+        }
+    }
+    fn is_valid_step(&mut self, op: &Self::Op, ret: &Self::Ret) -> bool {
+        // Override to avoid unnecessary `clone` on `Get`.
+        match (op, ret) {
+            (KeyValueOp::Put(k, v), KeyValueRet::PutOk) => {
+                self.0.insert(k.clone(), v.clone());
+                true
+            }
+            (KeyValueOp::Get(k), KeyValueRet::GetOk(v)) => self.0.get(k) == v.as_ref(),
+            _ => false,
+        }
+    }
+}
+
+impl<K, V> PartitionedSpec for KeyValue<K, V>
+where
+    K: Clone + Debug + Ord,
+    V: Clone + Debug + PartialEq,
+{
+    type Key = K;
+    fn partition(op: &Self::Op) -> K {
+        match op {
+            KeyValueOp::Put(k, _) | KeyValueOp::Get(k) => k.clone(),
+        }
+    }
+    fn project(&self, key: &K) -> Self {
+        KeyValue(
+            self.0
+                .get_key_value(key)
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .into_iter()
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+#[rustfmt::skip]
+mod test {
+    use super::*;
+
+    #[test]
+    fn models_expected_semantics() {
+        let mut kv = KeyValue(BTreeMap::from([('x', 1)]));
+        assert_eq!(kv.invoke(&KeyValueOp::Get('x')),    KeyValueRet::GetOk(Some(1)));
+        assert_eq!(kv.invoke(&KeyValueOp::Get('y')),    KeyValueRet::GetOk(None));
+        assert_eq!(kv.invoke(&KeyValueOp::Put('y', 2)), KeyValueRet::PutOk);
+        assert_eq!(kv.invoke(&KeyValueOp::Get('y')),    KeyValueRet::GetOk(Some(2)));
+        assert_eq!(kv.project(&'y'), KeyValue(BTreeMap::from([('y', 2)])));
+    }
+
+    #[test]
+    fn accepts_valid_histories() {
+        assert!(KeyValue::<char, u8>::default().is_valid_history(vec![]));
+        assert!(KeyValue::default().is_valid_history(vec![
+            (KeyValueOp::Get('x'),    KeyValueRet::GetOk(None)),
+            (KeyValueOp::Put('x', 1), KeyValueRet::PutOk),
+            (KeyValueOp::Put('y', 2), KeyValueRet::PutOk),
+            (KeyValueOp::Get('x'),    KeyValueRet::GetOk(Some(1))),
+        ]));
+    }
+
+    #[test]
+    fn rejects_invalid_histories() {
+        assert!(!KeyValue::default().is_valid_history(vec![
+            (KeyValueOp::Put('x', 1), KeyValueRet::PutOk),
+            (KeyValueOp::Get('y'),    KeyValueRet::GetOk(Some(1))),
+        ]));
+    }
+}
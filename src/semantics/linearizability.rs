@@ -1,8 +1,10 @@
 //! Private module for selective re-export. See [`LinearizabilityTester`].
 
-use crate::semantics::{ConsistencyTester, SequentialSpec};
-use std::collections::{btree_map, BTreeMap, VecDeque};
+use crate::semantics::{ConsistencyTester, PartitionedSpec, SequentialSpec};
+use crate::{fingerprint, Fingerprint};
+use std::collections::{btree_map, BTreeMap, BTreeSet, HashMap, VecDeque};
 use std::fmt::Debug;
+use std::hash::Hash;
 
 // This implementation is based on `SequentialConsistencyTester` and will be
 // easier to follow if you are already familiar with that code. The key
@@ -10,6 +12,10 @@ use std::fmt::Debug;
 // the index of the last operation completed by every other thread, and those
 // same indices are also preserved if/when the operation completes. That data
 // allows the tester to reject histories that violate "real time" ordering.
+//
+// The search follows Wing & Gong with Lowe's memoization: the operations serialized so far are
+// always a prefix of each thread's history, so the prefix lengths together with the reference
+// object identify a search state, and a state that failed once is not explored again.
 
 /// This tester captures a potentially concurrent history of operations and
 /// validates that it adheres to a [`SequentialSpec`] based on the
@@ -47,6 +53,10 @@ use std::fmt::Debug;
 /// then the responsibility of this tester to establish whether a valid total ordering of events
 /// exists under these constraints.
 ///
+/// Linearizability is also compositional, so if the reference object is a [`PartitionedSpec`]
+/// (such as a key-value store), then the history of each partition can be checked separately
+/// via [`LinearizabilityTester::serialized_history_by_partition`].
+///
 /// See also: [`SequentialConsistencyTester`].
 ///
 /// [linearizability]: https://en.wikipedia.org/wiki/Linearizability
@@ -64,6 +74,9 @@ pub struct LinearizabilityTester<ThreadId, RefObj: SequentialSpec> {
 type LastCompletedOpMap<ThreadId> = BTreeMap<ThreadId, usize>;
 type Complete<ThreadId, Op, Ret> = (LastCompletedOpMap<ThreadId>, Op, Ret);
 type InFlight<ThreadId, Op> = (LastCompletedOpMap<ThreadId>, Op);
+/// An operation that can be serialized once each listed peer has serialized the specified
+/// number of operations. Lacks a return value if still in flight.
+type Candidate<'a, Op, Ret> = (Vec<(usize, usize)>, &'a Op, Option<&'a Ret>);
 
 #[allow(clippy::len_without_is_empty)] // no use case for an emptiness check
 impl<T: Ord, RefObj: SequentialSpec> LinearizabilityTester<T, RefObj> {
@@ -91,7 +104,7 @@ impl<T: Ord, RefObj: SequentialSpec> LinearizabilityTester<T, RefObj> {
 impl<T, RefObj> ConsistencyTester<T, RefObj> for LinearizabilityTester<T, RefObj>
 where
     T: Copy + Debug + Ord,
-    RefObj: Clone + SequentialSpec,
+    RefObj: Clone + Hash + PartialEq + SequentialSpec,
     RefObj::Op: Clone + Debug,
     RefObj::Ret: Clone + Debug + PartialEq,
 {
@@ -165,7 +178,7 @@ where
 impl<T, RefObj> LinearizabilityTester<T, RefObj>
 where
     T: Copy + Debug + Ord,
-    RefObj: Clone + SequentialSpec,
+    RefObj: Clone + Hash + PartialEq + SequentialSpec,
     RefObj::Op: Clone + Debug,
     RefObj::Ret: Clone + Debug + PartialEq,
 {
@@ -176,107 +189,199 @@ where
         if !self.is_valid_history {
             return None;
         }
-        let history_by_thread = self
+        let thread_index: BTreeMap<T, usize> = self
+            .history_by_thread
+            .keys()
+            .enumerate()
+            .map(|(i, t)| (*t, i))
+            .collect();
+        let prereqs = |cs: &LastCompletedOpMap<T>| -> Vec<(usize, usize)> {
+            cs.iter()
+                .map(|(peer_id, last)| (thread_index[peer_id], last + 1))
+                .collect()
+        };
+        let candidates_by_thread: Vec<Vec<Candidate<RefObj::Op, RefObj::Ret>>> = self
             .history_by_thread
             .iter()
-            .map(|(t, cs)| (*t, cs.clone().into_iter().enumerate().collect()))
+            .map(|(thread_id, history)| {
+                let mut candidates: Vec<_> = history
+                    .iter()
+                    .map(|(cs, op, ret)| (prereqs(cs), op, Some(ret)))
+                    .collect();
+                if let Some((cs, op)) = self.in_flight_by_thread.get(thread_id) {
+                    candidates.push((prereqs(cs), op, None));
+                }
+                candidates
+            })
             .collect();
-        Self::serialize(
-            Vec::new(),
+        let completed_by_thread: Vec<usize> =
+            self.history_by_thread.values().map(|h| h.len()).collect();
+
+        let mut valid_history = Vec::new();
+        let found = Self::serialize(
+            &candidates_by_thread,
+            &completed_by_thread,
+            &mut vec![0; candidates_by_thread.len()],
             &self.init_ref_obj,
-            &history_by_thread,
-            &self.in_flight_by_thread,
-        )
+            &mut valid_history,
+            &mut HashMap::new(),
+        );
+        found.then_some(valid_history)
     }
 
-    #[allow(clippy::type_complexity)]
+    /// Extends a total order one operation at a time, backtracking as needed. Tracks the
+    /// serialized prefix of each thread's history, which together with the reference object
+    /// summarizes the search state. States that failed are keyed by fingerprint but compared in
+    /// full, as a fingerprint collision would otherwise prune a state that was never searched.
     fn serialize(
-        valid_history: Vec<(RefObj::Op, RefObj::Ret)>, // total order
+        candidates_by_thread: &[Vec<Candidate<RefObj::Op, RefObj::Ret>>], // partial order
+        completed_by_thread: &[usize],
+        serialized_by_thread: &mut Vec<usize>,
         ref_obj: &RefObj,
-        remaining_history_by_thread: &BTreeMap<
-            T,
-            VecDeque<(usize, Complete<T, RefObj::Op, RefObj::Ret>)>,
-        >, // partial order
-        in_flight_by_thread: &BTreeMap<T, InFlight<T, RefObj::Op>>,
-    ) -> Option<Vec<(RefObj::Op, RefObj::Ret)>> {
-        // Return collected total order when there is no remaining partial order to interleave.
-        let done = remaining_history_by_thread
+        valid_history: &mut Vec<(RefObj::Op, RefObj::Ret)>, // total order
+        failed: &mut HashMap<Fingerprint, Vec<(Vec<usize>, RefObj)>>,
+    ) -> bool {
+        // In-flight operations need not be serialized, as they may not have taken effect.
+        let done = serialized_by_thread
             .iter()
-            .all(|(_id, h)| h.is_empty());
+            .zip(completed_by_thread)
+            .all(|(serialized, completed)| serialized >= completed);
         if done {
-            return Some(valid_history);
+            return true;
         }
+        let same_fingerprint = failed
+            .entry(fingerprint(&(&*serialized_by_thread, ref_obj)))
+            .or_default();
+        if same_fingerprint.iter().any(|(serialized, failed_obj)| {
+            serialized == serialized_by_thread && failed_obj == ref_obj
+        }) {
+            return false;
+        }
+        same_fingerprint.push((serialized_by_thread.clone(), ref_obj.clone()));
 
-        // Otherwise try remaining interleavings.
-        for (thread_id, remaining_history) in remaining_history_by_thread.iter() {
-            let mut remaining_history_by_thread =
-                std::borrow::Cow::Borrowed(remaining_history_by_thread);
-            let mut in_flight_by_thread = std::borrow::Cow::Borrowed(in_flight_by_thread);
-            let (ref_obj, valid_history) = if remaining_history.is_empty() {
-                // Case 1: No remaining history to interleave. Maybe in-flight.
-                if !in_flight_by_thread.contains_key(thread_id) {
-                    continue;
-                }
-                let (cs, op) = in_flight_by_thread.to_mut().remove(thread_id).unwrap(); // `contains_key` above
-                let violation = cs.iter().any(|(peer_id, min_peer_time)| {
-                    // Ensure all pre-req operations were completed by peers
-                    if let Some(ops) = remaining_history_by_thread.get(peer_id) {
-                        if let Some((next_peer_time, _)) = ops.iter().next() {
-                            if next_peer_time <= min_peer_time {
-                                return true;
-                            }
+        for (thread, candidates) in candidates_by_thread.iter().enumerate() {
+            let (prereqs, op, ret) = match candidates.get(serialized_by_thread[thread]) {
+                None => continue,
+                Some(candidate) => candidate,
+            };
+            // Ensure all pre-req operations were completed by peers
+            if prereqs
+                .iter()
+                .any(|&(peer, count)| serialized_by_thread[peer] < count)
+            {
+                continue;
+            }
+            let mut ref_obj = ref_obj.clone();
+            let ret = match ret {
+                Some(ret) if ref_obj.is_valid_step(op, ret) => (*ret).clone(),
+                Some(_) => continue,
+                None => ref_obj.invoke(op),
+            };
+            serialized_by_thread[thread] += 1;
+            valid_history.push(((*op).clone(), ret));
+            if Self::serialize(
+                candidates_by_thread,
+                completed_by_thread,
+                serialized_by_thread,
+                &ref_obj,
+                valid_history,
+                failed,
+            ) {
+                return true;
+            }
+            valid_history.pop();
+            serialized_by_thread[thread] -= 1;
+        }
+        false
+    }
+}
+
+impl<T, RefObj> LinearizabilityTester<T, RefObj>
+where
+    T: Copy + Debug + Ord,
+    RefObj: Clone + Hash + PartialEq + PartitionedSpec,
+    RefObj::Op: Clone + Debug,
+    RefObj::Ret: Clone + Debug + PartialEq,
+{
+    /// Splits the recorded history into a separate tester for each partition of the reference
+    /// object, such as each key of a [`KeyValue`] store. Each tester includes the operations
+    /// acting on that partition, along with the real time ordering between them.
+    ///
+    /// [`KeyValue`]: crate::semantics::key_value::KeyValue
+    pub fn partitions(&self) -> BTreeMap<RefObj::Key, Self> {
+        let mut keys = BTreeSet::new();
+        for history in self.history_by_thread.values() {
+            keys.extend(history.iter().map(|(_, op, _)| RefObj::partition(op)));
+        }
+        keys.extend(
+            self.in_flight_by_thread
+                .values()
+                .map(|(_, op)| RefObj::partition(op)),
+        );
+
+        keys.into_iter()
+            .map(|key| {
+                // For each thread, the number of operations acting on the partition among the
+                // first `i` operations, so that indices can be remapped.
+                let kept_before: BTreeMap<T, Vec<usize>> = self
+                    .history_by_thread
+                    .iter()
+                    .map(|(t, history)| {
+                        let mut kept = vec![0];
+                        for (_, op, _) in history {
+                            let n = *kept.last().unwrap();
+                            kept.push(n + usize::from(RefObj::partition(op) == key));
                         }
+                        (*t, kept)
+                    })
+                    .collect();
+                let remap = |cs: &LastCompletedOpMap<T>| -> LastCompletedOpMap<T> {
+                    cs.iter()
+                        .filter_map(|(peer_id, last)| {
+                            let kept = kept_before[peer_id][last + 1];
+                            (kept > 0).then(|| (*peer_id, kept - 1))
+                        })
+                        .collect()
+                };
+
+                let mut tester = Self::new(self.init_ref_obj.project(&key));
+                for (thread_id, history) in &self.history_by_thread {
+                    let history: VecDeque<_> = history
+                        .iter()
+                        .filter(|(_, op, _)| RefObj::partition(op) == key)
+                        .map(|(cs, op, ret)| (remap(cs), op.clone(), ret.clone()))
+                        .collect();
+                    if !history.is_empty() {
+                        tester.history_by_thread.insert(*thread_id, history);
                     }
-                    false
-                });
-                if violation {
-                    continue;
                 }
-                let mut ref_obj = ref_obj.clone();
-                let ret = ref_obj.invoke(&op);
-                let mut valid_history = valid_history.clone();
-                valid_history.push((op, ret));
-                (ref_obj, valid_history)
-            } else {
-                // Case 2: Has remaining history to interleave.
-                let (_t, (cs, op, ret)) = remaining_history_by_thread
-                    .to_mut()
-                    .get_mut(thread_id)
-                    .unwrap() // iterator returned this thread ID
-                    .pop_front()
-                    .unwrap(); // `!is_empty()` above
-                let violation = cs.iter().any(|(peer_id, min_peer_time)| {
-                    // Ensure all pre-req operations were completed by peers
-                    if let Some(ops) = remaining_history_by_thread.get(peer_id) {
-                        if let Some((next_peer_time, _)) = ops.iter().next() {
-                            if next_peer_time <= min_peer_time {
-                                return true;
-                            }
-                        }
+                for (thread_id, (cs, op)) in &self.in_flight_by_thread {
+                    if RefObj::partition(op) == key {
+                        tester
+                            .in_flight_by_thread
+                            .insert(*thread_id, (remap(cs), op.clone()));
+                        tester.history_by_thread.entry(*thread_id).or_default();
                     }
-                    false
-                });
-                if violation {
-                    continue;
                 }
-                let mut ref_obj = ref_obj.clone();
-                if !ref_obj.is_valid_step(&op, &ret) {
-                    continue;
-                }
-                let mut valid_history = valid_history.clone();
-                valid_history.push((op, ret));
-                (ref_obj, valid_history)
-            };
-            if let Some(valid_history) = Self::serialize(
-                valid_history,
-                &ref_obj,
-                &remaining_history_by_thread,
-                &in_flight_by_thread,
-            ) {
-                return Some(valid_history);
-            }
+                (key, tester)
+            })
+            .collect()
+    }
+
+    /// Attempts to serialize the recorded history of each partition separately, which is
+    /// equivalent to [`LinearizabilityTester::serialized_history`] since linearizability is
+    /// compositional but typically much faster.
+    #[allow(clippy::type_complexity)]
+    pub fn serialized_history_by_partition(
+        &self,
+    ) -> Option<BTreeMap<RefObj::Key, Vec<(RefObj::Op, RefObj::Ret)>>> {
+        if !self.is_valid_history {
+            return None;
         }
-        None
+        self.partitions()
+            .into_iter()
+            .map(|(key, tester)| Some((key, tester.serialized_history()?)))
+            .collect()
     }
 }
 
@@ -506,4 +611,86 @@ mod test {
         );
         Ok(())
     }
+
+    #[test]
+    fn checks_partitions_separately() -> Result<(), String> {
+        use crate::semantics::key_value::*;
+        let mut tester = LinearizabilityTester::new(KeyValue::default());
+        tester
+            .on_invret(0, KeyValueOp::Put('x', 1), KeyValueRet::PutOk)?
+            .on_invoke(1, KeyValueOp::Put('y', 2))?
+            .on_invret(2, KeyValueOp::Get('y'), KeyValueRet::GetOk(Some(2)))?
+            .on_invret(2, KeyValueOp::Get('x'), KeyValueRet::GetOk(Some(1)))?;
+        assert_eq!(
+            tester.serialized_history_by_partition(),
+            Some(BTreeMap::from([
+                (
+                    'x',
+                    vec![
+                        (KeyValueOp::Put('x', 1), KeyValueRet::PutOk),
+                        (KeyValueOp::Get('x'), KeyValueRet::GetOk(Some(1))),
+                    ]
+                ),
+                (
+                    'y',
+                    vec![
+                        (KeyValueOp::Put('y', 2), KeyValueRet::PutOk),
+                        (KeyValueOp::Get('y'), KeyValueRet::GetOk(Some(2))),
+                    ]
+                ),
+            ]))
+        );
+        assert!(tester.is_consistent());
+
+        // Real time ordering is preserved across partitions: thread 2's read of `x` starts after
+        // thread 0's write returned.
+        tester.on_invret(2, KeyValueOp::Get('x'), KeyValueRet::GetOk(None))?;
+        assert_eq!(tester.serialized_history_by_partition(), None);
+        assert!(!tester.is_consistent());
+        Ok(())
+    }
+
+    #[test]
+    fn handles_many_concurrent_threads() -> Result<(), String> {
+        // Every thread writes concurrently and then reads the last value written, which
+        // requires considering many interleavings.
+        let mut tester = LinearizabilityTester::new(Register(0));
+        for t in 1..=8 {
+            tester.on_invoke(t, RegisterOp::Write(t))?;
+        }
+        for t in 1..=8 {
+            tester.on_return(t, RegisterRet::WriteOk)?;
+        }
+        for t in 1..=8 {
+            tester.on_invret(t, RegisterOp::Read, RegisterRet::ReadOk(3))?;
+        }
+        assert!(tester.is_consistent());
+        tester.on_invret(1, RegisterOp::Read, RegisterRet::ReadOk(4))?;
+        assert!(!tester.is_consistent());
+        Ok(())
+    }
+
+    #[test]
+    fn checks_offline_history() -> Result<(), String> {
+        use crate::semantics::key_value::*;
+        use crate::semantics::HistoryEvent;
+        let events: Vec<HistoryEvent<u64, KeyValueOp<String, u64>, KeyValueRet<u64>>> =
+            serde_json::from_str(
+                r#"[
+                    {"Invoke": [0, {"Put": ["x", 1]}]},
+                    {"Invoke": [1, {"Get": "x"}]},
+                    {"Return": [0, "PutOk"]},
+                    {"Return": [1, {"GetOk": 1}]},
+                    {"Invoke": [1, {"Get": "x"}]},
+                    {"Return": [1, {"GetOk": null}]}
+                ]"#,
+            )
+            .unwrap();
+        let mut tester = LinearizabilityTester::new(KeyValue::default());
+        tester.on_history(events.clone().into_iter().take(4))?;
+        assert!(tester.is_consistent());
+        tester.on_history(events.into_iter().skip(4))?;
+        assert!(!tester.is_consistent());
+        Ok(())
+    }
 }
@@ -10,14 +10,14 @@ pub struct Register<T>(pub T);
 
 /// An operation that can be invoked upon a [`Register`], resulting in a
 /// [`RegisterRet`]
-#[derive(Clone, Debug, Hash, PartialEq, serde::Serialize)]
+#[derive(Clone, Debug, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum RegisterOp<T> {
     Write(T),
     Read,
 }
 
 /// A return value for a [`RegisterOp`] invoked upon a [`Register`].
-#[derive(Clone, Debug, Hash, PartialEq, serde::Serialize)]
+#[derive(Clone, Debug, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum RegisterRet<T> {
     WriteOk,
     ReadOk(T),
@@ -0,0 +1,242 @@
+//! Private module for selective re-export. See [`SessionGuaranteeTester`].
+
+use crate::semantics::causal_consistency::VisibilityTester;
+use crate::semantics::{ConsistencyTester, SequentialSpec};
+use std::collections::BTreeSet;
+use std::fmt::Debug;
+
+/// A [session guarantee] that a [`SessionGuaranteeTester`] verifies. Each thread is a session.
+///
+/// Operations are not classified as reads or writes, so every operation is treated as both. An
+/// operation that does not modify the reference object is unaffected by whether other
+/// operations observe it.
+///
+/// [session guarantee]: https://doi.org/10.1109/PDIS.1994.331722
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, serde::Serialize)]
+pub enum SessionGuarantee {
+    /// Every operation observes the earlier operations in its session.
+    ReadYourWrites,
+    /// Every operation observes whatever the earlier operations in its session observed.
+    MonotonicReads,
+    /// Any operation that observes an operation also observes the earlier operations in that
+    /// operation's session.
+    MonotonicWrites,
+    /// Any operation that observes an operation also observes whatever the earlier operations
+    /// in that operation's session observed.
+    WritesFollowReads,
+}
+
+/// This tester captures a potentially concurrent history of operations and validates that it
+/// adheres to a [`SequentialSpec`] while providing the specified [`SessionGuarantee`]s, which
+/// are weaker than [`CausalConsistencyTester`]'s guarantees. Each guarantee can be verified
+/// separately to pinpoint which one a system violates.
+///
+/// As with `CausalConsistencyTester`, each operation observes a subset of the operations that
+/// precede it in an order common to all threads, and its return value must match the result of
+/// replaying the operations it observes. Operations still in flight may or may not have been
+/// observed.
+///
+/// # Example
+///
+/// ```
+/// use stateright::semantics::*;
+/// use stateright::semantics::register::*;
+///
+/// // The second read does not observe what the first read observed.
+/// let mut tester = SessionGuaranteeTester::new(Register('A'), SessionGuarantee::ReadYourWrites);
+/// tester
+///     .on_invret(0, RegisterOp::Write('B'), RegisterRet::WriteOk).unwrap()
+///     .on_invret(1, RegisterOp::Read, RegisterRet::ReadOk('B')).unwrap()
+///     .on_invret(1, RegisterOp::Read, RegisterRet::ReadOk('A')).unwrap();
+/// assert!(tester.is_consistent());
+/// assert!(!tester.guarantee(SessionGuarantee::MonotonicReads).is_consistent());
+/// ```
+///
+/// [`CausalConsistencyTester`]: crate::semantics::CausalConsistencyTester
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct SessionGuaranteeTester<ThreadId, RefObj: SequentialSpec>(
+    VisibilityTester<ThreadId, RefObj, RefObj::Op, RefObj::Ret>,
+);
+
+#[allow(clippy::len_without_is_empty)] // no use case for an emptiness check
+impl<T: Ord, RefObj: SequentialSpec> SessionGuaranteeTester<T, RefObj> {
+    /// Constructs a [`SessionGuaranteeTester`] that verifies a [`SessionGuarantee`].
+    pub fn new(init_ref_obj: RefObj, guarantee: SessionGuarantee) -> Self {
+        let guarantees = BTreeSet::from([guarantee]);
+        Self(VisibilityTester::new(init_ref_obj, guarantees, false))
+    }
+
+    /// Additionally verifies a [`SessionGuarantee`].
+    pub fn guarantee(&mut self, guarantee: SessionGuarantee) -> &mut Self {
+        self.0.guarantees_mut().insert(guarantee);
+        self
+    }
+
+    /// Indicates the aggregate number of operations completed or in flight
+    /// across all threads.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl<T, RefObj> ConsistencyTester<T, RefObj> for SessionGuaranteeTester<T, RefObj>
+where
+    T: Copy + Debug + Ord,
+    RefObj: Clone + SequentialSpec,
+    RefObj::Op: Clone + Debug,
+    RefObj::Ret: Clone + Debug + PartialEq,
+{
+    /// Indicates that a thread invoked an operation. Returns `Ok(...)` if the
+    /// history is valid, even if it violates a guarantee.
+    ///
+    /// See [`SessionGuaranteeTester::serialized_history`].
+    fn on_invoke(&mut self, thread_id: T, op: RefObj::Op) -> Result<&mut Self, String> {
+        self.0.on_invoke(thread_id, op)?;
+        Ok(self)
+    }
+
+    /// Indicates that a thread's earlier operation invocation returned. Returns
+    /// `Ok(...)` if the history is valid, even if it violates a guarantee.
+    ///
+    /// See [`SessionGuaranteeTester::serialized_history`].
+    fn on_return(&mut self, thread_id: T, ret: RefObj::Ret) -> Result<&mut Self, String> {
+        self.0.on_return(thread_id, ret)?;
+        Ok(self)
+    }
+
+    /// Indicates whether the recorded history provides the guarantees.
+    fn is_consistent(&self) -> bool {
+        self.serialized_history().is_some()
+    }
+}
+
+impl<T, RefObj> SessionGuaranteeTester<T, RefObj>
+where
+    T: Copy + Debug + Ord,
+    RefObj: Clone + SequentialSpec,
+    RefObj::Op: Clone + Debug,
+    RefObj::Ret: Clone + Debug + PartialEq,
+{
+    /// Attempts to order the recorded partially ordered operation history such that every
+    /// operation's return value results from replaying the operations it observes in that
+    /// order. Operations still in flight may or may not be included.
+    pub fn serialized_history(&self) -> Option<Vec<(RefObj::Op, RefObj::Ret)>> {
+        self.0.serialized_history()
+    }
+}
+
+impl<T, RefObj> serde::Serialize for SessionGuaranteeTester<T, RefObj>
+where
+    RefObj: serde::Serialize + SequentialSpec,
+    RefObj::Op: serde::Serialize,
+    RefObj::Ret: serde::Serialize,
+    T: Ord + serde::Serialize,
+{
+    fn serialize<Ser: serde::Serializer>(&self, ser: Ser) -> Result<Ser::Ok, Ser::Error> {
+        self.0.serialize("SessionGuaranteeTester", ser)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::semantics::vec::*;
+    use crate::semantics::CausalConsistencyTester;
+    use SessionGuarantee::*;
+
+    type History = [(u8, VecOp<u8>, VecRet<u8>)];
+
+    fn provides(guarantees: &[SessionGuarantee], history: &History) -> bool {
+        let mut tester = SessionGuaranteeTester::new(Vec::new(), guarantees[0]);
+        for guarantee in guarantees {
+            tester.guarantee(*guarantee);
+        }
+        for (thread_id, op, ret) in history {
+            tester
+                .on_invret(*thread_id, op.clone(), ret.clone())
+                .unwrap();
+        }
+        tester.is_consistent()
+    }
+
+    fn is_causal(history: &History) -> bool {
+        let mut tester = CausalConsistencyTester::new(Vec::new());
+        for (thread_id, op, ret) in history {
+            tester
+                .on_invret(*thread_id, op.clone(), ret.clone())
+                .unwrap();
+        }
+        tester.is_consistent()
+    }
+
+    #[test]
+    fn identifies_read_your_writes_violation() {
+        let history = [
+            (0, VecOp::Push(1), VecRet::PushOk),
+            (0, VecOp::Len, VecRet::LenOk(0)),
+        ];
+        assert!(!provides(&[ReadYourWrites], &history));
+        assert!(provides(
+            &[MonotonicReads, MonotonicWrites, WritesFollowReads],
+            &history
+        ));
+        assert!(!is_causal(&history));
+    }
+
+    #[test]
+    fn identifies_monotonic_reads_violation() {
+        let history = [
+            (0, VecOp::Push(1), VecRet::PushOk),
+            (1, VecOp::Len, VecRet::LenOk(1)),
+            (1, VecOp::Len, VecRet::LenOk(0)),
+        ];
+        assert!(!provides(&[MonotonicReads], &history));
+        assert!(provides(
+            &[ReadYourWrites, MonotonicWrites, WritesFollowReads],
+            &history
+        ));
+        assert!(!is_causal(&history));
+    }
+
+    // The remaining violations only manifest when an operation must observe at least as much as
+    // the operations before it, hence the additional guarantees.
+
+    #[test]
+    fn identifies_monotonic_writes_violation() {
+        let history = [
+            (0, VecOp::Push(1), VecRet::PushOk),
+            (0, VecOp::Push(2), VecRet::PushOk),
+            (1, VecOp::Pop, VecRet::PopOk(Some(2))),
+            (1, VecOp::Len, VecRet::LenOk(0)),
+        ];
+        assert!(!provides(
+            &[ReadYourWrites, MonotonicReads, MonotonicWrites],
+            &history
+        ));
+        assert!(provides(
+            &[ReadYourWrites, MonotonicReads, WritesFollowReads],
+            &history
+        ));
+        assert!(!is_causal(&history));
+    }
+
+    #[test]
+    fn identifies_writes_follow_reads_violation() {
+        let history = [
+            (0, VecOp::Push(1), VecRet::PushOk),
+            (1, VecOp::Len, VecRet::LenOk(1)),
+            (1, VecOp::Push(2), VecRet::PushOk),
+            (2, VecOp::Pop, VecRet::PopOk(Some(2))),
+            (2, VecOp::Len, VecRet::LenOk(0)),
+        ];
+        assert!(!provides(
+            &[ReadYourWrites, MonotonicReads, WritesFollowReads],
+            &history
+        ));
+        assert!(provides(
+            &[ReadYourWrites, MonotonicReads, MonotonicWrites],
+            &history
+        ));
+        assert!(!is_causal(&history));
+    }
+}
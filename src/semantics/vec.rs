@@ -4,7 +4,7 @@ use crate::semantics::SequentialSpec;
 
 /// An operation that can be invoked upon a [`Vec`], resulting in a
 /// [`VecRet`].
-#[derive(Clone, Debug, Hash, PartialEq)]
+#[derive(Clone, Debug, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum VecOp<T> {
     Push(T),
     Pop,
@@ -12,7 +12,7 @@ pub enum VecOp<T> {
 }
 
 /// A return value for a [`VecOp`] invoked upon a [`Vec`].
-#[derive(Clone, Debug, Hash, PartialEq)]
+#[derive(Clone, Debug, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum VecRet<T> {
     PushOk,
     PopOk(Option<T>),
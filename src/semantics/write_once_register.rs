@@ -10,14 +10,14 @@ pub struct WORegister<T>(pub Option<T>);
 
 /// An operation that can be invoked upon a [`WORegister`], resulting in a
 /// [`WORegisterRet`]
-#[derive(Clone, Debug, Hash, PartialEq, serde::Serialize)]
+#[derive(Clone, Debug, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum WORegisterOp<T> {
     Write(T),
     Read,
 }
 
 /// A return value for a [`WORegisterOp`] invoked upon a [`WORegister`].
-#[derive(Clone, Debug, Hash, PartialEq, serde::Serialize)]
+#[derive(Clone, Debug, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum WORegisterRet<T> {
     WriteOk,
     WriteFail,
@@ -7,7 +7,7 @@ pub mod binary_clock {
     #[derive(Clone)]
     pub struct BinaryClock;
 
-    #[derive(Clone, Debug, PartialEq)]
+    #[derive(Clone, Debug, Hash, PartialEq)]
     pub enum BinaryClockAction {
         GoLow,
         GoHigh,
@@ -142,13 +142,14 @@ pub mod linear_equation_solver {
 
     /// Given `a`, `b`, and `c`, finds `x` and `y` such that `a*x + b*y = c` where all values are
     /// in `u8`.
+    #[derive(Clone)]
     pub struct LinearEquation {
         pub a: u8,
         pub b: u8,
         pub c: u8,
     }
 
-    #[derive(Clone, Debug, Eq, PartialEq)]
+    #[derive(Clone, Debug, Eq, Hash, PartialEq)]
     pub enum Guess {
         IncreaseX,
         IncreaseY,